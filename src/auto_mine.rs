@@ -0,0 +1,209 @@
+//! Module for mining blocks in the background
+//!
+//! An [`AutoMiner`] is started with [`crate::LocalNet::auto_mine`] and generates blocks on a tokio task until it is
+//! stopped or dropped, so manual and long-running wallet tests receive blocks without calling
+//! [`Validator::generate_blocks`]. Blocks are mined either on a fixed interval or whenever the validator's mempool
+//! contains transactions, see [`AutoMine`]. The heights of the mined blocks are exposed as a stream with
+//! [`AutoMiner::heights`].
+//!
+//! The auto-miner takes ownership of the local network and shares its validator with the mining task, so the
+//! validator can only be used mutably again once [`AutoMiner::stop`] returns the local network. Each block is mined
+//! on its own task so a validator error or panic while mining is logged and the auto-miner keeps mining.
+
+use std::{sync::Arc, time::Duration};
+
+use tokio::{
+    sync::{broadcast, watch},
+    task::JoinHandle,
+};
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt as _};
+use zcash_protocol::consensus::BlockHeight;
+
+use crate::{indexer::Indexer, validator::Validator, LocalNet};
+
+/// Number of mined heights buffered for each [`AutoMiner::heights`] stream before the oldest are skipped
+const HEIGHTS_CAPACITY: usize = 64;
+
+/// Condition for mining a block
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AutoMine {
+    /// Mines a block every interval.
+    Interval(Duration),
+    /// Polls the mempool every interval and mines a block when it is not empty.
+    Mempool(Duration),
+}
+
+/// Local network with a background task which mines blocks on its validator
+///
+/// The task stops in the background when the auto-miner is dropped, finishing any block which is being mined. The
+/// validator is dropped once the task has stopped.
+pub struct AutoMiner<I, V>
+where
+    I: Indexer,
+    V: Validator,
+{
+    indexer: I,
+    validator: Arc<V>,
+    paused: watch::Sender<bool>,
+    stopped: watch::Sender<bool>,
+    heights: broadcast::Sender<BlockHeight>,
+    handle: JoinHandle<()>,
+}
+
+impl<I, V> AutoMiner<I, V>
+where
+    I: Indexer,
+    V: Validator + Send + Sync + 'static,
+{
+    /// Spawns a task on the current tokio runtime which mines blocks on the `local_net` validator when `mode` is
+    /// satisfied.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime.
+    pub fn spawn(local_net: LocalNet<I, V>, mode: AutoMine) -> Self {
+        let LocalNet { indexer, validator } = local_net;
+        let validator = Arc::new(validator);
+        let (paused, paused_receiver) = watch::channel(false);
+        let (stopped, stopped_receiver) = watch::channel(false);
+        let (heights, _) = broadcast::channel(HEIGHTS_CAPACITY);
+        let handle = tokio::spawn(mine(
+            Arc::clone(&validator),
+            mode,
+            paused_receiver,
+            stopped_receiver,
+            heights.clone(),
+        ));
+
+        AutoMiner {
+            indexer,
+            validator,
+            paused,
+            stopped,
+            heights,
+            handle,
+        }
+    }
+
+    /// Gets indexer.
+    pub fn indexer(&self) -> &I {
+        &self.indexer
+    }
+
+    /// Gets validator.
+    pub fn validator(&self) -> &V {
+        &self.validator
+    }
+
+    /// Pauses mining. A block which is already being mined is still produced.
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    /// Resumes mining.
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
+    /// Returns `true` if mining is paused.
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Returns a stream of the heights of blocks mined after this call.
+    ///
+    /// Heights are skipped if the stream falls more than 64 blocks behind the auto-miner.
+    pub fn heights(&self) -> impl Stream<Item = BlockHeight> + Send + Unpin {
+        BroadcastStream::new(self.heights.subscribe()).filter_map(Result::ok)
+    }
+
+    /// Stops mining and returns the local network once the task has released the validator. A block which is
+    /// already being mined is still produced.
+    pub async fn stop(self) -> LocalNet<I, V> {
+        let AutoMiner {
+            indexer,
+            validator,
+            stopped,
+            handle,
+            ..
+        } = self;
+        stopped.send_replace(true);
+        // the task only holds the validator while running, so it is released even if the task panicked
+        let _ = handle.await;
+
+        LocalNet {
+            indexer,
+            validator: Arc::into_inner(validator)
+                .expect("validator is only shared with the stopped auto-miner task"),
+        }
+    }
+}
+
+async fn mine<V>(
+    validator: Arc<V>,
+    mode: AutoMine,
+    mut paused: watch::Receiver<bool>,
+    mut stopped: watch::Receiver<bool>,
+    heights: broadcast::Sender<BlockHeight>,
+) where
+    V: Validator + Send + Sync + 'static,
+{
+    let interval = match mode {
+        AutoMine::Interval(interval) | AutoMine::Mempool(interval) => interval,
+    };
+    let mut ticker = tokio::time::interval(interval);
+    // mining or pausing can outlast the interval so missed ticks are skipped instead of mining a burst of blocks
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // the first tick completes immediately
+    ticker.tick().await;
+
+    // dropping the auto-miner drops the senders, which also stops the task
+    loop {
+        tokio::select! {
+            _ = stopped.wait_for(|stopped| *stopped) => return,
+            _ = ticker.tick() => (),
+        }
+        tokio::select! {
+            _ = stopped.wait_for(|stopped| *stopped) => return,
+            resumed = paused.wait_for(|paused| !paused) => {
+                if resumed.is_err() {
+                    return;
+                }
+            }
+        }
+
+        // mined on a separate task so a panicking validator does not stop the auto-miner
+        match tokio::spawn(mine_block(Arc::clone(&validator), mode)).await {
+            Ok(Ok(Some(height))) => {
+                // there are no receivers when no heights stream exists
+                let _ = heights.send(height);
+            }
+            Ok(Ok(None)) => (),
+            Ok(Err(e)) => tracing::warn!("auto-miner {e}"),
+            Err(e) => tracing::warn!("auto-miner failed to mine a block: {e}"),
+        }
+    }
+}
+
+/// Mines a block if `mode` is satisfied and returns its height, or `None` if no block was mined.
+async fn mine_block<V>(validator: Arc<V>, mode: AutoMine) -> Result<Option<BlockHeight>, String>
+where
+    V: Validator + Send + Sync + 'static,
+{
+    if let AutoMine::Mempool(_) = mode {
+        let mempool = validator
+            .call_rpc("getrawmempool", Vec::new())
+            .await
+            .map_err(|e| format!("failed to get mempool: {e}"))?;
+        if mempool.as_array().is_none_or(|txids| txids.is_empty()) {
+            return Ok(None);
+        }
+    }
+
+    validator
+        .generate_blocks(1)
+        .await
+        .map_err(|e| format!("failed to generate block: {e}"))?;
+
+    Ok(Some(validator.get_chain_height().await))
+}
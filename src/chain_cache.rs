@@ -0,0 +1,1057 @@
+//! Module for chain cache manifests and validation
+//!
+//! When a chain is cached with [`crate::validator::Validator::cache_chain`], a manifest describing the chain is
+//! written to the root of the chain cache directory. The manifest is checked against the validator config when the
+//! chain cache is loaded so incompatible caches are rejected before the validator is launched.
+//!
+//! Chain caches can be exported to a single gzipped tar archive with [`export_archive`] and imported back with
+//! [`import_archive`] so they can be stored as build artifacts and shared between machines.
+//!
+//! Generated chain caches can be tagged with a [`fingerprint`] of their inputs (generating scenario, binary versions
+//! etc.). [`ensure_chain_cache`] regenerates a chain cache which is missing or has a different fingerprint, holding a
+//! cross-process lock so concurrent test processes only regenerate it once.
+
+use std::{
+    fs::File,
+    io::Write as _,
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use fs4::fs_std::FileExt as _;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::{
+    config::{ActivationHeightsToml, FundingStreamsToml},
+    error::ChainCacheError,
+    network::{ActivationHeights, FundingStreams, Network},
+    validator::Validator,
+};
+
+/// Chain cache manifest filename
+pub const MANIFEST_FILENAME: &str = "manifest.toml";
+
+/// Current chain cache manifest format version
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Chain cache fingerprint filename
+pub const FINGERPRINT_FILENAME: &str = "fingerprint";
+
+/// Policy for writing a chain cache to a directory which already exists
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Return [`ChainCacheError::AlreadyExists`] and leave the existing chain cache untouched
+    #[default]
+    Error,
+    /// Replace the existing chain cache once the new chain cache is fully written
+    Replace,
+}
+
+/// Validator which created the chain cache
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidatorKind {
+    /// Zcashd
+    Zcashd,
+    /// Zebrad
+    Zebrad,
+}
+
+impl std::fmt::Display for ValidatorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Zcashd => write!(f, "zcashd"),
+            Self::Zebrad => write!(f, "zebrad"),
+        }
+    }
+}
+
+/// Chain cache manifest
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChainCacheManifest {
+    /// Manifest format version
+    pub manifest_version: u32,
+    /// Validator which created the chain cache
+    pub validator: ValidatorKind,
+    /// Validator build version, i.e. "v2.0.1"
+    pub validator_version: String,
+    /// Network type
+    pub network: Network,
+    /// Network upgrade activation heights. Only recorded for regtest chains.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activation_heights: Option<ActivationHeightsToml>,
+    /// Funding streams. Only recorded for regtest chains.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub funding_streams: Option<FundingStreamsToml>,
+    /// Miner address. Only recorded for regtest chains.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub miner_address: Option<String>,
+    /// Chain tip height
+    pub tip_height: u32,
+    /// Chain tip block hash
+    pub tip_hash: String,
+    /// State database version directory, i.e. "v26". Only recorded for zebrad chains.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_version: Option<String>,
+    /// Creation time in seconds since the unix epoch
+    pub created: u64,
+}
+
+impl ChainCacheManifest {
+    /// Reads the manifest from the `chain_cache` directory.
+    /// Returns `None` if the chain cache was created without a manifest.
+    pub fn read(chain_cache: &Path) -> Result<Option<Self>, ChainCacheError> {
+        let manifest_path = chain_cache.join(MANIFEST_FILENAME);
+        if !manifest_path.exists() {
+            return Ok(None);
+        }
+
+        let manifest: Self = toml::from_str(&std::fs::read_to_string(manifest_path)?)
+            .map_err(|e| ChainCacheError::InvalidManifest(e.to_string()))?;
+        if manifest.manifest_version > MANIFEST_VERSION {
+            return Err(ChainCacheError::InvalidManifest(format!(
+                "unsupported manifest version {}",
+                manifest.manifest_version
+            )));
+        }
+
+        Ok(Some(manifest))
+    }
+
+    /// Writes the manifest to the `chain_cache` directory.
+    pub(crate) fn write(&self, chain_cache: &Path) -> Result<(), ChainCacheError> {
+        let manifest = toml::to_string(self).expect("manifest should serialize to TOML");
+        std::fs::write(chain_cache.join(MANIFEST_FILENAME), manifest)?;
+
+        Ok(())
+    }
+
+    /// Checks the chain cache is compatible with the requested validator config.
+    ///
+    /// Activation heights, funding streams and miner address are only checked for regtest chains.
+    ///
+    /// `validator_version` is the output of the validator's `--version` flag. Chain caches created by a newer
+    /// validator version are rejected, chain caches created by an older version are upgraded by the validator and
+    /// logged. The check is skipped if `validator_version` is `None` or can not be parsed.
+    ///
+    /// Zebrad keeps its state in a directory per major database version, so zebrad chain caches must have the state
+    /// version of the zebra crates this crate is built against, which zebrad must match to generate blocks.
+    pub fn validate(
+        &self,
+        validator: ValidatorKind,
+        network: Network,
+        activation_heights: &ActivationHeights,
+        funding_streams: &FundingStreams,
+        miner_address: Option<&str>,
+        validator_version: Option<&str>,
+    ) -> Result<(), ChainCacheError> {
+        check_field("validator", validator, self.validator)?;
+        check_field("network", network, self.network)?;
+
+        if let Some(state_version) = &self.state_version {
+            check_state_version(
+                state_version,
+                zebra_state::state_database_format_version_in_code().major,
+            )?;
+        }
+        if let Some(validator_version) = validator_version {
+            check_validator_version(&self.validator_version, validator_version)?;
+        }
+
+        if matches!(network, Network::Regtest) {
+            if let Some(cached_heights) = self.activation_heights {
+                check_toml_field(
+                    "activation_heights",
+                    &ActivationHeightsToml::from(*activation_heights),
+                    &cached_heights,
+                )?;
+            }
+            if let Some(cached_streams) = &self.funding_streams {
+                check_toml_field(
+                    "funding_streams",
+                    &FundingStreamsToml::from(funding_streams),
+                    cached_streams,
+                )?;
+            }
+            if let (Some(miner_address), Some(cached_address)) =
+                (miner_address, self.miner_address.as_deref())
+            {
+                check_field("miner_address", miner_address, cached_address)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds the manifest for the chain currently served by the `validator`.
+    pub(crate) async fn from_validator<V: Validator + Sync>(
+        validator: &V,
+        validator_kind: ValidatorKind,
+        activation_heights: &ActivationHeights,
+        funding_streams: &FundingStreams,
+        miner_address: Option<&str>,
+        state_version: Option<String>,
+    ) -> Result<Self, ChainCacheError> {
+        let info = validator.call_rpc("getinfo", Vec::new()).await?;
+        let validator_version = info["build"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| info["version"].to_string());
+        let tip_hash = validator
+            .call_rpc("getbestblockhash", Vec::new())
+            .await?
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| {
+                ChainCacheError::InvalidManifest("getbestblockhash returned no hash".to_string())
+            })?;
+        let network = validator.network();
+        let regtest = matches!(network, Network::Regtest);
+
+        Ok(ChainCacheManifest {
+            manifest_version: MANIFEST_VERSION,
+            validator: validator_kind,
+            validator_version,
+            network,
+            activation_heights: regtest.then(|| (*activation_heights).into()),
+            funding_streams: regtest.then(|| funding_streams.into()),
+            miner_address: miner_address.filter(|_| regtest).map(String::from),
+            tip_height: validator.get_chain_height().await.into(),
+            tip_hash,
+            state_version,
+            created: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .expect("system time should be after the unix epoch")
+                .as_secs(),
+        })
+    }
+}
+
+fn check_field<T: ToString + PartialEq>(
+    field: &str,
+    expected: T,
+    found: T,
+) -> Result<(), ChainCacheError> {
+    if expected != found {
+        return Err(ChainCacheError::Incompatible {
+            field: field.to_string(),
+            expected: expected.to_string(),
+            found: found.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Checks the chain cache was not created by a newer version than the running `validator_version`.
+fn check_validator_version(
+    cached_version: &str,
+    validator_version: &str,
+) -> Result<(), ChainCacheError> {
+    let (Some(cached), Some(running)) = (
+        parse_version(cached_version),
+        parse_version(validator_version),
+    ) else {
+        tracing::warn!(
+            "can not compare chain cache validator version `{cached_version}` to `{validator_version}`, skipping \
+            version check"
+        );
+        return Ok(());
+    };
+
+    if cached > running {
+        return Err(ChainCacheError::Incompatible {
+            field: "validator_version".to_string(),
+            expected: format!("v{}.{}.{} or older", running.0, running.1, running.2),
+            found: cached_version.to_string(),
+        });
+    }
+    if cached < running {
+        tracing::warn!(
+            "chain cache was created by validator version {cached_version}, the validator will upgrade it to \
+            `{validator_version}`"
+        );
+    }
+
+    Ok(())
+}
+
+/// Checks the zebrad state version directory, i.e. "v26", matches the `supported` major database version.
+fn check_state_version(state_version: &str, supported: u64) -> Result<(), ChainCacheError> {
+    let version = state_version
+        .strip_prefix('v')
+        .and_then(|version| version.parse::<u64>().ok())
+        .ok_or_else(|| {
+            ChainCacheError::InvalidManifest(format!("invalid state version `{state_version}`"))
+        })?;
+    if version != supported {
+        return Err(ChainCacheError::Incompatible {
+            field: "state_version".to_string(),
+            expected: format!("v{supported}"),
+            found: state_version.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Parses the first `major.minor.patch` version in `version`, allowing a leading `v` and a pre-release or build
+/// suffix, i.e. "v2.0.1" or the "zebrad 2.0.1" output of `zebrad --version`.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    version.split_whitespace().find_map(|word| {
+        let word = word.strip_prefix('v').unwrap_or(word);
+        let mut numbers = word.split(['-', '+']).next()?.split('.');
+        let version = (
+            numbers.next()?.parse().ok()?,
+            numbers.next()?.parse().ok()?,
+            numbers.next()?.parse().ok()?,
+        );
+
+        numbers.next().is_none().then_some(version)
+    })
+}
+
+/// Checks a TOML table field, listing only the differing keys in the error so large tables stay readable.
+fn check_toml_field<T: Serialize + PartialEq>(
+    field: &str,
+    expected: &T,
+    found: &T,
+) -> Result<(), ChainCacheError> {
+    if expected == found {
+        return Ok(());
+    }
+
+    let to_table = |value: &T| match toml::Value::try_from(value) {
+        Ok(toml::Value::Table(table)) => table,
+        _ => toml::Table::new(),
+    };
+    let expected = to_table(expected);
+    let found = to_table(found);
+    let keys = expected
+        .keys()
+        .chain(found.keys())
+        .collect::<std::collections::BTreeSet<_>>();
+    let differences = |table: &toml::Table| {
+        keys.iter()
+            .filter(|key| expected.get(key.as_str()) != found.get(key.as_str()))
+            .map(|key| match table.get(key.as_str()) {
+                Some(value) => format!("{key} = {value}"),
+                None => format!("{key} unset"),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    Err(ChainCacheError::Incompatible {
+        field: field.to_string(),
+        expected: differences(&expected),
+        found: differences(&found),
+    })
+}
+
+/// Checks the manifest in the `chain_cache` directory is compatible with the validator config.
+///
+/// Chain caches created without a manifest are logged as a warning and not checked.
+pub(crate) fn check_manifest(
+    chain_cache: &Path,
+    validator: ValidatorKind,
+    network: Network,
+    activation_heights: &ActivationHeights,
+    funding_streams: &FundingStreams,
+    miner_address: Option<&str>,
+    validator_version: Option<&str>,
+) -> Result<(), ChainCacheError> {
+    match ChainCacheManifest::read(chain_cache)? {
+        Some(manifest) => manifest.validate(
+            validator,
+            network,
+            activation_heights,
+            funding_streams,
+            miner_address,
+            validator_version,
+        ),
+        None => {
+            tracing::warn!(
+                "chain cache {} has no manifest, skipping compatibility checks",
+                chain_cache.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Exports the `chain_cache` directory to a gzipped tar `archive`.
+/// Returns the manifest of the exported chain cache.
+///
+/// The chain cache must have a manifest, which is written as the first entry of the archive. The archive is written
+/// to a temporary file and moved into place so a partially written archive is never left at `archive`.
+pub fn export_archive(
+    chain_cache: &Path,
+    archive: &Path,
+) -> Result<ChainCacheManifest, ChainCacheError> {
+    let manifest = ChainCacheManifest::read(chain_cache)?
+        .ok_or_else(|| ChainCacheError::MissingManifest(chain_cache.to_path_buf()))?;
+    if archive.exists() {
+        return Err(ChainCacheError::AlreadyExists(archive.to_path_buf()));
+    }
+
+    let archive_dir = archive
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let temp_archive = tempfile::NamedTempFile::new_in(archive_dir)?;
+
+    let mut builder = tar::Builder::new(GzEncoder::new(
+        temp_archive.as_file(),
+        Compression::default(),
+    ));
+    builder.follow_symlinks(false);
+    builder.append_path_with_name(chain_cache.join(MANIFEST_FILENAME), MANIFEST_FILENAME)?;
+    for entry in std::fs::read_dir(chain_cache)? {
+        let entry = entry?;
+        if entry.file_name() == MANIFEST_FILENAME {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            builder.append_dir_all(entry.file_name(), entry.path())?;
+        } else {
+            builder.append_path_with_name(entry.path(), entry.file_name())?;
+        }
+    }
+    builder.into_inner()?.finish()?.flush()?;
+
+    temp_archive
+        .persist_noclobber(archive)
+        .map_err(|e| e.error)?;
+
+    Ok(manifest)
+}
+
+/// Imports a gzipped tar `archive` created by [`export_archive`] into the `chain_cache` directory.
+/// Returns the manifest of the imported chain cache.
+///
+/// The archive is unpacked with [`write_dir_atomic`] so the chain cache is only moved into place once it is fully
+/// unpacked and its manifest is valid. Entries with absolute paths or `..` components are rejected with
+/// [`ChainCacheError::UnsafeArchiveEntry`].
+pub fn import_archive(
+    archive: &Path,
+    chain_cache: &Path,
+    overwrite: OverwritePolicy,
+) -> Result<ChainCacheManifest, ChainCacheError> {
+    write_dir_atomic(chain_cache, overwrite, |unpack_dir| {
+        let mut archive_reader = tar::Archive::new(GzDecoder::new(File::open(archive)?));
+        for entry in archive_reader.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.into_owned();
+            if !path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(ChainCacheError::UnsafeArchiveEntry(path));
+            }
+            entry.unpack_in(unpack_dir)?;
+        }
+        ChainCacheManifest::read(unpack_dir)?
+            .ok_or_else(|| ChainCacheError::MissingManifest(archive.to_path_buf()))
+    })
+}
+
+/// Writes the `dir` directory atomically.
+///
+/// `write` is called with a temporary directory next to `dir`, which is renamed to `dir` once `write` succeeds. If
+/// `write` fails the temporary directory is removed and `dir` is left untouched. If `dir` already exists, `overwrite`
+/// decides whether it is replaced or [`ChainCacheError::AlreadyExists`] is returned.
+///
+/// Replacing `dir` takes two renames, moving the existing directory aside and then moving the new directory into
+/// place, so there is a short window where `dir` does not exist. If the second rename fails the existing directory
+/// is moved back before the error is returned.
+pub(crate) fn write_dir_atomic<T>(
+    dir: &Path,
+    overwrite: OverwritePolicy,
+    write: impl FnOnce(&Path) -> Result<T, ChainCacheError>,
+) -> Result<T, ChainCacheError> {
+    if dir.exists() && overwrite == OverwritePolicy::Error {
+        return Err(ChainCacheError::AlreadyExists(dir.to_path_buf()));
+    }
+
+    let parent_dir = dir
+        .parent()
+        .filter(|parent_dir| !parent_dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    std::fs::create_dir_all(parent_dir)?;
+    let temp_dir = tempfile::Builder::new()
+        .prefix(".chain_cache_")
+        .tempdir_in(parent_dir)?;
+
+    let output = write(temp_dir.path())?;
+
+    let replaced_dir = if dir.exists() {
+        if overwrite == OverwritePolicy::Error {
+            return Err(ChainCacheError::AlreadyExists(dir.to_path_buf()));
+        }
+        // move the existing directory aside, it is removed when `replaced_dir` is dropped
+        let replaced_dir = tempfile::Builder::new()
+            .prefix(".chain_cache_replaced_")
+            .tempdir_in(parent_dir)?;
+        std::fs::rename(dir, replaced_dir.path().join("chain_cache"))?;
+        Some(replaced_dir)
+    } else {
+        None
+    };
+
+    if let Err(error) = std::fs::rename(temp_dir.path(), dir) {
+        if let Some(replaced_dir) = replaced_dir {
+            let replaced = replaced_dir.path().join("chain_cache");
+            if let Err(restore_error) = std::fs::rename(&replaced, dir) {
+                // keep the existing chain cache on disk rather than removing it with the temporary directory
+                let replaced = replaced_dir.into_path().join("chain_cache");
+                tracing::error!(
+                    "failed to restore {} from {}: {restore_error}",
+                    dir.display(),
+                    replaced.display()
+                );
+            }
+        }
+        return Err(error.into());
+    }
+    // the temporary directory has been moved to `dir` so must not be removed on drop
+    let _ = temp_dir.into_path();
+
+    Ok(output)
+}
+
+/// Returns a fingerprint of the `inputs` used to generate a chain cache, i.e. the serialized generating scenario and
+/// binary versions.
+pub fn fingerprint<S: AsRef<str>>(inputs: &[S]) -> String {
+    let mut hasher = Sha256::new();
+    for input in inputs {
+        let input = input.as_ref();
+        // length prefixed so moving bytes between inputs changes the fingerprint
+        hasher.update((input.len() as u64).to_le_bytes());
+        hasher.update(input.as_bytes());
+    }
+
+    hex::encode(hasher.finalize())
+}
+
+/// Reads the fingerprint of the `chain_cache` directory.
+/// Returns `None` if the chain cache does not exist or was created without a fingerprint.
+pub fn read_fingerprint(chain_cache: &Path) -> Result<Option<String>, ChainCacheError> {
+    let fingerprint_path = chain_cache.join(FINGERPRINT_FILENAME);
+    if !fingerprint_path.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        std::fs::read_to_string(fingerprint_path)?
+            .trim()
+            .to_string(),
+    ))
+}
+
+/// Writes the `fingerprint` to the `chain_cache` directory.
+pub fn write_fingerprint(chain_cache: &Path, fingerprint: &str) -> Result<(), ChainCacheError> {
+    std::fs::write(chain_cache.join(FINGERPRINT_FILENAME), fingerprint)?;
+
+    Ok(())
+}
+
+/// Ensures the `chain_cache` directory exists and was generated with `fingerprint`, otherwise calls `generate` to
+/// regenerate it. Returns true if the chain cache was regenerated.
+///
+/// `generate` must write the chain cache to `chain_cache`, replacing any stale chain cache. The fingerprint is written
+/// once `generate` succeeds, so an interrupted regeneration is detected as stale by the next call.
+///
+/// Regeneration holds an exclusive lock on a `.lock` file next to the chain cache, waiting on a blocking thread while
+/// another process regenerates the same chain cache. The fingerprint is checked again once the lock is acquired so the
+/// chain cache is only regenerated once.
+///
+/// Panics if called outside of a tokio runtime.
+pub async fn ensure_chain_cache<F, Fut, T>(
+    chain_cache: &Path,
+    fingerprint: &str,
+    generate: F,
+) -> Result<bool, ChainCacheError>
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<T, ChainCacheError>>,
+{
+    if read_fingerprint(chain_cache)?.as_deref() == Some(fingerprint) {
+        return Ok(false);
+    }
+
+    let lock_path = lock_path(chain_cache);
+    if let Some(lock_dir) = lock_path.parent() {
+        std::fs::create_dir_all(lock_dir)?;
+    }
+    let lock_file = tokio::task::spawn_blocking(move || {
+        let lock_file = File::create(&lock_path)?;
+        lock_file.lock_exclusive()?;
+        Ok::<_, std::io::Error>(lock_file)
+    })
+    .await
+    .map_err(std::io::Error::other)??;
+
+    if read_fingerprint(chain_cache)?.as_deref() == Some(fingerprint) {
+        return Ok(false);
+    }
+    tracing::info!(
+        "chain cache {} is missing or stale, regenerating",
+        chain_cache.display()
+    );
+    generate().await?;
+    write_fingerprint(chain_cache, fingerprint)?;
+
+    // the lock is released when the lock file is closed
+    drop(lock_file);
+
+    Ok(true)
+}
+
+/// Returns the path of the lock file held while the `chain_cache` is regenerated.
+fn lock_path(chain_cache: &Path) -> PathBuf {
+    let mut lock_path = chain_cache.as_os_str().to_owned();
+    lock_path.push(".lock");
+    PathBuf::from(lock_path)
+}
+
+/// Returns the zebrad state database version directory (i.e. "v26") in the `state_dir`.
+pub(crate) fn zebrad_state_version(state_dir: &Path) -> Option<String> {
+    std::fs::read_dir(state_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| {
+            name.strip_prefix('v')
+                .is_some_and(|version| version.parse::<u32>().is_ok())
+        })
+        .max_by_key(|name| name[1..].parse::<u32>().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::ChainCacheError, network};
+
+    use super::{ChainCacheManifest, OverwritePolicy, ValidatorKind, MANIFEST_VERSION};
+
+    fn manifest() -> ChainCacheManifest {
+        ChainCacheManifest {
+            manifest_version: MANIFEST_VERSION,
+            validator: ValidatorKind::Zebrad,
+            validator_version: "v2.0.1".to_string(),
+            network: network::Network::Regtest,
+            activation_heights: Some(network::ActivationHeights::default().into()),
+            funding_streams: Some((&network::FundingStreams::default()).into()),
+            miner_address: Some("test_addr_1234".to_string()),
+            tip_height: 52,
+            tip_hash: "00".repeat(32),
+            state_version: Some("v26".to_string()),
+            created: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn manifest_round_trip() {
+        let chain_cache = tempfile::tempdir().unwrap();
+        assert_eq!(ChainCacheManifest::read(chain_cache.path()).unwrap(), None);
+
+        manifest().write(chain_cache.path()).unwrap();
+
+        assert_eq!(
+            ChainCacheManifest::read(chain_cache.path()).unwrap(),
+            Some(manifest())
+        );
+    }
+
+    #[test]
+    fn validate_manifest() {
+        let manifest = manifest();
+        let activation_heights = network::ActivationHeights::default();
+        let funding_streams = network::FundingStreams::default();
+        let validate = |validator,
+                        network,
+                        activation_heights: &network::ActivationHeights,
+                        funding_streams: &network::FundingStreams,
+                        miner_address| {
+            manifest.validate(
+                validator,
+                network,
+                activation_heights,
+                funding_streams,
+                miner_address,
+                Some("zebrad 2.0.1"),
+            )
+        };
+
+        validate(
+            ValidatorKind::Zebrad,
+            network::Network::Regtest,
+            &activation_heights,
+            &funding_streams,
+            Some("test_addr_1234"),
+        )
+        .unwrap();
+        manifest
+            .validate(
+                ValidatorKind::Zebrad,
+                network::Network::Regtest,
+                &activation_heights,
+                &funding_streams,
+                Some("test_addr_1234"),
+                Some("zebrad 2.1.0"),
+            )
+            .unwrap();
+        assert!(matches!(
+            manifest.validate(
+                ValidatorKind::Zebrad,
+                network::Network::Regtest,
+                &activation_heights,
+                &funding_streams,
+                Some("test_addr_1234"),
+                Some("zebrad 2.0.0"),
+            ),
+            Err(ChainCacheError::Incompatible { field, .. }) if field == "validator_version"
+        ));
+        assert!(matches!(
+            validate(
+                ValidatorKind::Zcashd,
+                network::Network::Regtest,
+                &activation_heights,
+                &funding_streams,
+                Some("test_addr_1234"),
+            ),
+            Err(ChainCacheError::Incompatible { field, .. }) if field == "validator"
+        ));
+        assert!(matches!(
+            validate(
+                ValidatorKind::Zebrad,
+                network::Network::Testnet,
+                &activation_heights,
+                &funding_streams,
+                None,
+            ),
+            Err(ChainCacheError::Incompatible { field, .. }) if field == "network"
+        ));
+        match validate(
+            ValidatorKind::Zebrad,
+            network::Network::Regtest,
+            &network::ActivationHeights {
+                nu6: Some(10.into()),
+                ..Default::default()
+            },
+            &funding_streams,
+            Some("test_addr_1234"),
+        ) {
+            Err(ChainCacheError::Incompatible {
+                field,
+                expected,
+                found,
+            }) => {
+                assert_eq!(field, "activation_heights");
+                assert_eq!(expected, "nu6 = 10");
+                assert_eq!(found, "nu6 unset");
+            }
+            result => panic!("expected activation heights to be incompatible, got {result:?}"),
+        }
+        assert!(matches!(
+            validate(
+                ValidatorKind::Zebrad,
+                network::Network::Regtest,
+                &activation_heights,
+                &network::FundingStreams {
+                    pre_nu6: None,
+                    post_nu6: Some(network::FundingStreamPeriod {
+                        height_range: 1.into()..10.into(),
+                        recipients: vec![network::FundingStreamRecipient {
+                            receiver: network::FundingStreamReceiver::Deferred,
+                            numerator: 12,
+                            addresses: Vec::new(),
+                        }],
+                    }),
+                },
+                Some("test_addr_1234"),
+            ),
+            Err(ChainCacheError::Incompatible { field, .. }) if field == "funding_streams"
+        ));
+        assert!(matches!(
+            validate(
+                ValidatorKind::Zebrad,
+                network::Network::Regtest,
+                &activation_heights,
+                &funding_streams,
+                Some("other_addr"),
+            ),
+            Err(ChainCacheError::Incompatible { field, .. }) if field == "miner_address"
+        ));
+    }
+
+    #[test]
+    fn validator_version() {
+        assert_eq!(super::parse_version("v2.0.1"), Some((2, 0, 1)));
+        assert_eq!(super::parse_version("zebrad 2.0.10"), Some((2, 0, 10)));
+        assert_eq!(
+            super::parse_version("Zcash Daemon version v6.0.0-rc1"),
+            Some((6, 0, 0))
+        );
+        assert_eq!(super::parse_version("v6.0"), None);
+
+        super::check_validator_version("v2.0.1", "zebrad 2.0.1").unwrap();
+        super::check_validator_version("v2.0.1", "zebrad 2.0.10").unwrap();
+        super::check_validator_version("v2.0.1", "unknown").unwrap();
+        match super::check_validator_version("v2.0.10", "zebrad 2.0.1") {
+            Err(ChainCacheError::Incompatible {
+                field,
+                expected,
+                found,
+            }) => {
+                assert_eq!(field, "validator_version");
+                assert_eq!(expected, "v2.0.1 or older");
+                assert_eq!(found, "v2.0.10");
+            }
+            result => panic!("expected validator version to be incompatible, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn state_version() {
+        super::check_state_version("v26", 26).unwrap();
+        assert!(matches!(
+            super::check_state_version("v27", 26),
+            Err(ChainCacheError::Incompatible { field, expected, .. })
+                if field == "state_version" && expected == "v26"
+        ));
+        assert!(matches!(
+            super::check_state_version("v25", 26),
+            Err(ChainCacheError::Incompatible { .. })
+        ));
+        assert!(matches!(
+            super::check_state_version("26", 26),
+            Err(ChainCacheError::InvalidManifest(_))
+        ));
+    }
+
+    #[test]
+    fn archive_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let chain_cache = dir.path().join("chain_cache");
+        let db_dir = chain_cache.join("state").join("v26").join("regtest");
+        std::fs::create_dir_all(&db_dir).unwrap();
+        std::fs::write(db_dir.join("000008.sst"), "table").unwrap();
+        manifest().write(&chain_cache).unwrap();
+
+        let archive = dir.path().join("chain_cache.tar.gz");
+        assert_eq!(
+            super::export_archive(&chain_cache, &archive).unwrap(),
+            manifest()
+        );
+        assert!(matches!(
+            super::export_archive(&chain_cache, &archive),
+            Err(ChainCacheError::AlreadyExists(_))
+        ));
+
+        let imported = dir.path().join("imported").join("chain_cache");
+        assert_eq!(
+            super::import_archive(&archive, &imported, OverwritePolicy::Error).unwrap(),
+            manifest()
+        );
+        assert_eq!(
+            std::fs::read_to_string(
+                imported
+                    .join("state")
+                    .join("v26")
+                    .join("regtest")
+                    .join("000008.sst")
+            )
+            .unwrap(),
+            "table"
+        );
+        assert!(matches!(
+            super::import_archive(&archive, &imported, OverwritePolicy::Error),
+            Err(ChainCacheError::AlreadyExists(_))
+        ));
+        std::fs::write(imported.join("LOCK"), "").unwrap();
+        super::import_archive(&archive, &imported, OverwritePolicy::Replace).unwrap();
+        assert!(!imported.join("LOCK").exists());
+    }
+
+    #[test]
+    fn archive_missing_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let chain_cache = dir.path().join("chain_cache");
+        std::fs::create_dir_all(chain_cache.join("regtest")).unwrap();
+
+        assert!(matches!(
+            super::export_archive(&chain_cache, &dir.path().join("chain_cache.tar.gz")),
+            Err(ChainCacheError::MissingManifest(_))
+        ));
+
+        let archive = dir.path().join("no_manifest.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            std::fs::File::create(&archive).unwrap(),
+            flate2::Compression::default(),
+        ));
+        builder
+            .append_dir_all("regtest", chain_cache.join("regtest"))
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let imported = dir.path().join("imported");
+        assert!(matches!(
+            super::import_archive(&archive, &imported, OverwritePolicy::Error),
+            Err(ChainCacheError::MissingManifest(_))
+        ));
+        assert!(!imported.exists());
+    }
+
+    #[test]
+    fn archive_unsafe_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("unsafe.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            std::fs::File::create(&archive).unwrap(),
+            flate2::Compression::default(),
+        ));
+        // `Header::set_path` rejects `..` so the name is written directly
+        let mut header = tar::Header::new_gnu();
+        let name = b"../escaped";
+        header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name);
+        header.set_size(4);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, "data".as_bytes()).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let imported = dir.path().join("imported").join("chain_cache");
+        assert!(matches!(
+            super::import_archive(&archive, &imported, OverwritePolicy::Error),
+            Err(ChainCacheError::UnsafeArchiveEntry(path)) if path == std::path::Path::new("../escaped")
+        ));
+        assert!(!imported.exists());
+        assert!(!dir.path().join("imported").join("escaped").exists());
+    }
+
+    #[test]
+    fn write_dir_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let chain_cache = dir.path().join("chain_cache");
+
+        super::write_dir_atomic(&chain_cache, OverwritePolicy::Error, |temp_dir| {
+            Ok(std::fs::write(temp_dir.join("block"), "1")?)
+        })
+        .unwrap();
+        assert!(matches!(
+            super::write_dir_atomic(&chain_cache, OverwritePolicy::Error, |_| Ok(())),
+            Err(ChainCacheError::AlreadyExists(_))
+        ));
+        assert!(
+            super::write_dir_atomic(&chain_cache, OverwritePolicy::Replace, |temp_dir| {
+                std::fs::write(temp_dir.join("block"), "2")?;
+                Err::<(), _>(ChainCacheError::InvalidManifest("failed".to_string()))
+            })
+            .is_err()
+        );
+        assert_eq!(
+            std::fs::read_to_string(chain_cache.join("block")).unwrap(),
+            "1"
+        );
+
+        super::write_dir_atomic(&chain_cache, OverwritePolicy::Replace, |temp_dir| {
+            Ok(std::fs::write(temp_dir.join("block"), "2")?)
+        })
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(chain_cache.join("block")).unwrap(),
+            "2"
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // removing the temporary directory makes the final rename fail
+        assert!(matches!(
+            super::write_dir_atomic(&chain_cache, OverwritePolicy::Replace, |temp_dir| {
+                Ok(std::fs::remove_dir(temp_dir)?)
+            }),
+            Err(ChainCacheError::Io(_))
+        ));
+        assert_eq!(
+            std::fs::read_to_string(chain_cache.join("block")).unwrap(),
+            "2"
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn fingerprint() {
+        assert_eq!(
+            super::fingerprint(&["scenario", "zcashd v6.0.0"]),
+            super::fingerprint(&["scenario".to_string(), "zcashd v6.0.0".to_string()])
+        );
+        assert_ne!(
+            super::fingerprint(&["scenario", "zcashd v6.0.0"]),
+            super::fingerprint(&["scenario", "zcashd v6.1.0"])
+        );
+        assert_ne!(
+            super::fingerprint(&["ab", "c"]),
+            super::fingerprint(&["a", "bc"])
+        );
+    }
+
+    #[tokio::test]
+    async fn ensure_chain_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let chain_cache = dir.path().join("chain_cache");
+        let generate = |block: &'static str| {
+            let chain_cache = chain_cache.clone();
+            move || async move {
+                super::write_dir_atomic(&chain_cache, OverwritePolicy::Replace, |temp_dir| {
+                    Ok(std::fs::write(temp_dir.join("block"), block)?)
+                })
+            }
+        };
+
+        assert!(super::ensure_chain_cache(&chain_cache, "a", generate("1"))
+            .await
+            .unwrap());
+        assert_eq!(
+            super::read_fingerprint(&chain_cache).unwrap().as_deref(),
+            Some("a")
+        );
+        assert!(!super::ensure_chain_cache(&chain_cache, "a", generate("2"))
+            .await
+            .unwrap());
+        assert_eq!(
+            std::fs::read_to_string(chain_cache.join("block")).unwrap(),
+            "1"
+        );
+
+        assert!(super::ensure_chain_cache(&chain_cache, "b", generate("2"))
+            .await
+            .unwrap());
+        assert_eq!(
+            std::fs::read_to_string(chain_cache.join("block")).unwrap(),
+            "2"
+        );
+        assert!(super::ensure_chain_cache(&chain_cache, "c", || async {
+            Err::<(), _>(ChainCacheError::InvalidManifest("failed".to_string()))
+        })
+        .await
+        .is_err());
+        assert_eq!(
+            super::read_fingerprint(&chain_cache).unwrap().as_deref(),
+            Some("b")
+        );
+    }
+
+    #[test]
+    fn zebrad_state_version() {
+        let state_dir = tempfile::tempdir().unwrap();
+        for dir in ["v25", "v26", "other"] {
+            std::fs::create_dir(state_dir.path().join(dir)).unwrap();
+        }
+
+        assert_eq!(
+            super::zebrad_state_version(state_dir.path()),
+            Some("v26".to_string())
+        );
+    }
+}
@@ -1,194 +1,1738 @@
 //! Module for configuring processes and writing configuration files
+//!
+//! Each config file is generated from a typed model ([`ZcashConf`], [`ZebradToml`], [`ZainodToml`] and
+//! [`LightwalletdYml`]) which can also be parsed back from a running process with `read_config`.
 
-use std::fs::File;
-use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
 
 use portpicker::Port;
-
-use crate::network::{ActivationHeights, Network};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ConfigError,
+    indexer::{LightwalletdConfig, ZainodConfig},
+    network::{
+        ActivationHeights, FundingStreamPeriod, FundingStreamReceiver, FundingStreamRecipient,
+        FundingStreams, Network,
+    },
+    validator::{ZcashdConfig, ZebradConfig, ZEBRAD_DEFAULT_MINER},
+    Process,
+};
 
 pub(crate) const ZCASHD_FILENAME: &str = "zcash.conf";
 pub(crate) const ZEBRAD_FILENAME: &str = "zebrad.toml";
 pub(crate) const ZAINOD_FILENAME: &str = "zindexer.toml";
 pub(crate) const LIGHTWALLETD_FILENAME: &str = "lightwalletd.yml";
 
+/// Zebrad mainnet DNS seeders
+const ZEBRAD_MAINNET_PEERS: [&str; 4] = [
+    "dnsseed.z.cash:8233",
+    "dnsseed.str4d.xyz:8233",
+    "mainnet.seeder.zfnd.org:8233",
+    "mainnet.is.yolo.money:8233",
+];
+/// Zebrad testnet DNS seeders
+const ZEBRAD_TESTNET_PEERS: [&str; 3] = [
+    "dnsseed.testnet.z.cash:18233",
+    "testnet.seeder.zfnd.org:18233",
+    "testnet.is.yolo.money:18233",
+];
+/// Zebrad has no option to disable peer crawling so offline mode sets an interval longer than any test run
+const ZEBRAD_OFFLINE_CRAWL_INTERVAL: &str = "1year";
+
+/// Zcashd regtest RPC username and password
+pub(crate) const ZCASHD_RPC_CREDENTIALS: &str = "xxxxxx";
+
+/// Zcashd options which are managed by this crate and can not be set with extra options.
+const ZCASHD_RESERVED_OPTIONS: [&str; 7] = [
+    "regtest",
+    "nuparams",
+    "fundingstream",
+    "rpcuser",
+    "rpcpassword",
+    "rpcport",
+    "mineraddress",
+];
+/// Zcashd command-line arguments which are managed by this crate and can not be set with extra arguments.
+const ZCASHD_RESERVED_ARGS: [&str; 3] = ["conf", "datadir", "printtoconsole"];
+/// Zebrad options which are managed by this crate and can not be set with extra options.
+const ZEBRAD_RESERVED_OPTIONS: [&str; 9] = [
+    "network.network",
+    "network.listen_addr",
+    "rpc.listen_addr",
+    "rpc.cookie_dir",
+    "state.cache_dir",
+    "mining.miner_address",
+    "network.testnet_parameters.activation_heights",
+    "network.testnet_parameters.pre_nu6_funding_streams",
+    "network.testnet_parameters.post_nu6_funding_streams",
+];
+/// Zebrad command-line arguments which are managed by this crate and can not be set with extra arguments.
+const ZEBRAD_RESERVED_ARGS: [&str; 2] = ["c", "config"];
+/// Zainod options which are managed by this crate and can not be set with extra options.
+const ZAINOD_RESERVED_OPTIONS: [&str; 4] =
+    ["listen_port", "zebrad_port", "node_user", "node_password"];
+/// Zainod command-line arguments which are managed by this crate and can not be set with extra arguments.
+const ZAINOD_RESERVED_ARGS: [&str; 1] = ["config"];
+/// Lightwalletd options which are managed by this crate and can not be set with extra options.
+const LIGHTWALLETD_RESERVED_OPTIONS: [&str; 3] = ["grpc-bind-addr", "log-file", "zcash-conf-path"];
+/// Lightwalletd command-line arguments which are managed by this crate and can not be set with extra arguments.
+const LIGHTWALLETD_RESERVED_ARGS: [&str; 6] = [
+    "no-tls-very-insecure",
+    "data-dir",
+    "log-file",
+    "zcash-conf-path",
+    "config",
+    "grpc-bind-addr",
+];
+
+/// Checks the network upgrades activate in order, each activation height must be greater than or equal to the
+/// previous network upgrade's activation height.
+///
+/// Zebrad also reserves block height 0 for the Genesis network upgrade so all activation heights must be at least 1.
+/// Zebrad block templates contain v4 coinbase transactions, which are only valid from Sapling, so Overwinter and
+/// Sapling must activate at height 1.
+pub(crate) fn validate_activation_heights(
+    process: Process,
+    activation_heights: &ActivationHeights,
+) -> Result<(), ConfigError> {
+    let heights = [
+        ("overwinter", Some(activation_heights.overwinter)),
+        ("sapling", Some(activation_heights.sapling)),
+        ("blossom", Some(activation_heights.blossom)),
+        ("heartwood", Some(activation_heights.heartwood)),
+        ("canopy", Some(activation_heights.canopy)),
+        ("nu5", Some(activation_heights.nu5)),
+        ("nu6", activation_heights.nu6),
+    ];
+    let invalid = |option: &str, reason: String| ConfigError::InvalidOption {
+        process_name: process.to_string(),
+        option: format!("activation_heights.{option}"),
+        reason,
+    };
+
+    let mut previous: Option<(&str, u32)> = None;
+    for (name, height) in heights {
+        let Some(height) = height.map(u32::from) else {
+            continue;
+        };
+        if matches!(process, Process::Zebrad) && height == 0 {
+            return Err(invalid(
+                name,
+                "block height 0 is reserved for the genesis block".to_string(),
+            ));
+        }
+        if matches!(process, Process::Zebrad)
+            && matches!(name, "overwinter" | "sapling")
+            && height != 1
+        {
+            return Err(invalid(
+                name,
+                "zebrad block templates are only valid from sapling so overwinter and sapling must activate at \
+                 height 1"
+                    .to_string(),
+            ));
+        }
+        if let Some((previous_name, previous_height)) = previous {
+            if height < previous_height {
+                return Err(invalid(
+                    name,
+                    format!(
+                        "activation height {height} is lower than {previous_name} activation height {previous_height}"
+                    ),
+                ));
+            }
+        }
+        previous = Some((name, height));
+    }
+
+    Ok(())
+}
+
+/// Checks each funding stream period has a valid height range and each recipient has addresses, except for the
+/// deferred lockbox stream which must not have any addresses.
+///
+/// Zcashd streams must also be supported by zcashd and use the consensus numerator, see [`zcashd_funding_stream`].
+pub(crate) fn validate_funding_streams(
+    process: Process,
+    funding_streams: &FundingStreams,
+) -> Result<(), ConfigError> {
+    let periods = [
+        ("pre_nu6", false, &funding_streams.pre_nu6),
+        ("post_nu6", true, &funding_streams.post_nu6),
+    ];
+    for (name, post_nu6, period) in periods {
+        let Some(period) = period else {
+            continue;
+        };
+        let invalid = |reason: String| ConfigError::InvalidOption {
+            process_name: process.to_string(),
+            option: format!("funding_streams.{name}"),
+            reason,
+        };
+
+        if period.height_range.is_empty() {
+            return Err(invalid(format!(
+                "height range {:?} is empty",
+                period.height_range
+            )));
+        }
+        for recipient in &period.recipients {
+            match recipient.receiver {
+                FundingStreamReceiver::Deferred if !recipient.addresses.is_empty() => {
+                    return Err(invalid(
+                        "the deferred lockbox stream can not have addresses".to_string(),
+                    ));
+                }
+                FundingStreamReceiver::Deferred => (),
+                receiver if recipient.addresses.is_empty() => {
+                    return Err(invalid(format!("{receiver:?} stream has no addresses")));
+                }
+                _ => (),
+            }
+            if matches!(process, Process::Zcashd) {
+                match zcashd_funding_stream(post_nu6, recipient.receiver) {
+                    Some((_, numerator)) if recipient.numerator != numerator => {
+                        return Err(invalid(format!(
+                            "zcashd {:?} stream numerator is fixed to {numerator} by consensus rules",
+                            recipient.receiver
+                        )));
+                    }
+                    Some(_) => (),
+                    None => {
+                        return Err(invalid(format!(
+                            "zcashd does not support a {name} {:?} stream",
+                            recipient.receiver
+                        )));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the zcashd funding stream index and consensus numerator of the `receiver` before or after NU6 activation.
+///
+/// Zcashd only supports the ECC, Zcash Foundation and Major Grants streams before NU6 and the Major Grants (Zcash
+/// Community Grants) and deferred lockbox streams after NU6.
+fn zcashd_funding_stream(post_nu6: bool, receiver: FundingStreamReceiver) -> Option<(u32, u64)> {
+    match (post_nu6, receiver) {
+        (false, FundingStreamReceiver::Ecc) => Some((ZcashdFundingStream::ZIP214_BP, 7)),
+        (false, FundingStreamReceiver::ZcashFoundation) => {
+            Some((ZcashdFundingStream::ZIP214_ZF, 5))
+        }
+        (false, FundingStreamReceiver::MajorGrants) => Some((ZcashdFundingStream::ZIP214_MG, 8)),
+        (true, FundingStreamReceiver::MajorGrants) => Some((ZcashdFundingStream::FPF_ZCG, 8)),
+        (true, FundingStreamReceiver::Deferred) => Some((ZcashdFundingStream::DEFERRED, 12)),
+        _ => None,
+    }
+}
+
+/// Checks the extra zcash.conf options and zcashd command-line arguments do not override any options managed by
+/// this crate.
+pub(crate) fn validate_zcashd_extras(
+    extra_options: &[(String, String)],
+    extra_args: &[String],
+) -> Result<(), ConfigError> {
+    for (key, value) in extra_options {
+        if ZCASHD_RESERVED_OPTIONS.contains(&key.as_str()) {
+            return Err(ConfigError::ReservedOption {
+                process_name: Process::Zcashd.to_string(),
+                option: key.clone(),
+            });
+        }
+        if key.is_empty() || key.contains(['=', '\n', '\r', '#']) {
+            return Err(ConfigError::InvalidOption {
+                process_name: Process::Zcashd.to_string(),
+                option: key.clone(),
+                reason: "option names must be non-empty and not contain '=', '#' or newlines"
+                    .to_string(),
+            });
+        }
+        // a newline in the value would start a new, possibly reserved, option and '#' starts a comment
+        if value.contains(['\n', '\r', '#']) {
+            return Err(ConfigError::InvalidOption {
+                process_name: Process::Zcashd.to_string(),
+                option: key.clone(),
+                reason: "option values must not contain '#' or newlines".to_string(),
+            });
+        }
+    }
+
+    // zcashd accepts any config file option as a command-line argument
+    let reserved_args: Vec<&str> = ZCASHD_RESERVED_OPTIONS
+        .iter()
+        .chain(ZCASHD_RESERVED_ARGS.iter())
+        .copied()
+        .collect();
+    validate_extra_args(Process::Zcashd, extra_args, &reserved_args)
+}
+
+/// Checks the extra zebrad.toml options and zebrad command-line arguments are compatible with the generated
+/// defaults and do not override any options managed by this crate.
+pub(crate) fn validate_zebrad_extras(
+    extra_options: &toml::Table,
+    extra_args: &[String],
+) -> Result<(), ConfigError> {
+    validate_toml_table(Process::Zebrad, extra_options, &ZEBRAD_RESERVED_OPTIONS, "")?;
+    validate_extra_args(Process::Zebrad, extra_args, &ZEBRAD_RESERVED_ARGS)
+}
+
+/// Checks the extra zindexer.toml options and zainod command-line arguments are compatible with the generated
+/// defaults and do not override any options managed by this crate.
+pub(crate) fn validate_zainod_extras(
+    extra_options: &toml::Table,
+    extra_args: &[String],
+) -> Result<(), ConfigError> {
+    validate_toml_table(Process::Zainod, extra_options, &ZAINOD_RESERVED_OPTIONS, "")?;
+    validate_extra_args(Process::Zainod, extra_args, &ZAINOD_RESERVED_ARGS)
+}
+
+/// Checks the extra lightwalletd.yml options and lightwalletd command-line arguments do not override any options
+/// managed by this crate.
+pub(crate) fn validate_lightwalletd_extras(
+    extra_options: &serde_yaml::Mapping,
+    extra_args: &[String],
+) -> Result<(), ConfigError> {
+    for key in extra_options.keys() {
+        let Some(key) = key.as_str() else {
+            return Err(ConfigError::InvalidOption {
+                process_name: Process::Lightwalletd.to_string(),
+                option: format!("{:?}", key),
+                reason: "option names must be strings".to_string(),
+            });
+        };
+        if LIGHTWALLETD_RESERVED_OPTIONS.contains(&key) {
+            return Err(ConfigError::ReservedOption {
+                process_name: Process::Lightwalletd.to_string(),
+                option: key.to_string(),
+            });
+        }
+    }
+    validate_extra_args(
+        Process::Lightwalletd,
+        extra_args,
+        &LIGHTWALLETD_RESERVED_ARGS,
+    )
+}
+
+fn validate_toml_table(
+    process: Process,
+    table: &toml::Table,
+    reserved: &[&str],
+    parent: &str,
+) -> Result<(), ConfigError> {
+    for (key, value) in table {
+        let option = if parent.is_empty() {
+            key.clone()
+        } else {
+            format!("{parent}.{key}")
+        };
+        if reserved.contains(&option.as_str()) {
+            return Err(ConfigError::ReservedOption {
+                process_name: process.to_string(),
+                option,
+            });
+        }
+        if let toml::Value::Table(table) = value {
+            validate_toml_table(process, table, reserved, &option)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_extra_args(
+    process: Process,
+    extra_args: &[String],
+    reserved: &[&str],
+) -> Result<(), ConfigError> {
+    for arg in extra_args {
+        let name = arg
+            .trim_start_matches('-')
+            .split('=')
+            .next()
+            .expect("split always returns at least one item");
+        if arg.starts_with('-') && reserved.contains(&name) {
+            return Err(ConfigError::ReservedOption {
+                process_name: process.to_string(),
+                option: arg.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Typed model of a process config file.
+pub trait ConfigFile: Sized {
+    /// Parses the config file at `path`.
+    fn read(path: &Path) -> Result<Self, ConfigError>;
+}
+
+/// Network upgrade activation parameters written to zcash.conf as `nuparams=<branch_id>:<activation_height>`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NuParams {
+    /// Consensus branch ID of the network upgrade
+    pub branch_id: u32,
+    /// Activation height of the network upgrade
+    pub activation_height: u32,
+}
+
+/// Regtest funding stream written to zcash.conf as `fundingstream=<stream_id>:<start>:<end>:<addresses>`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZcashdFundingStream {
+    /// Zcashd funding stream index
+    pub stream_id: u32,
+    /// Start height (inclusive)
+    pub start_height: u32,
+    /// End height (exclusive)
+    pub end_height: u32,
+    /// Comma separated recipient addresses
+    pub addresses: Vec<String>,
+}
+
+impl ZcashdFundingStream {
+    /// Zcashd funding stream index of the ZIP 214 Bootstrap Project (ECC) stream
+    pub const ZIP214_BP: u32 = 0;
+    /// Zcashd funding stream index of the ZIP 214 Zcash Foundation stream
+    pub const ZIP214_ZF: u32 = 1;
+    /// Zcashd funding stream index of the ZIP 214 Major Grants stream
+    pub const ZIP214_MG: u32 = 2;
+    /// Zcashd funding stream index of the ZIP 1015 Zcash Community Grants stream
+    pub const FPF_ZCG: u32 = 3;
+    /// Zcashd funding stream index of the ZIP 1015 deferred lockbox stream
+    pub const DEFERRED: u32 = 4;
+}
+
+/// Zcashd config file model (zcash.conf)
+///
+/// Options which are not part of the model are kept in order in `extra_options`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZcashConf {
+    /// Run on the regtest network
+    pub regtest: bool,
+    /// Network upgrade activation parameters
+    pub nuparams: Vec<NuParams>,
+    /// Regtest funding streams
+    pub fundingstream: Vec<ZcashdFundingStream>,
+    /// Maintain a full transaction index
+    pub txindex: bool,
+    /// Enable insight explorer RPCs
+    pub insightexplorer: bool,
+    /// Enable experimental features
+    pub experimentalfeatures: bool,
+    /// Enable lightwalletd support
+    pub lightwalletd: bool,
+    /// RPC username
+    pub rpcuser: String,
+    /// RPC password
+    pub rpcpassword: String,
+    /// RPC port
+    pub rpcport: Port,
+    /// Allowed RPC client IP
+    pub rpcallowip: String,
+    /// Accept incoming peer connections
+    pub listen: bool,
+    /// Block reward address
+    pub mineraddress: Option<String>,
+    /// Mine to the local zcashd wallet
+    pub minetolocalwallet: Option<bool>,
+    /// Any other options as key/value pairs
+    pub extra_options: Vec<(String, String)>,
+}
+
+impl ZcashConf {
+    /// Returns the config as ordered key/value pairs.
+    pub fn options(&self) -> Vec<(String, String)> {
+        let mut options = vec![("regtest".to_string(), conf_bool(self.regtest))];
+        options.extend(self.nuparams.iter().map(|nuparams| {
+            (
+                "nuparams".to_string(),
+                format!("{:08x}:{}", nuparams.branch_id, nuparams.activation_height),
+            )
+        }));
+        options.extend(self.fundingstream.iter().map(|fundingstream| {
+            (
+                "fundingstream".to_string(),
+                format!(
+                    "{}:{}:{}:{}",
+                    fundingstream.stream_id,
+                    fundingstream.start_height,
+                    fundingstream.end_height,
+                    fundingstream.addresses.join(",")
+                ),
+            )
+        }));
+        options.extend([
+            ("txindex".to_string(), conf_bool(self.txindex)),
+            (
+                "insightexplorer".to_string(),
+                conf_bool(self.insightexplorer),
+            ),
+            (
+                "experimentalfeatures".to_string(),
+                conf_bool(self.experimentalfeatures),
+            ),
+            ("lightwalletd".to_string(), conf_bool(self.lightwalletd)),
+            ("rpcuser".to_string(), self.rpcuser.clone()),
+            ("rpcpassword".to_string(), self.rpcpassword.clone()),
+            ("rpcport".to_string(), self.rpcport.to_string()),
+            ("rpcallowip".to_string(), self.rpcallowip.clone()),
+            ("listen".to_string(), conf_bool(self.listen)),
+        ]);
+        if let Some(mineraddress) = &self.mineraddress {
+            options.push(("mineraddress".to_string(), mineraddress.clone()));
+        }
+        if let Some(minetolocalwallet) = self.minetolocalwallet {
+            options.push((
+                "minetolocalwallet".to_string(),
+                conf_bool(minetolocalwallet),
+            ));
+        }
+        options.extend(self.extra_options.iter().cloned());
+
+        options
+    }
+
+    /// Builds the config from key/value pairs.
+    ///
+    /// Later values of single-valued options override earlier values.
+    pub fn from_options(options: Vec<(String, String)>) -> Result<Self, ConfigError> {
+        let mut regtest = None;
+        let mut nuparams = Vec::new();
+        let mut fundingstream = Vec::new();
+        let mut txindex = None;
+        let mut insightexplorer = None;
+        let mut experimentalfeatures = None;
+        let mut lightwalletd = None;
+        let mut rpcuser = None;
+        let mut rpcpassword = None;
+        let mut rpcport = None;
+        let mut rpcallowip = None;
+        let mut listen = None;
+        let mut mineraddress = None;
+        let mut minetolocalwallet = None;
+        let mut extra_options = Vec::new();
+
+        for (key, value) in options {
+            match key.as_str() {
+                "regtest" => regtest = Some(parse_conf_bool(&key, &value)?),
+                "nuparams" => nuparams.push(parse_nuparams(&value)?),
+                "fundingstream" => fundingstream.push(parse_fundingstream(&value)?),
+                "txindex" => txindex = Some(parse_conf_bool(&key, &value)?),
+                "insightexplorer" => insightexplorer = Some(parse_conf_bool(&key, &value)?),
+                "experimentalfeatures" => {
+                    experimentalfeatures = Some(parse_conf_bool(&key, &value)?)
+                }
+                "lightwalletd" => lightwalletd = Some(parse_conf_bool(&key, &value)?),
+                "rpcuser" => rpcuser = Some(value),
+                "rpcpassword" => rpcpassword = Some(value),
+                "rpcport" => rpcport = Some(parse_rpcport(&value)?),
+                "rpcallowip" => rpcallowip = Some(value),
+                "listen" => listen = Some(parse_conf_bool(&key, &value)?),
+                "mineraddress" => mineraddress = Some(value),
+                "minetolocalwallet" => minetolocalwallet = Some(parse_conf_bool(&key, &value)?),
+                _ => extra_options.push((key, value)),
+            }
+        }
+
+        Ok(ZcashConf {
+            regtest: regtest.unwrap_or(false),
+            nuparams,
+            fundingstream,
+            txindex: txindex.unwrap_or(false),
+            insightexplorer: insightexplorer.unwrap_or(false),
+            experimentalfeatures: experimentalfeatures.unwrap_or(false),
+            lightwalletd: lightwalletd.unwrap_or(false),
+            rpcuser: required_conf_option("rpcuser", rpcuser)?,
+            rpcpassword: required_conf_option("rpcpassword", rpcpassword)?,
+            rpcport: required_conf_option("rpcport", rpcport)?,
+            rpcallowip: rpcallowip.unwrap_or_default(),
+            listen: listen.unwrap_or(true),
+            mineraddress,
+            minetolocalwallet,
+            extra_options,
+        })
+    }
+
+    /// Reads only the `rpcport` option of the zcash.conf at `path`, e.g. of a config file which was not written by
+    /// this crate and may not match the model.
+    pub fn read_rpcport(path: &Path) -> Result<Port, ConfigError> {
+        let rpcport = parse_conf_options(&std::fs::read_to_string(path)?)?
+            .into_iter()
+            .rfind(|(key, _)| key == "rpcport")
+            .map(|(_, value)| parse_rpcport(&value))
+            .transpose()?;
+
+        required_conf_option("rpcport", rpcport)
+    }
+
+    /// Sets each extra option. Options which are part of the model are overwritten, all other options are
+    /// appended.
+    fn merge(&mut self, extra_options: &[(String, String)]) -> Result<(), ConfigError> {
+        if extra_options.is_empty() {
+            return Ok(());
+        }
+
+        let mut options = self.options();
+        options.extend(extra_options.iter().cloned());
+        *self = ZcashConf::from_options(options)?;
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ZcashConf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (key, value) in self.options() {
+            writeln!(f, "{key}={value}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for ZcashConf {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ZcashConf::from_options(parse_conf_options(s)?)
+    }
+}
+
+impl ConfigFile for ZcashConf {
+    fn read(path: &Path) -> Result<Self, ConfigError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+}
+
+fn conf_bool(value: bool) -> String {
+    if value { "1" } else { "0" }.to_string()
+}
+
+fn parse_conf_bool(key: &str, value: &str) -> Result<bool, ConfigError> {
+    match value {
+        "1" => Ok(true),
+        "0" => Ok(false),
+        _ => Err(ConfigError::Parse(format!(
+            "invalid zcash.conf {key} `{value}`, expected 0 or 1"
+        ))),
+    }
+}
+
+fn parse_nuparams(value: &str) -> Result<NuParams, ConfigError> {
+    let invalid = || ConfigError::Parse(format!("invalid zcash.conf nuparams `{value}`"));
+    let (branch_id, activation_height) = value.split_once(':').ok_or_else(invalid)?;
+
+    Ok(NuParams {
+        branch_id: u32::from_str_radix(branch_id, 16).map_err(|_| invalid())?,
+        activation_height: activation_height.parse().map_err(|_| invalid())?,
+    })
+}
+
+fn parse_fundingstream(value: &str) -> Result<ZcashdFundingStream, ConfigError> {
+    let invalid = || ConfigError::Parse(format!("invalid zcash.conf fundingstream `{value}`"));
+    let mut params = value.splitn(4, ':');
+    let mut next_u32 = || -> Result<u32, ConfigError> {
+        params
+            .next()
+            .and_then(|param| param.parse().ok())
+            .ok_or_else(invalid)
+    };
+    let stream_id = next_u32()?;
+    let start_height = next_u32()?;
+    let end_height = next_u32()?;
+    let addresses = params.next().ok_or_else(invalid)?;
+
+    Ok(ZcashdFundingStream {
+        stream_id,
+        start_height,
+        end_height,
+        addresses: addresses
+            .split(',')
+            .filter(|address| !address.is_empty())
+            .map(String::from)
+            .collect(),
+    })
+}
+
+/// Parses the key/value pairs of a zcash.conf, ignoring comments and empty lines.
+fn parse_conf_options(s: &str) -> Result<Vec<(String, String)>, ConfigError> {
+    let mut options = Vec::new();
+    for line in s.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| ConfigError::Parse(format!("invalid zcash.conf line `{line}`")))?;
+        options.push((key.trim().to_string(), value.trim().to_string()));
+    }
+
+    Ok(options)
+}
+
+fn parse_rpcport(value: &str) -> Result<Port, ConfigError> {
+    value
+        .parse()
+        .map_err(|_| ConfigError::Parse(format!("invalid zcash.conf rpcport `{value}`")))
+}
+
+fn required_conf_option<T>(key: &str, value: Option<T>) -> Result<T, ConfigError> {
+    value.ok_or_else(|| ConfigError::Parse(format!("zcash.conf is missing `{key}`")))
+}
+
+/// Zebrad config file model (zebrad.toml)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZebradToml {
+    /// Consensus section
+    pub consensus: ZebradConsensus,
+    /// Mempool section
+    pub mempool: ZebradMempool,
+    /// Metrics section
+    #[serde(default)]
+    pub metrics: toml::Table,
+    /// Network section
+    pub network: ZebradNetwork,
+    /// RPC section
+    pub rpc: ZebradRpc,
+    /// State section
+    pub state: ZebradState,
+    /// Sync section
+    pub sync: ZebradSync,
+    /// Tracing section
+    pub tracing: ZebradTracing,
+    /// Mining section
+    pub mining: ZebradMining,
+}
+
+impl ConfigFile for ZebradToml {
+    fn read(path: &Path) -> Result<Self, ConfigError> {
+        toml::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+}
+
+/// Zebrad consensus section
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZebradConsensus {
+    /// Use checkpoints to sync the chain
+    pub checkpoint_sync: bool,
+}
+
+/// Zebrad mempool section
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZebradMempool {
+    /// Time rejected transactions are remembered for
+    pub eviction_memory_time: String,
+    /// Maximum total cost of the mempool transactions
+    pub tx_cost_limit: u64,
+}
+
+/// Zebrad network section
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZebradNetwork {
+    /// Peer cache directory, or `false` to disable the peer cache
+    pub cache_dir: bool,
+    /// Interval between peer crawls
+    pub crawl_new_peer_interval: String,
+    /// Initial mainnet peers
+    pub initial_mainnet_peers: Vec<String>,
+    /// Initial testnet peers
+    pub initial_testnet_peers: Vec<String>,
+    /// Network listen address
+    pub listen_addr: String,
+    /// Maximum connections per peer IP address
+    pub max_connections_per_ip: u32,
+    /// Network type
+    pub network: String,
+    /// Initial peer set size
+    pub peerset_initial_target_size: u32,
+    /// Configured testnet (regtest) parameters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub testnet_parameters: Option<ZebradTestnetParameters>,
+}
+
+/// Zebrad configured testnet parameters, used for regtest
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZebradTestnetParameters {
+    /// Disable proof-of-work validation
+    pub disable_pow: bool,
+    /// Network upgrade activation heights
+    pub activation_heights: ZebradActivationHeights,
+    /// Funding streams before NU6 activation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_nu6_funding_streams: Option<ZebradFundingStreams>,
+    /// Funding streams and lockbox contribution after NU6 activation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_nu6_funding_streams: Option<ZebradFundingStreams>,
+}
+
+/// Zebrad configured funding streams
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZebradFundingStreams {
+    /// Block heights the funding streams are active for
+    pub height_range: ZebradHeightRange,
+    /// Funding stream recipients
+    pub recipients: Vec<ZebradFundingStreamRecipient>,
+}
+
+impl From<&FundingStreamPeriod> for ZebradFundingStreams {
+    fn from(period: &FundingStreamPeriod) -> Self {
+        Self {
+            height_range: ZebradHeightRange {
+                start: period.height_range.start.into(),
+                end: period.height_range.end.into(),
+            },
+            recipients: period
+                .recipients
+                .iter()
+                .map(|recipient| ZebradFundingStreamRecipient {
+                    receiver: match recipient.receiver {
+                        FundingStreamReceiver::Ecc => "ECC",
+                        FundingStreamReceiver::ZcashFoundation => "ZcashFoundation",
+                        FundingStreamReceiver::MajorGrants => "MajorGrants",
+                        FundingStreamReceiver::Deferred => "Deferred",
+                    }
+                    .to_string(),
+                    numerator: recipient.numerator,
+                    addresses: recipient.addresses.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Zebrad block height range. The start height is inclusive and the end height is exclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ZebradHeightRange {
+    /// Start height
+    pub start: u32,
+    /// End height
+    pub end: u32,
+}
+
+/// Zebrad configured funding stream recipient
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZebradFundingStreamRecipient {
+    /// Funding stream receiver
+    pub receiver: String,
+    /// Share of the block subsidy in hundredths
+    pub numerator: u64,
+    /// Recipient addresses
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<String>,
+}
+
+/// Zebrad network upgrade activation heights
+///
+/// Configured activation heights must be greater than or equal to 1, block height 0 is reserved for the Genesis
+/// network upgrade in Zebra.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ZebradActivationHeights {
+    /// Overwinter activation height
+    #[serde(
+        rename = "Overwinter",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub overwinter: Option<u32>,
+    /// Sapling activation height
+    #[serde(rename = "Sapling", default, skip_serializing_if = "Option::is_none")]
+    pub sapling: Option<u32>,
+    /// Blossom activation height
+    #[serde(rename = "Blossom", default, skip_serializing_if = "Option::is_none")]
+    pub blossom: Option<u32>,
+    /// Heartwood activation height
+    #[serde(rename = "Heartwood", default, skip_serializing_if = "Option::is_none")]
+    pub heartwood: Option<u32>,
+    /// Canopy activation height
+    #[serde(rename = "Canopy", default, skip_serializing_if = "Option::is_none")]
+    pub canopy: Option<u32>,
+    /// NU5 activation height
+    #[serde(rename = "NU5", default, skip_serializing_if = "Option::is_none")]
+    pub nu5: Option<u32>,
+    /// NU6 activation height
+    #[serde(rename = "NU6", default, skip_serializing_if = "Option::is_none")]
+    pub nu6: Option<u32>,
+}
+
+/// Zebrad RPC section
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZebradRpc {
+    /// Cookie file directory
+    pub cookie_dir: PathBuf,
+    /// Report the node as synced regardless of sync status
+    pub debug_force_finished_sync: bool,
+    /// Enable cookie authentication
+    pub enable_cookie_auth: bool,
+    /// RPC thread count, `0` uses the number of CPU cores
+    pub parallel_cpu_threads: u32,
+    /// RPC listen address
+    pub listen_addr: String,
+}
+
+/// Zebrad state section
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZebradState {
+    /// State cache directory
+    pub cache_dir: PathBuf,
+    /// Delete databases from old state versions
+    pub delete_old_database: bool,
+    /// Use a temporary state which is deleted on shutdown.
+    ///
+    /// Set false to enable chain caching.
+    pub ephemeral: bool,
+}
+
+/// Zebrad sync section
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZebradSync {
+    /// Checkpoint verification concurrency limit
+    pub checkpoint_verify_concurrency_limit: u32,
+    /// Block download concurrency limit
+    pub download_concurrency_limit: u32,
+    /// Full verification concurrency limit
+    pub full_verify_concurrency_limit: u32,
+    /// Sync thread count, `0` uses the number of CPU cores
+    pub parallel_cpu_threads: u32,
+}
+
+/// Zebrad tracing section
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZebradTracing {
+    /// Log buffer limit
+    pub buffer_limit: u32,
+    /// Force coloured logs
+    pub force_use_color: bool,
+    /// Use coloured logs
+    pub use_color: bool,
+    /// Log to journald
+    pub use_journald: bool,
+}
+
+/// Zebrad mining section
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZebradMining {
+    /// Mimic zcashd block template behaviour
+    pub debug_like_zcashd: bool,
+    /// Block reward address
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub miner_address: Option<String>,
+}
+
+/// Zainod config file model (zindexer.toml)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZainodToml {
+    /// Sets the TcpIngestor's status
+    pub tcp_active: bool,
+    /// TcpIngestors listen port
+    pub listen_port: Port,
+    /// Sets the NymIngestor's and NymDispatchers status
+    pub nym_active: bool,
+    /// Nym conf path used for micnet client conf
+    pub nym_conf_path: PathBuf,
+    /// LightWalletD listen port [DEPRECATED]
+    pub lightwalletd_port: Port,
+    /// Full node / validator listen port
+    pub zebrad_port: Port,
+    /// Full node username
+    pub node_user: String,
+    /// Full node password
+    pub node_password: String,
+    /// Maximum requests allowed in the request queue
+    pub max_queue_size: u32,
+    /// Maximum workers allowed in the worker pool
+    pub max_worker_pool_size: u32,
+    /// Minimum number of workers held in the worker pool when idle
+    pub idle_worker_pool_size: u32,
+}
+
+impl ConfigFile for ZainodToml {
+    fn read(path: &Path) -> Result<Self, ConfigError> {
+        toml::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+}
+
+/// Lightwalletd config file model (lightwalletd.yml)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LightwalletdYml {
+    /// gRPC listen address
+    pub grpc_bind_addr: String,
+    /// Number of blocks to cache
+    pub cache_size: u32,
+    /// Log file location
+    pub log_file: PathBuf,
+    /// Log level
+    pub log_level: u32,
+    /// Zcashd config file location
+    pub zcash_conf_path: PathBuf,
+}
+
+impl ConfigFile for LightwalletdYml {
+    fn read(path: &Path) -> Result<Self, ConfigError> {
+        serde_yaml::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+}
+
+/// Declarative [`crate::LocalNet`] config file model
+///
+/// Describes the validator and indexer of a local network in a single TOML file which can be loaded with
+/// `LocalNet::launch_from_file`. The `kind` of each process selects its config section:
+///
+/// ```toml
+/// [validator]
+/// kind = "zebrad"
+/// network = "regtest"
+/// miner_address = "t27eWDgjFYJGVXmzrXeVjnb5J3uXDM9xH9v"
+///
+/// [validator.activation_heights]
+/// nu5 = 2
+///
+/// [validator.extra_options.mempool]
+/// tx_cost_limit = 1000
+///
+/// [validator.funding_streams.post_nu6]
+/// start_height = 2
+/// end_height = 100
+///
+/// [[validator.funding_streams.post_nu6.recipients]]
+/// receiver = "major_grants"
+/// numerator = 8
+/// addresses = ["t27eWDgjFYJGVXmzrXeVjnb5J3uXDM9xH9v"]
+///
+/// [indexer]
+/// kind = "zainod"
+/// bin = "/usr/local/bin/zainod"
+/// ```
+///
+/// The validator port and config path used by the indexer are always taken from the launched validator.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LocalNetToml {
+    /// Validator section
+    pub validator: ValidatorToml,
+    /// Indexer section
+    pub indexer: IndexerToml,
+}
+
+impl LocalNetToml {
+    /// Returns the Zcashd launch config. Returns an error if the validator is not Zcashd.
+    pub fn zcashd_config(&self) -> Result<ZcashdConfig, ConfigError> {
+        match &self.validator {
+            ValidatorToml::Zcashd(config) => Ok(ZcashdConfig {
+                zcashd_bin: config.bin.clone(),
+                zcash_cli_bin: config.cli_bin.clone(),
+                rpc_port: config.rpc_port,
+                activation_heights: config.activation_heights.into(),
+                funding_streams: config.funding_streams.funding_streams(Process::Zcashd)?,
+                miner_address: config.miner_address.as_deref().map(intern_str),
+                chain_cache: config.chain_cache.clone(),
+                extra_options: config.extra_options.clone(),
+                extra_args: config.extra_args.clone(),
+            }),
+            validator => Err(process_mismatch(Process::Zcashd, validator.process())),
+        }
+    }
+
+    /// Returns the Zebrad launch config. Returns an error if the validator is not Zebrad.
+    pub fn zebrad_config(&self) -> Result<ZebradConfig, ConfigError> {
+        match &self.validator {
+            ValidatorToml::Zebrad(config) => Ok(ZebradConfig {
+                zebrad_bin: config.bin.clone(),
+                network_listen_port: config.network_listen_port,
+                rpc_listen_port: config.rpc_listen_port,
+                activation_heights: config.activation_heights.into(),
+                funding_streams: config.funding_streams.funding_streams(Process::Zebrad)?,
+                miner_address: config
+                    .miner_address
+                    .as_deref()
+                    .map_or(ZEBRAD_DEFAULT_MINER, intern_str),
+                chain_cache: config.chain_cache.clone(),
+                network: config.network,
+                offline: config.offline,
+                extra_options: config.extra_options.clone(),
+                extra_args: config.extra_args.clone(),
+            }),
+            validator => Err(process_mismatch(Process::Zebrad, validator.process())),
+        }
+    }
+
+    /// Returns the Zainod launch config. Returns an error if the indexer is not Zainod.
+    ///
+    /// The `validator_port` is set to `0` and is overwritten when the local network is launched.
+    pub fn zainod_config(&self) -> Result<ZainodConfig, ConfigError> {
+        match &self.indexer {
+            IndexerToml::Zainod(config) => Ok(ZainodConfig {
+                zainod_bin: config.bin.clone(),
+                listen_port: config.listen_port,
+                validator_port: 0,
+                extra_options: config.extra_options.clone(),
+                extra_args: config.extra_args.clone(),
+            }),
+            indexer => Err(process_mismatch(Process::Zainod, indexer.process())),
+        }
+    }
+
+    /// Returns the Lightwalletd launch config. Returns an error if the indexer is not Lightwalletd.
+    ///
+    /// The `zcashd_conf` is left empty and is overwritten when the local network is launched.
+    pub fn lightwalletd_config(&self) -> Result<LightwalletdConfig, ConfigError> {
+        match &self.indexer {
+            IndexerToml::Lightwalletd(config) => Ok(LightwalletdConfig {
+                lightwalletd_bin: config.bin.clone(),
+                listen_port: config.listen_port,
+                zcashd_conf: PathBuf::new(),
+                extra_options: config.extra_options.clone(),
+                extra_args: config.extra_args.clone(),
+            }),
+            indexer => Err(process_mismatch(Process::Lightwalletd, indexer.process())),
+        }
+    }
+}
+
+impl ConfigFile for LocalNetToml {
+    fn read(path: &Path) -> Result<Self, ConfigError> {
+        toml::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+}
+
+/// Validator section of the [`LocalNetToml`]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ValidatorToml {
+    /// Zcashd validator
+    Zcashd(ZcashdLaunchToml),
+    /// Zebrad validator
+    Zebrad(ZebradLaunchToml),
+}
+
+impl ValidatorToml {
+    fn process(&self) -> Process {
+        match self {
+            Self::Zcashd(_) => Process::Zcashd,
+            Self::Zebrad(_) => Process::Zebrad,
+        }
+    }
+}
+
+/// Indexer section of the [`LocalNetToml`]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IndexerToml {
+    /// Zainod indexer
+    Zainod(ZainodLaunchToml),
+    /// Lightwalletd indexer
+    Lightwalletd(LightwalletdLaunchToml),
+}
+
+impl IndexerToml {
+    fn process(&self) -> Process {
+        match self {
+            Self::Zainod(_) => Process::Zainod,
+            Self::Lightwalletd(_) => Process::Lightwalletd,
+        }
+    }
+}
+
+/// Zcashd section of the [`LocalNetToml`]. See [`crate::validator::ZcashdConfig`].
+///
+/// `extra_options` are written as an array of `[key, value]` pairs so options can be repeated.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ZcashdLaunchToml {
+    /// Zcashd binary location
+    pub bin: Option<PathBuf>,
+    /// Zcash-cli binary location
+    pub cli_bin: Option<PathBuf>,
+    /// Zcashd RPC port
+    pub rpc_port: Option<Port>,
+    /// Local network upgrade activation heights
+    pub activation_heights: ActivationHeightsToml,
+    /// Regtest funding streams
+    pub funding_streams: FundingStreamsToml,
+    /// Miner address
+    pub miner_address: Option<String>,
+    /// Chain cache location
+    pub chain_cache: Option<PathBuf>,
+    /// Extra zcash.conf options as key/value pairs
+    pub extra_options: Vec<(String, String)>,
+    /// Extra command-line arguments
+    pub extra_args: Vec<String>,
+}
+
+/// Zebrad section of the [`LocalNetToml`]. See [`crate::validator::ZebradConfig`].
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ZebradLaunchToml {
+    /// Zebrad binary location
+    pub bin: Option<PathBuf>,
+    /// Zebrad network listen port
+    pub network_listen_port: Option<Port>,
+    /// Zebrad RPC listen port
+    pub rpc_listen_port: Option<Port>,
+    /// Local network upgrade activation heights
+    pub activation_heights: ActivationHeightsToml,
+    /// Regtest funding streams
+    pub funding_streams: FundingStreamsToml,
+    /// Miner address. Defaults to [`crate::validator::ZEBRAD_DEFAULT_MINER`].
+    pub miner_address: Option<String>,
+    /// Chain cache location
+    pub chain_cache: Option<PathBuf>,
+    /// Network type
+    pub network: Network,
+    /// Serve the cached chain without connecting to peers
+    pub offline: bool,
+    /// Extra zebrad.toml options
+    pub extra_options: toml::Table,
+    /// Extra command-line arguments
+    pub extra_args: Vec<String>,
+}
+
+impl Default for ZebradLaunchToml {
+    fn default() -> Self {
+        Self {
+            bin: None,
+            network_listen_port: None,
+            rpc_listen_port: None,
+            activation_heights: ActivationHeightsToml::default(),
+            funding_streams: FundingStreamsToml::default(),
+            miner_address: None,
+            chain_cache: None,
+            network: Network::Regtest,
+            offline: false,
+            extra_options: toml::Table::new(),
+            extra_args: Vec::new(),
+        }
+    }
+}
+
+/// Zainod section of the [`LocalNetToml`]. See [`crate::indexer::ZainodConfig`].
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ZainodLaunchToml {
+    /// Zainod binary location
+    pub bin: Option<PathBuf>,
+    /// Listen RPC port
+    pub listen_port: Option<Port>,
+    /// Extra zindexer.toml options
+    pub extra_options: toml::Table,
+    /// Extra command-line arguments
+    pub extra_args: Vec<String>,
+}
+
+/// Lightwalletd section of the [`LocalNetToml`]. See [`crate::indexer::LightwalletdConfig`].
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LightwalletdLaunchToml {
+    /// Lightwalletd binary location
+    pub bin: Option<PathBuf>,
+    /// Listen RPC port
+    pub listen_port: Option<Port>,
+    /// Extra lightwalletd.yml options
+    pub extra_options: serde_yaml::Mapping,
+    /// Extra command-line arguments
+    pub extra_args: Vec<String>,
+}
+
+/// Activation heights of the [`LocalNetToml`]. Heights which are not specified default to 1, except NU6 which is
+/// not activated unless specified.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ActivationHeightsToml {
+    /// Overwinter network upgrade activation height
+    pub overwinter: u32,
+    /// Sapling network upgrade activation height
+    pub sapling: u32,
+    /// Blossom network upgrade activation height
+    pub blossom: u32,
+    /// Heartwood network upgrade activation height
+    pub heartwood: u32,
+    /// Canopy network upgrade activation height
+    pub canopy: u32,
+    /// Nu5 (a.k.a. Orchard) network upgrade activation height
+    pub nu5: u32,
+    /// Nu6 network upgrade activation height. If not specified, NU6 is not activated.
+    pub nu6: Option<u32>,
+}
+
+impl Default for ActivationHeightsToml {
+    fn default() -> Self {
+        Self {
+            overwinter: 1,
+            sapling: 1,
+            blossom: 1,
+            heartwood: 1,
+            canopy: 1,
+            nu5: 1,
+            nu6: None,
+        }
+    }
+}
+
+impl From<ActivationHeights> for ActivationHeightsToml {
+    fn from(heights: ActivationHeights) -> Self {
+        Self {
+            overwinter: heights.overwinter.into(),
+            sapling: heights.sapling.into(),
+            blossom: heights.blossom.into(),
+            heartwood: heights.heartwood.into(),
+            canopy: heights.canopy.into(),
+            nu5: heights.nu5.into(),
+            nu6: heights.nu6.map(Into::into),
+        }
+    }
+}
+
+impl From<ActivationHeightsToml> for ActivationHeights {
+    fn from(heights: ActivationHeightsToml) -> Self {
+        Self {
+            overwinter: heights.overwinter.into(),
+            sapling: heights.sapling.into(),
+            blossom: heights.blossom.into(),
+            heartwood: heights.heartwood.into(),
+            canopy: heights.canopy.into(),
+            nu5: heights.nu5.into(),
+            nu6: heights.nu6.map(Into::into),
+        }
+    }
+}
+
+/// Funding streams of the [`LocalNetToml`]. See [`crate::network::FundingStreams`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FundingStreamsToml {
+    /// Funding streams before NU6 activation (ZIP 214)
+    pub pre_nu6: Option<FundingStreamPeriodToml>,
+    /// Funding streams and lockbox contribution after NU6 activation (ZIP 1015)
+    pub post_nu6: Option<FundingStreamPeriodToml>,
+}
+
+impl FundingStreamsToml {
+    /// Returns the funding streams of the `process`. Returns an error if a recipient has no numerator and the
+    /// receiver has no zcashd consensus numerator to default to.
+    fn funding_streams(&self, process: Process) -> Result<FundingStreams, ConfigError> {
+        let period = |name: &str, post_nu6: bool, period: &Option<FundingStreamPeriodToml>| {
+            period
+                .as_ref()
+                .map(|period| period.funding_stream_period(process, name, post_nu6))
+                .transpose()
+        };
+
+        Ok(FundingStreams {
+            pre_nu6: period("pre_nu6", false, &self.pre_nu6)?,
+            post_nu6: period("post_nu6", true, &self.post_nu6)?,
+        })
+    }
+}
+
+impl From<&FundingStreams> for FundingStreamsToml {
+    fn from(funding_streams: &FundingStreams) -> Self {
+        Self {
+            pre_nu6: funding_streams.pre_nu6.as_ref().map(Into::into),
+            post_nu6: funding_streams.post_nu6.as_ref().map(Into::into),
+        }
+    }
+}
+
+/// Funding stream period of the [`LocalNetToml`]. See [`crate::network::FundingStreamPeriod`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FundingStreamPeriodToml {
+    /// Start height (inclusive)
+    pub start_height: u32,
+    /// End height (exclusive)
+    pub end_height: u32,
+    /// Funding stream recipients
+    #[serde(default)]
+    pub recipients: Vec<FundingStreamRecipientToml>,
+}
+
+impl FundingStreamPeriodToml {
+    fn funding_stream_period(
+        &self,
+        process: Process,
+        name: &str,
+        post_nu6: bool,
+    ) -> Result<FundingStreamPeriod, ConfigError> {
+        let recipients = self
+            .recipients
+            .iter()
+            .map(|recipient| {
+                let numerator = recipient
+                    .numerator
+                    .or_else(|| {
+                        zcashd_funding_stream(post_nu6, recipient.receiver)
+                            .map(|(_, numerator)| numerator)
+                    })
+                    .ok_or_else(|| ConfigError::InvalidOption {
+                        process_name: process.to_string(),
+                        option: format!("funding_streams.{name}"),
+                        reason: format!(
+                            "{:?} stream has no default numerator so a numerator must be specified",
+                            recipient.receiver
+                        ),
+                    })?;
+
+                Ok(FundingStreamRecipient {
+                    receiver: recipient.receiver,
+                    numerator,
+                    addresses: recipient.addresses.clone(),
+                })
+            })
+            .collect::<Result<_, ConfigError>>()?;
+
+        Ok(FundingStreamPeriod {
+            height_range: self.start_height.into()..self.end_height.into(),
+            recipients,
+        })
+    }
+}
+
+impl From<&FundingStreamPeriod> for FundingStreamPeriodToml {
+    fn from(period: &FundingStreamPeriod) -> Self {
+        Self {
+            start_height: period.height_range.start.into(),
+            end_height: period.height_range.end.into(),
+            recipients: period
+                .recipients
+                .iter()
+                .map(|recipient| FundingStreamRecipientToml {
+                    receiver: recipient.receiver,
+                    numerator: Some(recipient.numerator),
+                    addresses: recipient.addresses.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Funding stream recipient of the [`LocalNetToml`]. See [`crate::network::FundingStreamRecipient`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FundingStreamRecipientToml {
+    /// Funding stream receiver, e.g. "major_grants"
+    pub receiver: FundingStreamReceiver,
+    /// Share of the block subsidy in hundredths. Defaults to the zcashd consensus numerator of the receiver.
+    pub numerator: Option<u64>,
+    /// Recipient addresses. Must be empty for the deferred lockbox stream.
+    #[serde(default)]
+    pub addresses: Vec<String>,
+}
+
+/// Launch configs take `&'static str` miner addresses so addresses loaded from file are leaked for the lifetime of
+/// the test process. Addresses are interned so each distinct address is only leaked once.
+fn intern_str(s: &str) -> &'static str {
+    static INTERNED: std::sync::Mutex<BTreeSet<&'static str>> =
+        std::sync::Mutex::new(BTreeSet::new());
+
+    let mut interned = INTERNED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(interned_str) = interned.get(s) {
+        return interned_str;
+    }
+    let leaked: &'static str = Box::leak(s.to_string().into_boxed_str());
+    interned.insert(leaked);
+    leaked
+}
+
+fn process_mismatch(expected: Process, found: Process) -> ConfigError {
+    ConfigError::ProcessMismatch {
+        expected: expected.to_string(),
+        found: found.to_string(),
+    }
+}
+
+/// Serializes the `config` to TOML with the `extra_options` merged in.
+///
+/// Tables are merged recursively, all other values in `extra_options` replace the generated defaults.
+/// Returns an error if an extra option does not match the type of the generated default.
+fn merged_toml<T: Serialize>(
+    process: Process,
+    config: &T,
+    extra_options: &toml::Table,
+) -> Result<String, ConfigError> {
+    let mut table =
+        toml::Table::try_from(config).expect("config model should serialize to a TOML table");
+    merge_toml_table(process, &mut table, extra_options, "")?;
+
+    Ok(toml::to_string(&table).expect("merged config should serialize to TOML"))
+}
+
+fn merge_toml_table(
+    process: Process,
+    table: &mut toml::Table,
+    extra_options: &toml::Table,
+    parent: &str,
+) -> Result<(), ConfigError> {
+    for (key, extra_value) in extra_options {
+        let option = if parent.is_empty() {
+            key.clone()
+        } else {
+            format!("{parent}.{key}")
+        };
+        match (table.get_mut(key), extra_value) {
+            (Some(toml::Value::Table(default)), toml::Value::Table(extra)) => {
+                merge_toml_table(process, default, extra, &option)?;
+            }
+            (Some(default), extra) if default.type_str() != extra.type_str() => {
+                return Err(ConfigError::InvalidOption {
+                    process_name: process.to_string(),
+                    option,
+                    reason: format!(
+                        "expected {}, found {}",
+                        default.type_str(),
+                        extra.type_str()
+                    ),
+                });
+            }
+            _ => {
+                table.insert(key.clone(), extra_value.clone());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Serializes the `config` to YAML with the `extra_options` merged in.
+///
+/// Options which are already set are replaced, all other options are appended.
+fn merged_yaml<T: Serialize>(
+    config: &T,
+    extra_options: &serde_yaml::Mapping,
+) -> Result<String, ConfigError> {
+    let mut mapping = match serde_yaml::to_value(config) {
+        Ok(serde_yaml::Value::Mapping(mapping)) => mapping,
+        _ => panic!("config model should serialize to a YAML mapping"),
+    };
+    for (key, value) in extra_options {
+        mapping.insert(key.clone(), value.clone());
+    }
+
+    Ok(serde_yaml::to_string(&mapping).expect("merged config should serialize to YAML"))
+}
+
 /// Writes the Zcashd config file to the specified config directory.
 /// Returns the path to the config file.
 pub(crate) fn zcashd(
     config_dir: &Path,
     rpc_port: Port,
     activation_heights: &ActivationHeights,
+    funding_streams: &FundingStreams,
     miner_address: Option<&str>,
-) -> std::io::Result<PathBuf> {
+    extra_options: &[(String, String)],
+) -> Result<PathBuf, ConfigError> {
     let config_file_path = config_dir.join(ZCASHD_FILENAME);
-    let mut config_file = File::create(config_file_path.clone())?;
-
-    let overwinter_activation_height = activation_heights.overwinter;
-    let sapling_activation_height = activation_heights.sapling;
-    let blossom_activation_height = activation_heights.blossom;
-    let heartwood_activation_height = activation_heights.heartwood;
-    let canopy_activation_height = activation_heights.canopy;
-    let nu5_activation_height = activation_heights.nu5;
 
-    config_file.write_all(format!("\
-### Blockchain Configuration
-regtest=1
-nuparams=5ba81b19:{overwinter_activation_height} # Overwinter
-nuparams=76b809bb:{sapling_activation_height} # Sapling
-nuparams=2bb40e60:{blossom_activation_height} # Blossom
-nuparams=f5b9230b:{heartwood_activation_height} # Heartwood
-nuparams=e9ff75a6:{canopy_activation_height} # Canopy
-nuparams=c2d6d0b4:{nu5_activation_height} # NU5 (Orchard)
-
-### MetaData Storage and Retrieval
-# txindex:
-# https://zcash.readthedocs.io/en/latest/rtd_pages/zcash_conf_guide.html#miscellaneous-options
-txindex=1
-# insightexplorer:
-# https://zcash.readthedocs.io/en/latest/rtd_pages/insight_explorer.html?highlight=insightexplorer#additional-getrawtransaction-fields
-insightexplorer=1
-experimentalfeatures=1
-lightwalletd=1
-
-### RPC Server Interface Options:
-# https://zcash.readthedocs.io/en/latest/rtd_pages/zcash_conf_guide.html#json-rpc-options
-rpcuser=xxxxxx
-rpcpassword=xxxxxx
-rpcport={rpc_port}
-rpcallowip=127.0.0.1
-
-# Buried config option to allow non-canonical RPC-PORT:
-# https://zcash.readthedocs.io/en/latest/rtd_pages/zcash_conf_guide.html#zcash-conf-guide
-listen=0"
-            ).as_bytes())?;
-
-    if let Some(addr) = miner_address {
-        config_file.write_all(
-
-                format!("\n\n\
-### Zcashd Help provides documentation of the following:
-mineraddress={addr}
-minetolocalwallet=0 # This is set to false so that we can mine to a wallet, other than the zcashd wallet."
-                ).as_bytes()
-        )?;
+    let mut config = ZcashConf {
+        regtest: true,
+        nuparams: vec![
+            NuParams {
+                branch_id: 0x5ba81b19,
+                activation_height: activation_heights.overwinter.into(),
+            },
+            NuParams {
+                branch_id: 0x76b809bb,
+                activation_height: activation_heights.sapling.into(),
+            },
+            NuParams {
+                branch_id: 0x2bb40e60,
+                activation_height: activation_heights.blossom.into(),
+            },
+            NuParams {
+                branch_id: 0xf5b9230b,
+                activation_height: activation_heights.heartwood.into(),
+            },
+            NuParams {
+                branch_id: 0xe9ff75a6,
+                activation_height: activation_heights.canopy.into(),
+            },
+            NuParams {
+                branch_id: 0xc2d6d0b4,
+                activation_height: activation_heights.nu5.into(),
+            },
+        ],
+        fundingstream: zcashd_funding_streams(funding_streams)?,
+        // https://zcash.readthedocs.io/en/latest/rtd_pages/zcash_conf_guide.html#miscellaneous-options
+        txindex: true,
+        // https://zcash.readthedocs.io/en/latest/rtd_pages/insight_explorer.html?highlight=insightexplorer#additional-getrawtransaction-fields
+        insightexplorer: true,
+        experimentalfeatures: true,
+        lightwalletd: true,
+        // https://zcash.readthedocs.io/en/latest/rtd_pages/zcash_conf_guide.html#json-rpc-options
+        rpcuser: ZCASHD_RPC_CREDENTIALS.to_string(),
+        rpcpassword: ZCASHD_RPC_CREDENTIALS.to_string(),
+        rpcport: rpc_port,
+        rpcallowip: "127.0.0.1".to_string(),
+        // Buried config option to allow non-canonical RPC-PORT:
+        // https://zcash.readthedocs.io/en/latest/rtd_pages/zcash_conf_guide.html#zcash-conf-guide
+        listen: false,
+        mineraddress: miner_address.map(|addr| addr.to_string()),
+        // This is set to false so that we can mine to a wallet, other than the zcashd wallet.
+        minetolocalwallet: miner_address.map(|_| false),
+        extra_options: Vec::new(),
+    };
+    if let Some(nu6) = activation_heights.nu6 {
+        config.nuparams.push(NuParams {
+            branch_id: 0xc8e71055,
+            activation_height: nu6.into(),
+        });
     }
+    config.merge(extra_options)?;
+    std::fs::write(&config_file_path, config.to_string())?;
 
     Ok(config_file_path)
 }
 
+/// Maps the funding stream receivers to zcashd funding stream indices. See [`zcashd_funding_stream`].
+fn zcashd_funding_streams(
+    funding_streams: &FundingStreams,
+) -> Result<Vec<ZcashdFundingStream>, ConfigError> {
+    let mut fundingstream = Vec::new();
+    let periods = [
+        ("pre_nu6", false, &funding_streams.pre_nu6),
+        ("post_nu6", true, &funding_streams.post_nu6),
+    ];
+    for (name, post_nu6, period) in periods {
+        let Some(period) = period else {
+            continue;
+        };
+        for recipient in &period.recipients {
+            let Some((stream_id, _)) = zcashd_funding_stream(post_nu6, recipient.receiver) else {
+                return Err(ConfigError::InvalidOption {
+                    process_name: Process::Zcashd.to_string(),
+                    option: format!("funding_streams.{name}"),
+                    reason: format!(
+                        "zcashd does not support a {name} {:?} stream",
+                        recipient.receiver
+                    ),
+                });
+            };
+            fundingstream.push(ZcashdFundingStream {
+                stream_id,
+                start_height: period.height_range.start.into(),
+                end_height: period.height_range.end.into(),
+                addresses: recipient.addresses.clone(),
+            });
+        }
+    }
+
+    Ok(fundingstream)
+}
+
 /// Writes the Zebrad config file to the specified config directory.
 /// Returns the path to the config file.
 ///
-/// Canopy (and all earlier netwrok upgrades) must have an activation height of 1 for zebrad regtest mode
+/// The `activation_heights` should be checked with [`validate_activation_heights`] before writing the config.
+///
+/// If `offline` is true, the initial peer lists are left empty, peer crawling is effectively disabled and zebrad
+/// reports the chain as synced so cached chains are served without network access.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn zebrad(
     config_dir: PathBuf,
     cache_dir: PathBuf,
     network_listen_port: Port,
     rpc_listen_port: Port,
     activation_heights: &ActivationHeights,
+    funding_streams: &FundingStreams,
     miner_address: &str,
     network: Network,
-) -> std::io::Result<PathBuf> {
+    offline: bool,
+    extra_options: &toml::Table,
+) -> Result<PathBuf, ConfigError> {
     let config_file_path = config_dir.join(ZEBRAD_FILENAME);
-    let mut config_file = File::create(config_file_path.clone())?;
-
-    if activation_heights.canopy != 1.into() {
-        panic!("canopy must be active for zebrad regtest mode. please set activation height to 1");
-    }
-    let nu5_activation_height: u32 = activation_heights.nu5.into();
-
-    let chain_cache = cache_dir.to_str().unwrap();
-
-    let network_string = network.to_string();
-
-    config_file.write_all(
-        format!(
-            "\
-[consensus]
-checkpoint_sync = true
-
-[mempool]
-eviction_memory_time = \"1h\"
-tx_cost_limit = 80000000
-
-[metrics]
-
-[network]
-cache_dir = false
-crawl_new_peer_interval = \"1m 1s\"
-initial_mainnet_peers = [
-    \"dnsseed.z.cash:8233\",
-    \"dnsseed.str4d.xyz:8233\",
-    \"mainnet.seeder.zfnd.org:8233\",
-    \"mainnet.is.yolo.money:8233\",
-]
-initial_testnet_peers = [
-    \"dnsseed.testnet.z.cash:18233\",
-    \"testnet.seeder.zfnd.org:18233\",
-    \"testnet.is.yolo.money:18233\",
-]
-listen_addr = \"127.0.0.1:{network_listen_port}\"
-max_connections_per_ip = 1
-network = \"{network_string}\"
-peerset_initial_target_size = 25
-
-[rpc]
-cookie_dir = \"{chain_cache}\"
-debug_force_finished_sync = false
-enable_cookie_auth = false
-parallel_cpu_threads = 0
-listen_addr = \"127.0.0.1:{rpc_listen_port}\"
-
-[state]
-cache_dir = \"{chain_cache}\"
-delete_old_database = true
-# ephemeral is set false to enable chain caching
-ephemeral = false
-
-[sync]
-checkpoint_verify_concurrency_limit = 1000
-download_concurrency_limit = 50
-full_verify_concurrency_limit = 20
-parallel_cpu_threads = 0
-
-[tracing]
-buffer_limit = 128000
-force_use_color = false
-use_color = true
-use_journald = false"
-        )
-        .as_bytes(),
-    )?;
 
-    if matches!(network, Network::Regtest) {
-        config_file.write_all(
-            format!(
-                "\n\n\
-[mining]
-debug_like_zcashd = true
-miner_address = \"{miner_address}\"
-
-[network.testnet_parameters]
-disable_pow = true
-
-[network.testnet_parameters.activation_heights]
-# Configured activation heights must be greater than or equal to 1,
-# block height 0 is reserved for the Genesis network upgrade in Zebra
-NU5 = {nu5_activation_height}"
-            )
-            .as_bytes(),
-        )?;
+    let (initial_mainnet_peers, initial_testnet_peers, crawl_new_peer_interval) = if offline {
+        (Vec::new(), Vec::new(), ZEBRAD_OFFLINE_CRAWL_INTERVAL)
     } else {
-        config_file.write_all(
-            format!(
-                "\n\n\
-[mining]
-debug_like_zcashd = true"
-            )
-            .as_bytes(),
-        )?;
-    }
+        (
+            ZEBRAD_MAINNET_PEERS.map(String::from).to_vec(),
+            ZEBRAD_TESTNET_PEERS.map(String::from).to_vec(),
+            "1m 1s",
+        )
+    };
+
+    let regtest = matches!(network, Network::Regtest);
+    let config = ZebradToml {
+        consensus: ZebradConsensus {
+            checkpoint_sync: true,
+        },
+        mempool: ZebradMempool {
+            eviction_memory_time: "1h".to_string(),
+            tx_cost_limit: 80_000_000,
+        },
+        metrics: toml::Table::new(),
+        network: ZebradNetwork {
+            cache_dir: false,
+            crawl_new_peer_interval: crawl_new_peer_interval.to_string(),
+            initial_mainnet_peers,
+            initial_testnet_peers,
+            listen_addr: format!("127.0.0.1:{network_listen_port}"),
+            max_connections_per_ip: 1,
+            network: network.to_string(),
+            peerset_initial_target_size: 25,
+            testnet_parameters: regtest.then(|| ZebradTestnetParameters {
+                disable_pow: true,
+                activation_heights: ZebradActivationHeights {
+                    overwinter: Some(activation_heights.overwinter.into()),
+                    sapling: Some(activation_heights.sapling.into()),
+                    blossom: Some(activation_heights.blossom.into()),
+                    heartwood: Some(activation_heights.heartwood.into()),
+                    canopy: Some(activation_heights.canopy.into()),
+                    nu5: Some(activation_heights.nu5.into()),
+                    nu6: activation_heights.nu6.map(u32::from),
+                },
+                pre_nu6_funding_streams: funding_streams.pre_nu6.as_ref().map(Into::into),
+                post_nu6_funding_streams: funding_streams.post_nu6.as_ref().map(Into::into),
+            }),
+        },
+        rpc: ZebradRpc {
+            cookie_dir: cache_dir.clone(),
+            debug_force_finished_sync: offline,
+            enable_cookie_auth: false,
+            parallel_cpu_threads: 0,
+            listen_addr: format!("127.0.0.1:{rpc_listen_port}"),
+        },
+        state: ZebradState {
+            cache_dir,
+            // chain caches may be shared between zebrad versions
+            delete_old_database: false,
+            ephemeral: false,
+        },
+        sync: ZebradSync {
+            checkpoint_verify_concurrency_limit: 1000,
+            download_concurrency_limit: 50,
+            full_verify_concurrency_limit: 20,
+            parallel_cpu_threads: 0,
+        },
+        tracing: ZebradTracing {
+            buffer_limit: 128_000,
+            force_use_color: false,
+            use_color: true,
+            use_journald: false,
+        },
+        mining: ZebradMining {
+            debug_like_zcashd: true,
+            miner_address: regtest.then(|| miner_address.to_string()),
+        },
+    };
+    std::fs::write(
+        &config_file_path,
+        merged_toml(Process::Zebrad, &config, extra_options)?,
+    )?;
 
     Ok(config_file_path)
 }
@@ -199,49 +1743,26 @@ pub(crate) fn zainod(
     config_dir: &Path,
     listen_port: Port,
     validator_port: Port,
-) -> std::io::Result<PathBuf> {
+    extra_options: &toml::Table,
+) -> Result<PathBuf, ConfigError> {
     let config_file_path = config_dir.join(ZAINOD_FILENAME);
-    let mut config_file = File::create(config_file_path.clone())?;
-
-    config_file.write_all(
-        format!(
-            "\
-# Configuration for Zaino
-
-# Sets the TcpIngestor's status (true or false)
-tcp_active = true
-
-# Optional TcpIngestors listen port (use None or specify a port number)
-listen_port = {listen_port}
-
-# Sets the NymIngestor's and NymDispatchers status (true or false)
-nym_active = false
-
-# Optional Nym conf path used for micnet client conf
-nym_conf_path = \"/tmp/indexer/nym\"
-
-# LightWalletD listen port [DEPRECATED]
-lightwalletd_port = 9067
 
-# Full node / validator listen port
-zebrad_port = {validator_port}
-
-# Optional full node Username
-node_user = \"xxxxxx\"
-
-# Optional full node Password
-node_password = \"xxxxxx\"
-
-# Maximum requests allowed in the request queue
-max_queue_size = 1024
-
-# Maximum workers allowed in the worker pool
-max_worker_pool_size = 64
-
-# Minimum number of workers held in the worker pool when idle
-idle_worker_pool_size = 4"
-        )
-        .as_bytes(),
+    let config = ZainodToml {
+        tcp_active: true,
+        listen_port,
+        nym_active: false,
+        nym_conf_path: PathBuf::from("/tmp/indexer/nym"),
+        lightwalletd_port: 9067,
+        zebrad_port: validator_port,
+        node_user: ZCASHD_RPC_CREDENTIALS.to_string(),
+        node_password: ZCASHD_RPC_CREDENTIALS.to_string(),
+        max_queue_size: 1024,
+        max_worker_pool_size: 64,
+        idle_worker_pool_size: 4,
+    };
+    std::fs::write(
+        &config_file_path,
+        merged_toml(Process::Zainod, &config, extra_options)?,
     )?;
 
     Ok(config_file_path)
@@ -249,30 +1770,23 @@ idle_worker_pool_size = 4"
 
 /// Writes the Lightwalletd config file to the specified config directory.
 /// Returns the path to the config file.
-#[allow(dead_code)]
 pub(crate) fn lightwalletd(
     config_dir: &Path,
     grpc_bind_addr_port: Port,
     log_file: PathBuf,
     validator_conf: PathBuf,
-) -> std::io::Result<PathBuf> {
-    let validator_conf = validator_conf.to_str().unwrap();
-    let log_file = log_file.to_str().unwrap();
-
+    extra_options: &serde_yaml::Mapping,
+) -> Result<PathBuf, ConfigError> {
     let config_file_path = config_dir.join(LIGHTWALLETD_FILENAME);
-    let mut config_file = File::create(config_file_path.clone())?;
-
-    config_file.write_all(
-        format!(
-            "\
-grpc-bind-addr: 127.0.0.1:{grpc_bind_addr_port}
-cache-size: 10
-log-file: {log_file}
-log-level: 10
-zcash-conf-path: {validator_conf}"
-        )
-        .as_bytes(),
-    )?;
+
+    let config = LightwalletdYml {
+        grpc_bind_addr: format!("127.0.0.1:{grpc_bind_addr_port}"),
+        cache_size: 10,
+        log_file,
+        log_level: 10,
+        zcash_conf_path: validator_conf,
+    };
+    std::fs::write(&config_file_path, merged_yaml(&config, extra_options)?)?;
 
     Ok(config_file_path)
 }
@@ -281,7 +1795,13 @@ zcash-conf-path: {validator_conf}"
 mod tests {
     use std::path::PathBuf;
 
-    use crate::{logs, network};
+    use crate::{error::ConfigError, logs, network, Process};
+
+    use super::{
+        ConfigFile as _, LightwalletdYml, LocalNetToml, NuParams, ZainodToml, ZcashConf,
+        ZcashdFundingStream, ZebradActivationHeights, ZebradFundingStreamRecipient,
+        ZebradFundingStreams, ZebradHeightRange, ZebradToml,
+    };
 
     #[test]
     fn zcashd() {
@@ -293,152 +1813,579 @@ mod tests {
             heartwood: 4.into(),
             canopy: 5.into(),
             nu5: 6.into(),
+            nu6: Some(7.into()),
         };
 
-        super::zcashd(config_dir.path(), 1234, &activation_heights, None).unwrap();
+        let config_path = super::zcashd(
+            config_dir.path(),
+            1234,
+            &activation_heights,
+            &network::FundingStreams::default(),
+            None,
+            &[],
+        )
+        .unwrap();
+        let config = ZcashConf::read(&config_path).unwrap();
+
+        assert!(config.regtest);
+        assert_eq!(
+            config.nuparams,
+            vec![
+                NuParams {
+                    branch_id: 0x5ba81b19,
+                    activation_height: 1
+                },
+                NuParams {
+                    branch_id: 0x76b809bb,
+                    activation_height: 2
+                },
+                NuParams {
+                    branch_id: 0x2bb40e60,
+                    activation_height: 3
+                },
+                NuParams {
+                    branch_id: 0xf5b9230b,
+                    activation_height: 4
+                },
+                NuParams {
+                    branch_id: 0xe9ff75a6,
+                    activation_height: 5
+                },
+                NuParams {
+                    branch_id: 0xc2d6d0b4,
+                    activation_height: 6
+                },
+                NuParams {
+                    branch_id: 0xc8e71055,
+                    activation_height: 7
+                },
+            ]
+        );
+        assert!(config.txindex);
+        assert!(config.insightexplorer);
+        assert!(config.lightwalletd);
+        assert_eq!(config.rpcport, 1234);
+        assert_eq!(config.rpcuser, "xxxxxx");
+        assert_eq!(config.rpcpassword, "xxxxxx");
+        assert!(!config.listen);
+        assert_eq!(config.mineraddress, None);
+        assert_eq!(config.minetolocalwallet, None);
+        assert!(config.extra_options.is_empty());
+    }
+
+    #[test]
+    fn zcashd_funded() {
+        let config_dir = tempfile::tempdir().unwrap();
+
+        let config_path = super::zcashd(
+            config_dir.path(),
+            1234,
+            &network::ActivationHeights::default(),
+            &network::FundingStreams::default(),
+            Some("test_addr_1234"),
+            &[],
+        )
+        .unwrap();
+        let config = ZcashConf::read(&config_path).unwrap();
+
+        assert_eq!(config.mineraddress, Some("test_addr_1234".to_string()));
+        assert_eq!(config.minetolocalwallet, Some(false));
+        assert!(config
+            .nuparams
+            .iter()
+            .all(|nuparams| nuparams.branch_id != 0xc8e71055));
+    }
 
-        assert_eq!(std::fs::read_to_string(config_dir.path().join(super::ZCASHD_FILENAME)).unwrap(),
-                        format!("\
+    #[test]
+    fn zcash_conf_round_trip() {
+        let config: ZcashConf = "\
 ### Blockchain Configuration
 regtest=1
-nuparams=5ba81b19:1 # Overwinter
-nuparams=76b809bb:2 # Sapling
-nuparams=2bb40e60:3 # Blossom
-nuparams=f5b9230b:4 # Heartwood
-nuparams=e9ff75a6:5 # Canopy
 nuparams=c2d6d0b4:6 # NU5 (Orchard)
 
-### MetaData Storage and Retrieval
-# txindex:
-# https://zcash.readthedocs.io/en/latest/rtd_pages/zcash_conf_guide.html#miscellaneous-options
-txindex=1
-# insightexplorer:
-# https://zcash.readthedocs.io/en/latest/rtd_pages/insight_explorer.html?highlight=insightexplorer#additional-getrawtransaction-fields
-insightexplorer=1
-experimentalfeatures=1
-lightwalletd=1
-
-### RPC Server Interface Options:
-# https://zcash.readthedocs.io/en/latest/rtd_pages/zcash_conf_guide.html#json-rpc-options
-rpcuser=xxxxxx
-rpcpassword=xxxxxx
+rpcuser=user
+rpcpassword=password
 rpcport=1234
-rpcallowip=127.0.0.1
+addnode=127.0.0.1:18344"
+            .parse()
+            .unwrap();
+
+        assert!(config.regtest);
+        assert_eq!(
+            config.nuparams,
+            vec![NuParams {
+                branch_id: 0xc2d6d0b4,
+                activation_height: 6
+            }]
+        );
+        assert_eq!(config.rpcuser, "user");
+        assert_eq!(
+            config.extra_options,
+            vec![("addnode".to_string(), "127.0.0.1:18344".to_string())]
+        );
+        assert_eq!(config.to_string().parse::<ZcashConf>().unwrap(), config);
+    }
+
+    #[test]
+    fn zcash_conf_read_rpcport() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let config_path = config_dir.path().join("zcash.conf");
+        // zcash.conf files of other validators may not include the options required by the model
+        std::fs::write(
+            &config_path,
+            "rpcport=1234\nrpcbind=127.0.0.1\nrpcport=5678 # override\n",
+        )
+        .unwrap();
+
+        assert!(ZcashConf::read(&config_path).is_err());
+        assert_eq!(ZcashConf::read_rpcport(&config_path).unwrap(), 5678);
+
+        std::fs::write(&config_path, "rpcuser=user\n").unwrap();
+        assert!(matches!(
+            ZcashConf::read_rpcport(&config_path),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn zebrad() {
+        let config_dir = tempfile::tempdir().unwrap();
+
+        let config_path = super::zebrad(
+            config_dir.path().to_path_buf(),
+            PathBuf::from("cache_dir"),
+            1234,
+            5678,
+            &network::ActivationHeights::default(),
+            &network::FundingStreams::default(),
+            "test_addr_1234",
+            network::Network::Regtest,
+            false,
+            &toml::Table::new(),
+        )
+        .unwrap();
+        let config = ZebradToml::read(&config_path).unwrap();
+
+        assert_eq!(config.network.network, "Regtest");
+        assert_eq!(config.network.listen_addr, "127.0.0.1:1234");
+        assert_eq!(config.rpc.listen_addr, "127.0.0.1:5678");
+        assert_eq!(config.state.cache_dir, PathBuf::from("cache_dir"));
+        assert!(!config.state.ephemeral);
+        assert!(!config.state.delete_old_database);
+        assert_eq!(
+            config.mining.miner_address,
+            Some("test_addr_1234".to_string())
+        );
+        let testnet_parameters = config.network.testnet_parameters.unwrap();
+        assert!(testnet_parameters.disable_pow);
+        assert_eq!(
+            testnet_parameters.activation_heights,
+            ZebradActivationHeights {
+                overwinter: Some(1),
+                sapling: Some(1),
+                blossom: Some(1),
+                heartwood: Some(1),
+                canopy: Some(1),
+                nu5: Some(1),
+                nu6: None,
+            }
+        );
+    }
+
+    #[test]
+    fn zebrad_nu6() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let activation_heights = network::ActivationHeights {
+            nu5: 2.into(),
+            nu6: Some(3.into()),
+            ..Default::default()
+        };
 
-# Buried config option to allow non-canonical RPC-PORT:
-# https://zcash.readthedocs.io/en/latest/rtd_pages/zcash_conf_guide.html#zcash-conf-guide
-listen=0"
-                        )
+        let config_path = super::zebrad(
+            config_dir.path().to_path_buf(),
+            PathBuf::from("cache_dir"),
+            1234,
+            5678,
+            &activation_heights,
+            &network::FundingStreams::default(),
+            "test_addr_1234",
+            network::Network::Regtest,
+            false,
+            &toml::Table::new(),
+        )
+        .unwrap();
+        let config = ZebradToml::read(&config_path).unwrap();
 
+        assert_eq!(
+            config
+                .network
+                .testnet_parameters
+                .unwrap()
+                .activation_heights,
+            ZebradActivationHeights {
+                overwinter: Some(1),
+                sapling: Some(1),
+                blossom: Some(1),
+                heartwood: Some(1),
+                canopy: Some(1),
+                nu5: Some(2),
+                nu6: Some(3),
+            }
+        );
+        let config: toml::Table = std::fs::read_to_string(config_path)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            config["network"]["testnet_parameters"]["activation_heights"]["NU6"].as_integer(),
+            Some(3)
         );
     }
 
     #[test]
-    fn zcashd_funded() {
+    fn zebrad_activation_heights() {
         let config_dir = tempfile::tempdir().unwrap();
         let activation_heights = network::ActivationHeights {
             overwinter: 1.into(),
-            sapling: 2.into(),
+            sapling: 1.into(),
             blossom: 3.into(),
             heartwood: 4.into(),
             canopy: 5.into(),
             nu5: 6.into(),
+            nu6: None,
         };
+        super::validate_activation_heights(Process::Zebrad, &activation_heights).unwrap();
 
-        super::zcashd(
-            config_dir.path(),
+        let config_path = super::zebrad(
+            config_dir.path().to_path_buf(),
+            PathBuf::from("cache_dir"),
             1234,
+            5678,
             &activation_heights,
-            Some("test_addr_1234"),
+            &network::FundingStreams::default(),
+            "test_addr_1234",
+            network::Network::Regtest,
+            false,
+            &toml::Table::new(),
         )
         .unwrap();
+        let config = ZebradToml::read(&config_path).unwrap();
 
-        assert_eq!(std::fs::read_to_string(config_dir.path().join(super::ZCASHD_FILENAME)).unwrap(),
-                        format!("\
-### Blockchain Configuration
-regtest=1
-nuparams=5ba81b19:1 # Overwinter
-nuparams=76b809bb:2 # Sapling
-nuparams=2bb40e60:3 # Blossom
-nuparams=f5b9230b:4 # Heartwood
-nuparams=e9ff75a6:5 # Canopy
-nuparams=c2d6d0b4:6 # NU5 (Orchard)
+        assert_eq!(
+            config
+                .network
+                .testnet_parameters
+                .unwrap()
+                .activation_heights,
+            ZebradActivationHeights {
+                overwinter: Some(1),
+                sapling: Some(1),
+                blossom: Some(3),
+                heartwood: Some(4),
+                canopy: Some(5),
+                nu5: Some(6),
+                nu6: None,
+            }
+        );
+    }
 
-### MetaData Storage and Retrieval
-# txindex:
-# https://zcash.readthedocs.io/en/latest/rtd_pages/zcash_conf_guide.html#miscellaneous-options
-txindex=1
-# insightexplorer:
-# https://zcash.readthedocs.io/en/latest/rtd_pages/insight_explorer.html?highlight=insightexplorer#additional-getrawtransaction-fields
-insightexplorer=1
-experimentalfeatures=1
-lightwalletd=1
-
-### RPC Server Interface Options:
-# https://zcash.readthedocs.io/en/latest/rtd_pages/zcash_conf_guide.html#json-rpc-options
-rpcuser=xxxxxx
-rpcpassword=xxxxxx
-rpcport=1234
-rpcallowip=127.0.0.1
+    #[test]
+    fn invalid_activation_heights() {
+        let non_monotonic = network::ActivationHeights {
+            canopy: 3.into(),
+            nu5: 2.into(),
+            ..Default::default()
+        };
+        for process in [Process::Zcashd, Process::Zebrad] {
+            assert!(matches!(
+                super::validate_activation_heights(process, &non_monotonic),
+                Err(ConfigError::InvalidOption { option, .. }) if option == "activation_heights.nu5"
+            ));
+        }
+
+        let nu6_before_nu5 = network::ActivationHeights {
+            nu5: 3.into(),
+            nu6: Some(2.into()),
+            ..Default::default()
+        };
+        assert!(super::validate_activation_heights(Process::Zcashd, &nu6_before_nu5).is_err());
+
+        let genesis = network::ActivationHeights {
+            overwinter: 0.into(),
+            ..Default::default()
+        };
+        assert!(super::validate_activation_heights(Process::Zcashd, &genesis).is_ok());
+        assert!(super::validate_activation_heights(Process::Zebrad, &genesis).is_err());
+
+        let overwinter_only = network::ActivationHeights {
+            overwinter: 1.into(),
+            sapling: 2.into(),
+            blossom: 2.into(),
+            heartwood: 2.into(),
+            canopy: 2.into(),
+            nu5: 2.into(),
+            nu6: None,
+        };
+        assert!(super::validate_activation_heights(Process::Zcashd, &overwinter_only).is_ok());
+        assert!(matches!(
+            super::validate_activation_heights(Process::Zebrad, &overwinter_only),
+            Err(ConfigError::InvalidOption { option, .. }) if option == "activation_heights.sapling"
+        ));
+    }
+
+    fn funding_streams() -> network::FundingStreams {
+        network::FundingStreams {
+            pre_nu6: Some(network::FundingStreamPeriod {
+                height_range: 2.into()..10.into(),
+                recipients: vec![network::FundingStreamRecipient {
+                    receiver: network::FundingStreamReceiver::ZcashFoundation,
+                    numerator: 5,
+                    addresses: vec!["zf_addr".to_string()],
+                }],
+            }),
+            post_nu6: Some(network::FundingStreamPeriod {
+                height_range: 10.into()..20.into(),
+                recipients: vec![
+                    network::FundingStreamRecipient {
+                        receiver: network::FundingStreamReceiver::MajorGrants,
+                        numerator: 8,
+                        addresses: vec!["mg_addr_1".to_string(), "mg_addr_2".to_string()],
+                    },
+                    network::FundingStreamRecipient {
+                        receiver: network::FundingStreamReceiver::Deferred,
+                        numerator: 12,
+                        addresses: Vec::new(),
+                    },
+                ],
+            }),
+        }
+    }
 
-# Buried config option to allow non-canonical RPC-PORT:
-# https://zcash.readthedocs.io/en/latest/rtd_pages/zcash_conf_guide.html#zcash-conf-guide
-listen=0
+    #[test]
+    fn zcashd_funding_streams() {
+        let config_dir = tempfile::tempdir().unwrap();
 
-### Zcashd Help provides documentation of the following:
-mineraddress=test_addr_1234
-minetolocalwallet=0 # This is set to false so that we can mine to a wallet, other than the zcashd wallet."
-                        )
+        let config_path = super::zcashd(
+            config_dir.path(),
+            1234,
+            &network::ActivationHeights::default(),
+            &funding_streams(),
+            None,
+            &[],
+        )
+        .unwrap();
+        let config = ZcashConf::read(&config_path).unwrap();
 
+        assert_eq!(
+            config.fundingstream,
+            vec![
+                ZcashdFundingStream {
+                    stream_id: ZcashdFundingStream::ZIP214_ZF,
+                    start_height: 2,
+                    end_height: 10,
+                    addresses: vec!["zf_addr".to_string()],
+                },
+                ZcashdFundingStream {
+                    stream_id: ZcashdFundingStream::FPF_ZCG,
+                    start_height: 10,
+                    end_height: 20,
+                    addresses: vec!["mg_addr_1".to_string(), "mg_addr_2".to_string()],
+                },
+                ZcashdFundingStream {
+                    stream_id: ZcashdFundingStream::DEFERRED,
+                    start_height: 10,
+                    end_height: 20,
+                    addresses: Vec::new(),
+                },
+            ]
         );
+        let contents = std::fs::read_to_string(config_path).unwrap();
+        assert!(contents.contains("fundingstream=3:10:20:mg_addr_1,mg_addr_2\n"));
+        assert!(contents.contains("fundingstream=4:10:20:\n"));
+
+        let unsupported = network::FundingStreams {
+            post_nu6: Some(network::FundingStreamPeriod {
+                height_range: 10.into()..20.into(),
+                recipients: vec![network::FundingStreamRecipient {
+                    receiver: network::FundingStreamReceiver::Ecc,
+                    numerator: 7,
+                    addresses: vec!["ecc_addr".to_string()],
+                }],
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            super::zcashd(
+                config_dir.path(),
+                1234,
+                &network::ActivationHeights::default(),
+                &unsupported,
+                None,
+                &[],
+            ),
+            Err(ConfigError::InvalidOption { .. })
+        ));
     }
 
     #[test]
-    fn zainod() {
+    fn zebrad_funding_streams() {
         let config_dir = tempfile::tempdir().unwrap();
 
-        super::zainod(config_dir.path(), 1234, 18232).unwrap();
+        let config_path = super::zebrad(
+            config_dir.path().to_path_buf(),
+            PathBuf::from("cache_dir"),
+            1234,
+            5678,
+            &network::ActivationHeights::default(),
+            &funding_streams(),
+            "test_addr_1234",
+            network::Network::Regtest,
+            false,
+            &toml::Table::new(),
+        )
+        .unwrap();
+        let config = ZebradToml::read(&config_path).unwrap();
 
+        let testnet_parameters = config.network.testnet_parameters.unwrap();
         assert_eq!(
-            std::fs::read_to_string(config_dir.path().join(super::ZAINOD_FILENAME)).unwrap(),
-            format!(
-                "\
-# Configuration for Zaino
-
-# Sets the TcpIngestor's status (true or false)
-tcp_active = true
+            testnet_parameters.pre_nu6_funding_streams,
+            Some(ZebradFundingStreams {
+                height_range: ZebradHeightRange { start: 2, end: 10 },
+                recipients: vec![ZebradFundingStreamRecipient {
+                    receiver: "ZcashFoundation".to_string(),
+                    numerator: 5,
+                    addresses: vec!["zf_addr".to_string()],
+                }],
+            })
+        );
+        assert_eq!(
+            testnet_parameters.post_nu6_funding_streams,
+            Some(ZebradFundingStreams {
+                height_range: ZebradHeightRange { start: 10, end: 20 },
+                recipients: vec![
+                    ZebradFundingStreamRecipient {
+                        receiver: "MajorGrants".to_string(),
+                        numerator: 8,
+                        addresses: vec!["mg_addr_1".to_string(), "mg_addr_2".to_string()],
+                    },
+                    ZebradFundingStreamRecipient {
+                        receiver: "Deferred".to_string(),
+                        numerator: 12,
+                        addresses: Vec::new(),
+                    },
+                ],
+            })
+        );
+    }
 
-# Optional TcpIngestors listen port (use None or specify a port number)
-listen_port = 1234
+    #[test]
+    fn invalid_funding_streams() {
+        assert!(super::validate_funding_streams(Process::Zebrad, &funding_streams()).is_ok());
+
+        let empty_range = network::FundingStreams {
+            pre_nu6: Some(network::FundingStreamPeriod {
+                height_range: 10.into()..10.into(),
+                recipients: Vec::new(),
+            }),
+            ..Default::default()
+        };
+        assert!(super::validate_funding_streams(Process::Zebrad, &empty_range).is_err());
+
+        let mut lockbox_address = funding_streams();
+        lockbox_address.post_nu6.as_mut().unwrap().recipients[1]
+            .addresses
+            .push("lockbox_addr".to_string());
+        assert!(super::validate_funding_streams(Process::Zcashd, &lockbox_address).is_err());
+
+        let mut no_address = funding_streams();
+        no_address.pre_nu6.as_mut().unwrap().recipients[0]
+            .addresses
+            .clear();
+        assert!(super::validate_funding_streams(Process::Zcashd, &no_address).is_err());
+
+        assert!(super::validate_funding_streams(Process::Zcashd, &funding_streams()).is_ok());
+        let mut numerator = funding_streams();
+        numerator.pre_nu6.as_mut().unwrap().recipients[0].numerator = 6;
+        assert!(super::validate_funding_streams(Process::Zebrad, &numerator).is_ok());
+        assert!(matches!(
+            super::validate_funding_streams(Process::Zcashd, &numerator),
+            Err(ConfigError::InvalidOption { option, .. }) if option == "funding_streams.pre_nu6"
+        ));
+    }
 
-# Sets the NymIngestor's and NymDispatchers status (true or false)
-nym_active = false
+    #[test]
+    fn zebrad_offline() {
+        let config_dir = tempfile::tempdir().unwrap();
 
-# Optional Nym conf path used for micnet client conf
-nym_conf_path = \"/tmp/indexer/nym\"
+        let config_path = super::zebrad(
+            config_dir.path().to_path_buf(),
+            PathBuf::from("cache_dir"),
+            1234,
+            5678,
+            &network::ActivationHeights::default(),
+            &network::FundingStreams::default(),
+            "test_addr_1234",
+            network::Network::Testnet,
+            true,
+            &toml::Table::new(),
+        )
+        .unwrap();
+        let config = ZebradToml::read(&config_path).unwrap();
 
-# LightWalletD listen port [DEPRECATED]
-lightwalletd_port = 9067
+        assert!(config.network.initial_mainnet_peers.is_empty());
+        assert!(config.network.initial_testnet_peers.is_empty());
+        assert_eq!(config.network.crawl_new_peer_interval, "1year");
+        assert!(config.rpc.debug_force_finished_sync);
+    }
 
-# Full node / validator listen port
-zebrad_port = 18232
+    #[test]
+    fn zebrad_testnet() {
+        let config_dir = tempfile::tempdir().unwrap();
 
-# Optional full node Username
-node_user = \"xxxxxx\"
+        let config_path = super::zebrad(
+            config_dir.path().to_path_buf(),
+            PathBuf::from("cache_dir"),
+            1234,
+            5678,
+            &network::ActivationHeights::default(),
+            &network::FundingStreams::default(),
+            "test_addr_1234",
+            network::Network::Testnet,
+            false,
+            &toml::Table::new(),
+        )
+        .unwrap();
+        let config = ZebradToml::read(&config_path).unwrap();
 
-# Optional full node Password
-node_password = \"xxxxxx\"
+        assert_eq!(config.network.network, "Testnet");
+        assert_eq!(config.network.testnet_parameters, None);
+        assert_eq!(config.mining.miner_address, None);
+    }
 
-# Maximum requests allowed in the request queue
-max_queue_size = 1024
+    #[test]
+    fn zainod() {
+        let config_dir = tempfile::tempdir().unwrap();
 
-# Maximum workers allowed in the worker pool
-max_worker_pool_size = 64
+        let config_path =
+            super::zainod(config_dir.path(), 1234, 18232, &toml::Table::new()).unwrap();
+        let config = ZainodToml::read(&config_path).unwrap();
 
-# Minimum number of workers held in the worker pool when idle
-idle_worker_pool_size = 4"
-            )
-        )
+        assert_eq!(
+            config,
+            ZainodToml {
+                tcp_active: true,
+                listen_port: 1234,
+                nym_active: false,
+                nym_conf_path: PathBuf::from("/tmp/indexer/nym"),
+                lightwalletd_port: 9067,
+                zebrad_port: 18232,
+                node_user: "xxxxxx".to_string(),
+                node_password: "xxxxxx".to_string(),
+                max_queue_size: 1024,
+                max_worker_pool_size: 64,
+                idle_worker_pool_size: 4,
+            }
+        );
     }
 
     #[test]
@@ -447,25 +2394,406 @@ idle_worker_pool_size = 4"
         let logs_dir = tempfile::tempdir().unwrap();
         let log_file_path = logs_dir.path().join(logs::LIGHTWALLETD_LOG);
 
-        super::lightwalletd(
+        let config_path = super::lightwalletd(
             config_dir.path(),
             1234,
             log_file_path.clone(),
             PathBuf::from("conf_path"),
+            &serde_yaml::Mapping::new(),
         )
         .unwrap();
-        let log_file_path = log_file_path.to_str().unwrap();
+        let config = LightwalletdYml::read(&config_path).unwrap();
 
         assert_eq!(
-            std::fs::read_to_string(config_dir.path().join(super::LIGHTWALLETD_FILENAME)).unwrap(),
-            format!(
-                "\
-grpc-bind-addr: 127.0.0.1:1234
-cache-size: 10
-log-file: {log_file_path}
-log-level: 10
-zcash-conf-path: conf_path"
-            )
+            config,
+            LightwalletdYml {
+                grpc_bind_addr: "127.0.0.1:1234".to_string(),
+                cache_size: 10,
+                log_file: log_file_path,
+                log_level: 10,
+                zcash_conf_path: PathBuf::from("conf_path"),
+            }
+        );
+    }
+
+    #[test]
+    fn zcashd_extra_options() {
+        let config_dir = tempfile::tempdir().unwrap();
+
+        let config_path = super::zcashd(
+            config_dir.path(),
+            1234,
+            &network::ActivationHeights::default(),
+            &network::FundingStreams::default(),
+            None,
+            &[
+                ("txindex".to_string(), "0".to_string()),
+                ("addnode".to_string(), "127.0.0.1:18344".to_string()),
+                ("addnode".to_string(), "127.0.0.1:18345".to_string()),
+            ],
+        )
+        .unwrap();
+        let config = ZcashConf::read(&config_path).unwrap();
+
+        assert!(!config.txindex);
+        assert_eq!(
+            config.extra_options,
+            vec![
+                ("addnode".to_string(), "127.0.0.1:18344".to_string()),
+                ("addnode".to_string(), "127.0.0.1:18345".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn zcashd_reserved_extras() {
+        assert!(matches!(
+            super::validate_zcashd_extras(&[("rpcport".to_string(), "1".to_string())], &[]),
+            Err(ConfigError::ReservedOption { .. })
+        ));
+        assert!(matches!(
+            super::validate_zcashd_extras(&[], &["-datadir=/tmp".to_string()]),
+            Err(ConfigError::ReservedOption { .. })
+        ));
+        assert!(matches!(
+            super::validate_zcashd_extras(
+                &[("txindex".to_string(), "1\nrpcport=1".to_string())],
+                &[]
+            ),
+            Err(ConfigError::InvalidOption { .. })
+        ));
+        assert!(super::validate_zcashd_extras(
+            &[("txindex".to_string(), "0".to_string())],
+            &["-debug=rpc".to_string()]
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn zebrad_extra_options() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let extra_options: toml::Table = toml::toml! {
+            [mempool]
+            tx_cost_limit = 1000
+
+            [sync]
+            extra_sync_option = true
+        };
+
+        let config_path = super::zebrad(
+            config_dir.path().to_path_buf(),
+            PathBuf::from("cache_dir"),
+            1234,
+            5678,
+            &network::ActivationHeights::default(),
+            &network::FundingStreams::default(),
+            "test_addr_1234",
+            network::Network::Regtest,
+            false,
+            &extra_options,
+        )
+        .unwrap();
+        let config = ZebradToml::read(&config_path).unwrap();
+
+        assert_eq!(config.mempool.tx_cost_limit, 1000);
+        assert_eq!(config.mempool.eviction_memory_time, "1h");
+        assert_eq!(config.state.cache_dir, PathBuf::from("cache_dir"));
+
+        let config: toml::Table = std::fs::read_to_string(config_path)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(config["sync"]["extra_sync_option"].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn zebrad_invalid_extras() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let extra_options: toml::Table = toml::toml! {
+            [mempool]
+            tx_cost_limit = "1000"
+        };
+
+        assert!(matches!(
+            super::zebrad(
+                config_dir.path().to_path_buf(),
+                PathBuf::from("cache_dir"),
+                1234,
+                5678,
+                &network::ActivationHeights::default(),
+                &network::FundingStreams::default(),
+                "test_addr_1234",
+                network::Network::Regtest,
+                false,
+                &extra_options,
+            ),
+            Err(ConfigError::InvalidOption { .. })
+        ));
+
+        let reserved_options: toml::Table = toml::toml! {
+            [state]
+            cache_dir = "other_cache_dir"
+        };
+        assert!(matches!(
+            super::validate_zebrad_extras(&reserved_options, &[]),
+            Err(ConfigError::ReservedOption { .. })
+        ));
+        assert!(matches!(
+            super::validate_zebrad_extras(
+                &toml::Table::new(),
+                &["--config=zebrad.toml".to_string()]
+            ),
+            Err(ConfigError::ReservedOption { .. })
+        ));
+    }
+
+    #[test]
+    fn zainod_extra_options() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let extra_options: toml::Table = toml::toml! {
+            max_queue_size = 2048
+        };
+
+        let config_path = super::zainod(config_dir.path(), 1234, 18232, &extra_options).unwrap();
+        let config = ZainodToml::read(&config_path).unwrap();
+
+        assert_eq!(config.max_queue_size, 2048);
+        assert_eq!(config.zebrad_port, 18232);
+    }
+
+    #[test]
+    fn lightwalletd_extra_options() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let mut extra_options = serde_yaml::Mapping::new();
+        extra_options.insert("cache-size".into(), 20.into());
+        extra_options.insert("ping-very-insecure".into(), true.into());
+
+        let config_path = super::lightwalletd(
+            config_dir.path(),
+            1234,
+            PathBuf::from("lwd.log"),
+            PathBuf::from("conf_path"),
+            &extra_options,
+        )
+        .unwrap();
+        let config = LightwalletdYml::read(&config_path).unwrap();
+
+        assert_eq!(config.cache_size, 20);
+        assert_eq!(config.zcash_conf_path, PathBuf::from("conf_path"));
+
+        let config: serde_yaml::Mapping =
+            serde_yaml::from_str(&std::fs::read_to_string(config_path).unwrap()).unwrap();
+        assert_eq!(config["ping-very-insecure"].as_bool(), Some(true));
+
+        let mut reserved_options = serde_yaml::Mapping::new();
+        reserved_options.insert("log-file".into(), "other.log".into());
+        assert!(matches!(
+            super::validate_lightwalletd_extras(&reserved_options, &[]),
+            Err(ConfigError::ReservedOption { .. })
+        ));
+    }
+
+    #[test]
+    fn local_net_zebrad_zainod() {
+        let config: LocalNetToml = toml::from_str(
+            r#"
+            [validator]
+            kind = "zebrad"
+            bin = "/usr/local/bin/zebrad"
+            rpc_listen_port = 18232
+            miner_address = "test_addr_1234"
+
+            [validator.activation_heights]
+            nu5 = 2
+
+            [validator.extra_options.mempool]
+            tx_cost_limit = 1000
+
+            [indexer]
+            kind = "zainod"
+            extra_args = ["--verbose"]
+            "#,
+        )
+        .unwrap();
+
+        let zebrad_config = config.zebrad_config().unwrap();
+        assert_eq!(
+            zebrad_config.zebrad_bin,
+            Some(PathBuf::from("/usr/local/bin/zebrad"))
+        );
+        assert_eq!(zebrad_config.rpc_listen_port, Some(18232));
+        assert_eq!(zebrad_config.network_listen_port, None);
+        assert_eq!(zebrad_config.network, network::Network::Regtest);
+        assert_eq!(zebrad_config.miner_address, "test_addr_1234");
+        assert_eq!(
+            zebrad_config.activation_heights,
+            network::ActivationHeights {
+                nu5: 2.into(),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            zebrad_config.extra_options["mempool"]["tx_cost_limit"].as_integer(),
+            Some(1000)
+        );
+
+        let zainod_config = config.zainod_config().unwrap();
+        assert_eq!(zainod_config.zainod_bin, None);
+        assert_eq!(zainod_config.extra_args, vec!["--verbose".to_string()]);
+
+        assert!(matches!(
+            config.zcashd_config(),
+            Err(ConfigError::ProcessMismatch { .. })
+        ));
+        assert!(matches!(
+            config.lightwalletd_config(),
+            Err(ConfigError::ProcessMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn local_net_zcashd_lightwalletd() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let config_path = config_dir.path().join("local_net.toml");
+        std::fs::write(
+            &config_path,
+            r#"
+            [validator]
+            kind = "zcashd"
+            rpc_port = 18232
+            extra_options = [["addnode", "127.0.0.1:18344"], ["addnode", "127.0.0.1:18345"]]
+
+            [indexer]
+            kind = "lightwalletd"
+            listen_port = 9067
+
+            [indexer.extra_options]
+            cache-size = 20
+            "#,
+        )
+        .unwrap();
+
+        let config = LocalNetToml::read(&config_path).unwrap();
+
+        let zcashd_config = config.zcashd_config().unwrap();
+        assert_eq!(zcashd_config.rpc_port, Some(18232));
+        assert_eq!(zcashd_config.miner_address, None);
+        assert_eq!(
+            zcashd_config.activation_heights,
+            network::ActivationHeights::default()
+        );
+        assert_eq!(
+            zcashd_config.extra_options,
+            vec![
+                ("addnode".to_string(), "127.0.0.1:18344".to_string()),
+                ("addnode".to_string(), "127.0.0.1:18345".to_string()),
+            ]
+        );
+
+        let lightwalletd_config = config.lightwalletd_config().unwrap();
+        assert_eq!(lightwalletd_config.listen_port, Some(9067));
+        assert_eq!(
+            lightwalletd_config.extra_options["cache-size"].as_u64(),
+            Some(20)
+        );
+    }
+
+    #[test]
+    fn local_net_funding_streams() {
+        let config: LocalNetToml = toml::from_str(
+            r#"
+            [validator]
+            kind = "zcashd"
+
+            [validator.funding_streams.pre_nu6]
+            start_height = 2
+            end_height = 10
+
+            [[validator.funding_streams.pre_nu6.recipients]]
+            receiver = "zcash_foundation"
+            addresses = ["zf_addr"]
+
+            [validator.funding_streams.post_nu6]
+            start_height = 10
+            end_height = 20
+
+            [[validator.funding_streams.post_nu6.recipients]]
+            receiver = "major_grants"
+            numerator = 8
+            addresses = ["mg_addr_1", "mg_addr_2"]
+
+            [[validator.funding_streams.post_nu6.recipients]]
+            receiver = "deferred"
+
+            [indexer]
+            kind = "zainod"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.zcashd_config().unwrap().funding_streams,
+            funding_streams()
+        );
+
+        let config: LocalNetToml = toml::from_str(
+            r#"
+            [validator]
+            kind = "zebrad"
+
+            [validator.funding_streams.post_nu6]
+            start_height = 10
+            end_height = 20
+
+            [[validator.funding_streams.post_nu6.recipients]]
+            receiver = "ecc"
+            addresses = ["ecc_addr"]
+
+            [indexer]
+            kind = "zainod"
+            "#,
+        )
+        .unwrap();
+        assert!(matches!(
+            config.zebrad_config(),
+            Err(ConfigError::InvalidOption { .. })
+        ));
+    }
+
+    #[test]
+    fn local_net_unknown_fields() {
+        for config in [
+            "[validator]\nkind = \"zcashd\"\nrpcport = 18232\n[indexer]\nkind = \"zainod\"",
+            "[validator]\nkind = \"zebrad\"\n[validator.activation_heights]\nnu_5 = 2\n[indexer]\nkind = \"zainod\"",
+            "[validator]\nkind = \"zcashd\"\n[indexer]\nkind = \"lightwalletd\"\nlisten = 9067",
+            "[validator]\nkind = \"zcashd\"\n[indexer]\nkind = \"zainod\"\n[wallet]",
+        ] {
+            assert!(toml::from_str::<LocalNetToml>(config).is_err(), "{config}");
+        }
+    }
+
+    #[test]
+    fn intern_str() {
+        // addresses read from separate config files are separate allocations
+        let first = String::from("test_addr_1234");
+        let second = String::from("test_addr_1234");
+
+        let address = super::intern_str(&first);
+        assert_eq!(address, "test_addr_1234");
+        assert!(std::ptr::eq(address, super::intern_str(&second)));
+    }
+
+    #[test]
+    fn local_net_invalid_kind() {
+        assert!(toml::from_str::<LocalNetToml>(
+            r#"
+            [validator]
+            kind = "bitcoind"
+
+            [indexer]
+            kind = "zainod"
+            "#,
         )
+        .is_err());
     }
 }
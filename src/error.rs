@@ -1,5 +1,7 @@
 //! Crate level error module
 
+use std::{path::PathBuf, sync::Arc};
+
 /// Errors associated with launching processes
 #[derive(thiserror::Error, Debug, Clone)]
 pub enum LaunchError {
@@ -17,4 +19,351 @@ pub enum LaunchError {
         /// Stderr log
         stderr: String,
     },
+    /// Process configuration is invalid
+    #[error("invalid configuration. {0}")]
+    InvalidConfig(#[from] ConfigError),
+    /// Chain cache could not be loaded
+    #[error("failed to load chain cache. {0}")]
+    ChainCache(#[from] ChainCacheError),
+}
+
+/// Errors associated with process configuration
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum ConfigError {
+    /// Option is managed by this crate and can not be overridden
+    #[error(
+        "{process_name} option `{option}` is managed by zcash_local_net and can not be overridden"
+    )]
+    ReservedOption {
+        /// Process name
+        process_name: String,
+        /// Option name
+        option: String,
+    },
+    /// Option is not compatible with the generated config
+    #[error("{process_name} option `{option}` is invalid: {reason}")]
+    InvalidOption {
+        /// Process name
+        process_name: String,
+        /// Option name
+        option: String,
+        /// Reason the option is invalid
+        reason: String,
+    },
+    /// Config file describes a different process than the one being launched
+    #[error("config file describes {found} but {expected} is being launched")]
+    ProcessMismatch {
+        /// Process being launched
+        expected: String,
+        /// Process described in the config file
+        found: String,
+    },
+    /// Failed to parse config file
+    #[error("failed to parse config file. {0}")]
+    Parse(String),
+    /// Failed to read or write config file
+    #[error("config file IO error: {0}")]
+    Io(Arc<std::io::Error>),
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(Arc::new(error))
+    }
+}
+
+/// Errors associated with validator RPC requests
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum RpcError {
+    /// Failed to send request or receive a response
+    #[error("{method} request failed. {reason}")]
+    Request {
+        /// RPC method
+        method: String,
+        /// Reason the request failed
+        reason: String,
+    },
+    /// Validator returned an error response
+    #[error("{method} returned an error. {message}")]
+    Response {
+        /// RPC method
+        method: String,
+        /// Error message
+        message: String,
+    },
+    /// Response could not be parsed
+    #[error("invalid {method} response. {reason}")]
+    InvalidResponse {
+        /// RPC method
+        method: String,
+        /// Reason the response is invalid
+        reason: String,
+    },
+}
+
+/// Errors associated with generating blocks from chosen transactions
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum GenerateBlockError {
+    /// Transaction could not be decoded
+    #[error("invalid transaction. {0}")]
+    InvalidTransaction(String),
+    /// Mempool contains transactions which were not chosen. Zcashd mines every mempool transaction.
+    #[error("mempool contains transactions which were not chosen: {0:?}")]
+    UnexpectedMempoolTransactions(Vec<String>),
+    /// Validator can not build the requested block
+    #[error("{0}")]
+    Unsupported(String),
+    /// Validator rejected the block
+    #[error("block was rejected. {0}")]
+    Rejected(String),
+    /// Failed to query the validator
+    #[error(transparent)]
+    Rpc(#[from] RpcError),
+}
+
+/// Errors associated with funding addresses from the funding miner key
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum FundingError {
+    /// Address could not be decoded for the local network
+    #[error("invalid address {0}")]
+    InvalidAddress(String),
+    /// Spendable outputs of the funding address do not cover the value and fee
+    #[error("insufficient funds. {required} zatoshis required but only {available} zatoshis are spendable")]
+    InsufficientFunds {
+        /// Value and fee in zatoshis
+        required: u64,
+        /// Spendable value in zatoshis
+        available: u64,
+    },
+    /// Transaction could not be built
+    #[error("failed to build transaction. {0}")]
+    Build(String),
+    /// Validator rejected the transaction
+    #[error("transaction was rejected. {0}")]
+    Rejected(String),
+    /// Failed to query the validator
+    #[error(transparent)]
+    Rpc(#[from] RpcError),
+}
+
+/// Errors associated with the zcashd wallet faucet
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum FaucetError {
+    /// Wallet operation failed
+    #[error("operation {opid} failed. {message}")]
+    OperationFailed {
+        /// Operation id
+        opid: String,
+        /// Error message
+        message: String,
+    },
+    /// Wallet operation did not complete in time
+    #[error("operation {opid} did not complete within {timeout:?}")]
+    OperationTimeout {
+        /// Operation id
+        opid: String,
+        /// Time waited for the operation
+        timeout: std::time::Duration,
+    },
+    /// Failed to query the validator
+    #[error(transparent)]
+    Rpc(#[from] RpcError),
+}
+
+/// Errors associated with mining and shielding coinbase outputs
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum CoinbaseError {
+    /// Address is not a transparent, Sapling or unified address
+    #[error("{0} can not receive coinbase outputs")]
+    UnsupportedAddress(String),
+    /// Coinbase outputs can not be paid to the address's pool before a network upgrade activates
+    #[error("coinbase outputs can not be paid to {address} before {network_upgrade} activates")]
+    UpgradeNotActive {
+        /// Coinbase address
+        address: String,
+        /// Network upgrade enabling coinbase outputs to the address's pool
+        network_upgrade: String,
+    },
+    /// Validator can not shield coinbase outputs
+    #[error("{0}")]
+    Unsupported(String),
+    /// Zcashd wallet operation failed
+    #[error(transparent)]
+    Faucet(#[from] FaucetError),
+    /// Failed to build or broadcast the shielding transaction
+    #[cfg(feature = "funding")]
+    #[error(transparent)]
+    Funding(#[from] FundingError),
+    /// Failed to query the validator
+    #[error(transparent)]
+    Rpc(#[from] RpcError),
+}
+
+/// Errors associated with waiting for transactions
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum WaitError {
+    /// Transaction did not enter the mempool in time
+    #[error("transaction {txid} did not enter the mempool within {timeout:?}")]
+    MempoolTimeout {
+        /// Transaction id
+        txid: String,
+        /// Time waited for the transaction
+        timeout: std::time::Duration,
+    },
+    /// Transaction did not reach the confirmation depth in time
+    #[error("transaction {txid} has {confirmations} of {depth} confirmations after {timeout:?}")]
+    ConfirmationTimeout {
+        /// Transaction id
+        txid: String,
+        /// Required number of confirmations
+        depth: u32,
+        /// Number of confirmations when the wait timed out
+        confirmations: u32,
+        /// Time waited for the transaction
+        timeout: std::time::Duration,
+    },
+    /// Indexer did not sync to the height in time
+    #[error("indexer is at height {indexer_height}, behind height {height}, after {timeout:?}")]
+    HeightTimeout {
+        /// Height waited for
+        height: u32,
+        /// Indexer's chain height when the wait timed out
+        indexer_height: u32,
+        /// Time waited for the indexer
+        timeout: std::time::Duration,
+    },
+    /// Indexer of a local network did not sync to the validator's chain height in time
+    #[error(
+        "indexer is at height {indexer_height}, behind validator at height {validator_height}, after {timeout:?}"
+    )]
+    SyncTimeout {
+        /// Validator's chain height when the wait timed out
+        validator_height: u32,
+        /// Indexer's chain height when the wait timed out
+        indexer_height: u32,
+        /// Time waited for the indexer
+        timeout: std::time::Duration,
+    },
+    /// Failed to query the validator or indexer
+    #[error(transparent)]
+    Rpc(#[from] RpcError),
+}
+
+/// Errors associated with exporting and replaying blocks
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum ReplayError {
+    /// Block file could not be parsed
+    #[error("invalid block file. {0}")]
+    InvalidBlockFile(String),
+    /// Block file was exported from a different network
+    #[error("block file is for {found} but the validator is running {expected}")]
+    NetworkMismatch {
+        /// Validator network
+        expected: String,
+        /// Block file network
+        found: String,
+    },
+    /// Block file does not start at the block after the validator chain tip
+    #[error("block file starts at height {start_height} but the validator chain tip is at height {tip_height}")]
+    HeightMismatch {
+        /// Height of the first block in the block file
+        start_height: u32,
+        /// Validator chain tip height
+        tip_height: u32,
+    },
+    /// Validator rejected a block
+    #[error("block at height {height} was rejected. {reason}")]
+    Rejected {
+        /// Block height
+        height: u32,
+        /// Reason the block was rejected
+        reason: String,
+    },
+    /// Failed to query the validator
+    #[error(transparent)]
+    Rpc(#[from] RpcError),
+    /// Failed to read or write the block file
+    #[error("block file IO error: {0}")]
+    Io(Arc<std::io::Error>),
+}
+
+impl From<std::io::Error> for ReplayError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(Arc::new(error))
+    }
+}
+
+/// Errors associated with caching and loading chains
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum ChainCacheError {
+    /// Chain cache directory already exists
+    #[error("chain cache {0} already exists")]
+    AlreadyExists(PathBuf),
+    /// Chain cache is missing the validator data directory
+    #[error("chain cache directory {0} not found")]
+    NotFound(PathBuf),
+    /// Chain cache or chain cache archive has no manifest
+    #[error("chain cache {0} has no manifest")]
+    MissingManifest(PathBuf),
+    /// Manifest could not be parsed
+    #[error("invalid chain cache manifest. {0}")]
+    InvalidManifest(String),
+    /// Chain cache archive entry would unpack outside of the chain cache
+    #[error("chain cache archive entry {0} would unpack outside of the chain cache")]
+    UnsafeArchiveEntry(PathBuf),
+    /// Scenario record could not be parsed
+    #[error("invalid scenario record. {0}")]
+    InvalidScenarioRecord(String),
+    /// Chain cache is not compatible with the validator config
+    #[error("chain cache {field} is {found} but the validator is configured with {expected}")]
+    Incompatible {
+        /// Manifest field
+        field: String,
+        /// Value in the validator config
+        expected: String,
+        /// Value in the chain cache manifest
+        found: String,
+    },
+    /// Failed to query the validator
+    #[error(transparent)]
+    Rpc(#[from] RpcError),
+    /// Failed to read or write the chain cache
+    #[error("chain cache IO error: {0}")]
+    Io(Arc<std::io::Error>),
+}
+
+impl From<std::io::Error> for ChainCacheError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(Arc::new(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::WaitError;
+
+    #[test]
+    fn height_timeout_message() {
+        let error = WaitError::HeightTimeout {
+            height: 12,
+            indexer_height: 10,
+            timeout: Duration::from_secs(60),
+        };
+        assert_eq!(
+            error.to_string(),
+            "indexer is at height 10, behind height 12, after 60s"
+        );
+
+        let error = WaitError::SyncTimeout {
+            validator_height: 13,
+            indexer_height: 10,
+            timeout: Duration::from_secs(60),
+        };
+        assert_eq!(
+            error.to_string(),
+            "indexer is at height 10, behind validator at height 13, after 60s"
+        );
+    }
 }
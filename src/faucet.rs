@@ -0,0 +1,210 @@
+//! Module for funding addresses from the zcashd wallet
+//!
+//! Zcashd launched without a miner address mines to its own wallet. A [`ZcashdFaucet`] creates a wallet account,
+//! shields the wallet's mature coinbase into the account with `z_shieldcoinbase` and sends to any address with
+//! `z_sendmany`, waiting for each asynchronous operation to complete with `z_getoperationresult`. This funds recipient
+//! wallets without building a lightclient faucet.
+//!
+//! ```ignore (requires zcashd)
+//! let zcashd = Zcashd::launch(ZcashdConfig {
+//!     miner_address: None,
+//!     ..
+//! })
+//! .await
+//! .unwrap();
+//! zcashd.generate_blocks(network::COINBASE_MATURITY + 1).await.unwrap();
+//!
+//! let faucet = ZcashdFaucet::new(&zcashd).await.unwrap();
+//! faucet.shield_coinbase().await.unwrap();
+//! faucet.send(recipient_address, 1_000_000).await.unwrap();
+//! zcashd.generate_blocks(1).await.unwrap();
+//! ```
+
+use std::{ops::ControlFlow, time::Duration};
+
+use crate::{
+    error::{FaucetError, RpcError},
+    utils,
+    validator::{Validator as _, Zcashd},
+};
+
+/// Maximum time to wait for a wallet operation to complete
+const OPERATION_TIMEOUT: Duration = Duration::from_secs(120);
+/// Interval between polls of a wallet operation
+const OPERATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Privacy policy of faucet sends. Sends to transparent addresses and other pools reveal the recipient and amount.
+const PRIVACY_POLICY: &str = "AllowRevealedRecipients";
+
+/// Faucet backed by an account of the zcashd wallet
+pub struct ZcashdFaucet<'a> {
+    zcashd: &'a Zcashd,
+    account: u32,
+    address: String,
+}
+
+impl<'a> ZcashdFaucet<'a> {
+    /// Creates a new account in the `zcashd` wallet for the faucet.
+    pub async fn new(zcashd: &'a Zcashd) -> Result<Self, FaucetError> {
+        let response = zcashd.call_rpc("z_getnewaccount", Vec::new()).await?;
+        let account = response["account"]
+            .as_u64()
+            .and_then(|account| u32::try_from(account).ok())
+            .ok_or_else(|| invalid_response("z_getnewaccount", "expected account number"))?;
+        let response = zcashd
+            .call_rpc(
+                "z_getaddressforaccount",
+                vec![account.into(), serde_json::json!(["orchard"])],
+            )
+            .await?;
+        let address = response["address"]
+            .as_str()
+            .ok_or_else(|| invalid_response("z_getaddressforaccount", "expected address"))?
+            .to_string();
+
+        Ok(ZcashdFaucet {
+            zcashd,
+            account,
+            address,
+        })
+    }
+
+    /// Returns the wallet account number.
+    pub fn account(&self) -> u32 {
+        self.account
+    }
+
+    /// Returns the unified address of the faucet account.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Shields the mature coinbase outputs of the zcashd wallet into the faucet account and mines the shielding
+    /// transaction. Returns the txid of the shielding transaction.
+    ///
+    /// Zcashd shields a limited number of outputs per transaction, so this may be called repeatedly.
+    pub async fn shield_coinbase(&self) -> Result<String, FaucetError> {
+        let txid = shield_coinbase(self.zcashd, &self.address).await?;
+        self.zcashd
+            .generate_blocks(1)
+            .await
+            .map_err(|e| RpcError::Request {
+                method: "generate".to_string(),
+                reason: e.to_string(),
+            })?;
+
+        Ok(txid)
+    }
+
+    /// Sends `value` zatoshis to `address` from the faucet account and returns the txid. The transaction is not
+    /// mined.
+    pub async fn send(&self, address: &str, value: u64) -> Result<String, FaucetError> {
+        self.send_many(&[(address, value)]).await
+    }
+
+    /// Sends to each `(address, value)` of `recipients`, in zatoshis, from the faucet account with `z_sendmany` and
+    /// returns the txid. The transaction pays the ZIP 317 conventional fee and is not mined.
+    pub async fn send_many(&self, recipients: &[(&str, u64)]) -> Result<String, FaucetError> {
+        let amounts: Vec<serde_json::Value> = recipients
+            .iter()
+            .map(
+                |(address, value)| serde_json::json!({ "address": address, "amount": zec(*value) }),
+            )
+            .collect();
+        let opid = self
+            .zcashd
+            .call_rpc(
+                "z_sendmany",
+                vec![
+                    self.address.as_str().into(),
+                    amounts.into(),
+                    1.into(),
+                    serde_json::Value::Null,
+                    PRIVACY_POLICY.into(),
+                ],
+            )
+            .await?;
+        let opid = opid
+            .as_str()
+            .ok_or_else(|| invalid_response("z_sendmany", "expected opid"))?;
+
+        wait_for_operation(self.zcashd, opid).await
+    }
+}
+
+/// Shields the mature transparent coinbase outputs of the zcashd wallet to `address` with `z_shieldcoinbase` and
+/// returns the txid of the shielding transaction.
+pub(crate) async fn shield_coinbase(zcashd: &Zcashd, address: &str) -> Result<String, FaucetError> {
+    let response = zcashd
+        .call_rpc("z_shieldcoinbase", vec!["*".into(), address.into()])
+        .await?;
+    let opid = response["opid"]
+        .as_str()
+        .ok_or_else(|| invalid_response("z_shieldcoinbase", "expected opid"))?;
+
+    wait_for_operation(zcashd, opid).await
+}
+
+/// Polls `z_getoperationresult` until the zcashd wallet operation `opid` completes and returns the txid of the
+/// operation.
+async fn wait_for_operation(zcashd: &Zcashd, opid: &str) -> Result<String, FaucetError> {
+    utils::poll_until(OPERATION_TIMEOUT, OPERATION_POLL_INTERVAL, || async move {
+        // results are only returned, and removed from the wallet, once the operation completes
+        let response = zcashd
+            .call_rpc("z_getoperationresult", vec![serde_json::json!([opid])])
+            .await?;
+        let Some(result) = response.as_array().and_then(|results| results.first()) else {
+            return Ok(ControlFlow::Continue(()));
+        };
+
+        let txid = match result["status"].as_str() {
+            Some("success") => result["result"]["txid"]
+                .as_str()
+                .map(String::from)
+                .ok_or_else(|| invalid_response("z_getoperationresult", "expected txid"))?,
+            _ => {
+                return Err(FaucetError::OperationFailed {
+                    opid: opid.to_string(),
+                    message: result["error"]["message"]
+                        .as_str()
+                        .unwrap_or("unknown error")
+                        .to_string(),
+                })
+            }
+        };
+        Ok(ControlFlow::Break(txid))
+    })
+    .await?
+    .map_err(|()| FaucetError::OperationTimeout {
+        opid: opid.to_string(),
+        timeout: OPERATION_TIMEOUT,
+    })
+}
+
+/// Converts zatoshis to a ZEC amount for zcashd RPCs.
+fn zec(value: u64) -> f64 {
+    value as f64 / 100_000_000.0
+}
+
+fn invalid_response(method: &str, reason: &str) -> RpcError {
+    RpcError::InvalidResponse {
+        method: method.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn zec() {
+        assert_eq!(serde_json::json!(super::zec(100_000)).to_string(), "0.001");
+        assert_eq!(
+            serde_json::json!(super::zec(2_100_000_000_000_000)).to_string(),
+            "21000000.0"
+        );
+        assert_eq!(
+            serde_json::json!(super::zec(123_456_789)).to_string(),
+            "1.23456789"
+        );
+    }
+}
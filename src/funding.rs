@@ -0,0 +1,521 @@
+//! Module for funding wallets from the block rewards of a crate-owned miner key
+//!
+//! Zebrad has no wallet, so local networks mining to [`FUNDING_ADDRESS`] are funded by a [`Funder`] instead. The
+//! funder holds [`FUNDING_SECRET_KEY`], finds the spendable outputs of the funding address with `getaddressutxos`
+//! and builds and broadcasts transactions paying transparent, Sapling or Orchard addresses with `sendrawtransaction`.
+//!
+//! Coinbase outputs can only be spent after [`COINBASE_MATURITY`] blocks, so at least `COINBASE_MATURITY + 1` blocks
+//! must be mined to the funding address before the first transaction is funded:
+//! ```ignore (requires zebrad)
+//! let zebrad = Zebrad::launch(ZebradConfig {
+//!     miner_address: funding::FUNDING_ADDRESS,
+//!     ..ZebradConfig::default()
+//! })
+//! .await
+//! .unwrap();
+//! zebrad.generate_blocks(network::COINBASE_MATURITY + 1).await.unwrap();
+//!
+//! let funder = Funder::new(&ActivationHeights::default());
+//! funder.fund(&zebrad, &[(recipient_address, 1_000_000)]).await.unwrap();
+//! ```
+//!
+//! Shielded outputs and proofs are randomised, so a funder created with [`Funder::new`] builds a different transaction
+//! on every run. A funder created with [`Funder::with_rng`] and a seeded RNG builds the same transactions for the same
+//! chain, see [`crate::scenario`].
+
+use std::{collections::HashSet, sync::Mutex};
+
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use serde::Deserialize;
+use zcash_keys::address::Address;
+use zcash_primitives::{
+    legacy::{keys::pubkey_to_address, Script, TransparentAddress},
+    memo::MemoBytes,
+    transaction::{
+        builder::{BuildConfig, Builder},
+        components::{amount::NonNegativeAmount, transparent::TxOut, OutPoint},
+        fees::fixed::FeeRule as FixedFeeRule,
+    },
+};
+use zcash_proofs::prover::LocalTxProver;
+use zcash_protocol::{consensus::BlockHeight, local_consensus::LocalNetwork};
+
+use crate::{
+    error::{FundingError, RpcError},
+    network::{ActivationHeights, COINBASE_MATURITY},
+    validator::Validator,
+};
+
+/// Secret key of [`FUNDING_ADDRESS`]. For local networks only, the key is public.
+pub const FUNDING_SECRET_KEY: [u8; 32] = [
+    0x27, 0x26, 0xff, 0xc3, 0x96, 0x10, 0x8c, 0x12, 0x72, 0xf0, 0x6b, 0x27, 0x71, 0x4f, 0xa3, 0x05,
+    0xc4, 0xe6, 0x20, 0xd2, 0x15, 0x6d, 0x6a, 0xf5, 0xa8, 0x6e, 0x45, 0xb8, 0x5d, 0xf0, 0xf3, 0x14,
+];
+
+/// Regtest transparent P2PKH address of [`FUNDING_SECRET_KEY`], for use as the zebrad miner address.
+pub const FUNDING_ADDRESS: &str = "tmRcjFZ4BhFJF1Z4jmuYhHxkGUgVem62Z5Z";
+
+/// ZIP 317 marginal fee per logical action
+const MARGINAL_FEE: u64 = 5_000;
+
+/// ZIP 317 number of logical actions which are not charged
+const GRACE_ACTIONS: usize = 2;
+
+/// Unspent transparent output of the funding address
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Utxo {
+    /// Txid of the transaction which created the output
+    pub txid: String,
+    /// Index of the output in the transaction
+    pub output_index: u32,
+    /// Hex encoded lock script
+    pub script: String,
+    /// Value in zatoshis
+    pub value: u64,
+    /// Height of the block containing the transaction
+    pub height: u32,
+}
+
+/// `getaddressutxos` response entry
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddressUtxo {
+    txid: String,
+    output_index: u32,
+    script: String,
+    satoshis: u64,
+    height: u32,
+}
+
+/// Inputs, outputs and fee of a funding transaction
+struct Spend {
+    inputs: Vec<Utxo>,
+    recipients: Vec<(Recipient, u64)>,
+    change: u64,
+    fee: u64,
+}
+
+/// Recipient of a funding transaction
+enum Recipient {
+    Transparent(TransparentAddress),
+    Sapling(sapling::PaymentAddress),
+    Orchard(orchard::Address),
+}
+
+/// Builds and broadcasts transactions spending the outputs of [`FUNDING_ADDRESS`]
+pub struct Funder<R = OsRng> {
+    secret_key: secp256k1::SecretKey,
+    address: TransparentAddress,
+    params: LocalNetwork,
+    /// Outputs spent by broadcast transactions which may not be mined yet
+    spent: Mutex<HashSet<(String, u32)>>,
+    /// Random number generator used to build transactions
+    rng: Mutex<R>,
+}
+
+impl Funder {
+    /// Creates a funder for a local network with the given `activation_heights`.
+    pub fn new(activation_heights: &ActivationHeights) -> Self {
+        Self::with_rng(activation_heights, OsRng)
+    }
+}
+
+impl<R: RngCore + CryptoRng> Funder<R> {
+    /// Creates a funder for a local network with the given `activation_heights` which builds transactions with `rng`.
+    pub fn with_rng(activation_heights: &ActivationHeights, rng: R) -> Self {
+        let secret_key = secp256k1::SecretKey::from_slice(&FUNDING_SECRET_KEY)
+            .expect("funding secret key should be valid");
+        let public_key = secret_key.public_key(&secp256k1::Secp256k1::signing_only());
+
+        Funder {
+            secret_key,
+            address: pubkey_to_address(&public_key),
+            params: LocalNetwork::from(activation_heights),
+            spent: Mutex::new(HashSet::new()),
+            rng: Mutex::new(rng),
+        }
+    }
+
+    /// Returns the outputs of the funding address which can be spent in the next block. Immature coinbase outputs
+    /// and outputs spent by previously funded transactions are excluded.
+    pub async fn spendable_utxos<V: Validator + Sync>(
+        &self,
+        validator: &V,
+    ) -> Result<Vec<Utxo>, FundingError> {
+        let next_height = u32::from(validator.get_chain_height().await) + 1;
+        let response = validator
+            .call_rpc(
+                "getaddressutxos",
+                vec![serde_json::json!({ "addresses": [FUNDING_ADDRESS] })],
+            )
+            .await?;
+        let address_utxos: Vec<AddressUtxo> =
+            serde_json::from_value(response).map_err(|e| RpcError::InvalidResponse {
+                method: "getaddressutxos".to_string(),
+                reason: e.to_string(),
+            })?;
+
+        let mut utxos = Vec::new();
+        for address_utxo in address_utxos {
+            if self
+                .spent
+                .lock()
+                .unwrap()
+                .contains(&(address_utxo.txid.clone(), address_utxo.output_index))
+            {
+                continue;
+            }
+            // coinbase transactions are only looked up for outputs which would be immature
+            if !is_mature(address_utxo.height, next_height)
+                && is_coinbase(validator, &address_utxo.txid, address_utxo.height).await?
+            {
+                continue;
+            }
+
+            utxos.push(Utxo {
+                txid: address_utxo.txid,
+                output_index: address_utxo.output_index,
+                script: address_utxo.script,
+                value: address_utxo.satoshis,
+                height: address_utxo.height,
+            });
+        }
+        utxos.sort_by_key(|utxo| utxo.height);
+
+        Ok(utxos)
+    }
+
+    /// Builds and broadcasts a transaction paying each `(address, value)` of `recipients`, in zatoshis, and returns
+    /// its txid. Unified addresses are paid to their Orchard receiver, falling back to Sapling then transparent.
+    ///
+    /// The ZIP 317 conventional fee is paid and the change is returned to the funding address. The transaction is
+    /// only broadcast, the caller mines it into a block.
+    pub async fn fund<V: Validator + Sync>(
+        &self,
+        validator: &V,
+        recipients: &[(&str, u64)],
+    ) -> Result<String, FundingError> {
+        let spend = self.select_inputs(validator, recipients).await?;
+        self.send(validator, spend).await
+    }
+
+    /// Builds a transaction paying each `(address, value)` of `recipients` like [`Funder::fund`] and returns it hex
+    /// encoded without broadcasting it, e.g. to mine it with [`Validator::generate_block_with_transactions`].
+    ///
+    /// The spent outputs are not reserved, so the transaction conflicts with transactions funded before it is mined.
+    pub async fn build<V: Validator + Sync>(
+        &self,
+        validator: &V,
+        recipients: &[(&str, u64)],
+    ) -> Result<String, FundingError> {
+        let spend = self.select_inputs(validator, recipients).await?;
+        let (_, transaction) = self.build_transaction(validator, &spend).await?;
+
+        Ok(transaction)
+    }
+
+    /// Selects the spendable outputs paying each `(address, value)` of `recipients` and the ZIP 317 conventional
+    /// fee.
+    async fn select_inputs<V: Validator + Sync>(
+        &self,
+        validator: &V,
+        recipients: &[(&str, u64)],
+    ) -> Result<Spend, FundingError> {
+        let recipients = recipients
+            .iter()
+            .map(|(address, value)| Ok((self.decode_address(address)?, *value)))
+            .collect::<Result<Vec<_>, FundingError>>()?;
+        let count = |predicate: fn(&Recipient) -> bool| {
+            recipients
+                .iter()
+                .filter(|(recipient, _)| predicate(recipient))
+                .count()
+        };
+        // the change output is always counted
+        let transparent_outputs = count(|r| matches!(r, Recipient::Transparent(_))) + 1;
+        let sapling_outputs = count(|r| matches!(r, Recipient::Sapling(_)));
+        let orchard_outputs = count(|r| matches!(r, Recipient::Orchard(_)));
+        let value: u64 = recipients.iter().map(|(_, value)| value).sum();
+
+        let mut inputs = Vec::new();
+        let mut available = 0;
+        let mut fee = conventional_fee(0, transparent_outputs, sapling_outputs, orchard_outputs);
+        for utxo in self.spendable_utxos(validator).await? {
+            if available >= value + fee {
+                break;
+            }
+            available += utxo.value;
+            inputs.push(utxo);
+            fee = conventional_fee(
+                inputs.len(),
+                transparent_outputs,
+                sapling_outputs,
+                orchard_outputs,
+            );
+        }
+        if available < value + fee {
+            return Err(FundingError::InsufficientFunds {
+                required: value + fee,
+                available,
+            });
+        }
+
+        Ok(Spend {
+            inputs,
+            recipients,
+            change: available - value - fee,
+            fee,
+        })
+    }
+
+    /// Builds and broadcasts a transaction paying every spendable output of the funding address, less the ZIP 317
+    /// conventional fee, to `address` and returns its txid. Used to shield the transparent coinbase of the funding
+    /// address to a shielded address.
+    pub async fn sweep<V: Validator + Sync>(
+        &self,
+        validator: &V,
+        address: &str,
+    ) -> Result<String, FundingError> {
+        let recipient = self.decode_address(address)?;
+        let inputs = self.spendable_utxos(validator).await?;
+        let available: u64 = inputs.iter().map(|utxo| utxo.value).sum();
+        let fee = match recipient {
+            Recipient::Transparent(_) => conventional_fee(inputs.len(), 1, 0, 0),
+            Recipient::Sapling(_) => conventional_fee(inputs.len(), 0, 1, 0),
+            Recipient::Orchard(_) => conventional_fee(inputs.len(), 0, 0, 1),
+        };
+        if available <= fee {
+            return Err(FundingError::InsufficientFunds {
+                required: fee + 1,
+                available,
+            });
+        }
+
+        self.send(
+            validator,
+            Spend {
+                inputs,
+                recipients: vec![(recipient, available - fee)],
+                change: 0,
+                fee,
+            },
+        )
+        .await
+    }
+
+    /// Builds and broadcasts the transaction of the `spend` and returns its txid.
+    async fn send<V: Validator + Sync>(
+        &self,
+        validator: &V,
+        spend: Spend,
+    ) -> Result<String, FundingError> {
+        let (txid, transaction) = self.build_transaction(validator, &spend).await?;
+
+        validator
+            .call_rpc("sendrawtransaction", vec![transaction.into()])
+            .await
+            .map_err(|e| match e {
+                RpcError::Response { message, .. } => FundingError::Rejected(message),
+                e => e.into(),
+            })?;
+        self.spent.lock().unwrap().extend(
+            spend
+                .inputs
+                .into_iter()
+                .map(|utxo| (utxo.txid, utxo.output_index)),
+        );
+
+        Ok(txid)
+    }
+
+    /// Builds the transaction of the `spend`, spending its inputs to its recipients, returning the change to the
+    /// funding address and paying the fee. Returns the txid and the hex encoded transaction.
+    async fn build_transaction<V: Validator + Sync>(
+        &self,
+        validator: &V,
+        spend: &Spend,
+    ) -> Result<(String, String), FundingError> {
+        let Spend {
+            inputs,
+            recipients,
+            change,
+            fee,
+        } = spend;
+        let target_height: BlockHeight = validator.get_chain_height().await + 1;
+        let mut builder = Builder::new(
+            self.params,
+            target_height,
+            BuildConfig::Standard {
+                sapling_anchor: Some(sapling::Anchor::empty_tree()),
+                orchard_anchor: Some(orchard::Anchor::empty_tree()),
+            },
+        );
+        for utxo in inputs {
+            let mut txid = [0; 32];
+            hex::decode_to_slice(&utxo.txid, &mut txid)
+                .map_err(|e| FundingError::Build(e.to_string()))?;
+            // txids are displayed in reverse byte order
+            txid.reverse();
+            let coin = TxOut {
+                value: zatoshis(utxo.value)?,
+                script_pubkey: Script(
+                    hex::decode(&utxo.script).map_err(|e| FundingError::Build(e.to_string()))?,
+                ),
+            };
+            builder
+                .add_transparent_input(
+                    self.secret_key,
+                    OutPoint::new(txid, utxo.output_index),
+                    coin,
+                )
+                .map_err(|e| FundingError::Build(e.to_string()))?;
+        }
+        for (recipient, value) in recipients {
+            let value = *value;
+            match recipient {
+                Recipient::Transparent(address) => builder
+                    .add_transparent_output(address, zatoshis(value)?)
+                    .map_err(|e| FundingError::Build(e.to_string())),
+                Recipient::Sapling(address) => builder
+                    .add_sapling_output::<std::convert::Infallible>(
+                        None,
+                        *address,
+                        zatoshis(value)?,
+                        MemoBytes::empty(),
+                    )
+                    .map_err(|e| FundingError::Build(e.to_string())),
+                Recipient::Orchard(address) => builder
+                    .add_orchard_output::<std::convert::Infallible>(
+                        None,
+                        *address,
+                        value,
+                        MemoBytes::empty(),
+                    )
+                    .map_err(|e| FundingError::Build(e.to_string())),
+            }?;
+        }
+        if *change > 0 {
+            builder
+                .add_transparent_output(&self.address, zatoshis(*change)?)
+                .map_err(|e| FundingError::Build(e.to_string()))?;
+        }
+
+        let prover = LocalTxProver::bundled();
+        let build_result = builder
+            .build(
+                &mut *self.rng.lock().unwrap(),
+                &prover,
+                &prover,
+                &FixedFeeRule::non_standard(zatoshis(*fee)?),
+            )
+            .map_err(|e| FundingError::Build(e.to_string()))?;
+        let mut transaction_data = Vec::new();
+        build_result
+            .transaction()
+            .write(&mut transaction_data)
+            .map_err(|e| FundingError::Build(e.to_string()))?;
+        let txid = build_result.transaction().txid().to_string();
+
+        Ok((txid, hex::encode(transaction_data)))
+    }
+
+    fn decode_address(&self, address: &str) -> Result<Recipient, FundingError> {
+        let invalid = || FundingError::InvalidAddress(address.to_string());
+
+        match Address::decode(&self.params, address).ok_or_else(invalid)? {
+            Address::Transparent(address) => Ok(Recipient::Transparent(address)),
+            Address::Sapling(address) => Ok(Recipient::Sapling(address)),
+            Address::Unified(address) => {
+                if let Some(address) = address.orchard() {
+                    Ok(Recipient::Orchard(*address))
+                } else if let Some(address) = address.sapling() {
+                    Ok(Recipient::Sapling(*address))
+                } else {
+                    address
+                        .transparent()
+                        .map(|address| Recipient::Transparent(*address))
+                        .ok_or_else(invalid)
+                }
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Returns `true` if a coinbase output mined at `height` can be spent in the block at `next_height`.
+fn is_mature(height: u32, next_height: u32) -> bool {
+    height + COINBASE_MATURITY <= next_height
+}
+
+/// Returns `true` if `txid` is the coinbase transaction of the block at `height`.
+async fn is_coinbase<V: Validator + Sync>(
+    validator: &V,
+    txid: &str,
+    height: u32,
+) -> Result<bool, RpcError> {
+    let block = validator
+        .call_rpc(
+            "getblock",
+            vec![height.to_string().into(), serde_json::json!(1)],
+        )
+        .await?;
+
+    Ok(block["tx"][0].as_str() == Some(txid))
+}
+
+/// Returns the ZIP 317 conventional fee of a transaction spending `transparent_inputs` P2PKH outputs.
+///
+/// Sapling outputs and Orchard actions are padded to 2 by the transaction builder so are counted with padding.
+fn conventional_fee(
+    transparent_inputs: usize,
+    transparent_outputs: usize,
+    sapling_outputs: usize,
+    orchard_outputs: usize,
+) -> u64 {
+    let padded = |outputs: usize| if outputs == 0 { 0 } else { outputs.max(2) };
+    let logical_actions = transparent_inputs.max(transparent_outputs)
+        + padded(sapling_outputs)
+        + padded(orchard_outputs);
+
+    MARGINAL_FEE * logical_actions.max(GRACE_ACTIONS) as u64
+}
+
+fn zatoshis(value: u64) -> Result<NonNegativeAmount, FundingError> {
+    NonNegativeAmount::from_u64(value)
+        .map_err(|_| FundingError::Build(format!("{value} zatoshis is not a valid amount")))
+}
+
+#[cfg(test)]
+mod tests {
+    use zcash_keys::address::Address;
+
+    use crate::network::ActivationHeights;
+
+    #[test]
+    fn funding_address() {
+        let funder = super::Funder::new(&ActivationHeights::default());
+
+        assert_eq!(
+            Address::Transparent(funder.address).encode(&funder.params),
+            super::FUNDING_ADDRESS
+        );
+    }
+
+    #[test]
+    fn is_mature() {
+        assert!(!super::is_mature(1, 100));
+        assert!(super::is_mature(1, 101));
+    }
+
+    #[test]
+    fn conventional_fee() {
+        // grace actions
+        assert_eq!(super::conventional_fee(1, 1, 0, 0), 10_000);
+        assert_eq!(super::conventional_fee(3, 2, 0, 0), 15_000);
+        // padded shielded outputs
+        assert_eq!(super::conventional_fee(1, 1, 1, 0), 15_000);
+        assert_eq!(super::conventional_fee(1, 1, 0, 1), 15_000);
+        assert_eq!(super::conventional_fee(2, 1, 3, 1), 35_000);
+    }
+}
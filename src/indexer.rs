@@ -3,19 +3,42 @@
 //! Processes which are not strictly indexers but have a similar role in serving light-clients/light-wallets
 //! (i.e. Lightwalletd) are also included in this category and are referred to as "light-nodes".
 
+#[cfg(feature = "client")]
+use std::ops::ControlFlow;
 use std::{fs::File, path::PathBuf, process::Child};
 
 use getset::{CopyGetters, Getters};
 use portpicker::Port;
 use tempfile::TempDir;
-
-use crate::{config, error::LaunchError, launch, logs, network, Process};
+use zcash_protocol::consensus::BlockHeight;
+
+#[cfg(feature = "client")]
+use zcash_client_backend::proto::service::{
+    self, compact_tx_streamer_client::CompactTxStreamerClient,
+};
+#[cfg(feature = "client")]
+use zingo_netutils::{GetClientError, UnderlyingService};
+
+use crate::{
+    config,
+    error::{ConfigError, LaunchError},
+    launch, logs, network, Process,
+};
+#[cfg(feature = "client")]
+use crate::{
+    error::{RpcError, WaitError},
+    network::Network,
+    utils,
+};
 
 /// Zainod configuration
 ///
 /// Use `fixed_port` to specify a port for Zainod. Otherwise, a port is picked at random between 15000-25000.
 ///
 /// The `validator_port` must be specified and the validator process must be running before launching Zainod.
+///
+/// Use `extra_options` to add or override zindexer.toml options and `extra_args` to pass additional command-line
+/// arguments to Zainod. Options managed by this crate (ports and validator credentials) can not be overridden.
 pub struct ZainodConfig {
     /// Zainod binary location
     pub zainod_bin: Option<PathBuf>,
@@ -23,6 +46,10 @@ pub struct ZainodConfig {
     pub listen_port: Option<Port>,
     /// Validator RPC port
     pub validator_port: Port,
+    /// Extra zindexer.toml options
+    pub extra_options: toml::Table,
+    /// Extra command-line arguments
+    pub extra_args: Vec<String>,
 }
 
 /// Lightwalletd configuration
@@ -30,6 +57,10 @@ pub struct ZainodConfig {
 /// Use `fixed_port` to specify a port for Lightwalletd. Otherwise, a port is picked at random between 15000-25000.
 ///
 /// The `validator_port` must be specified and the validator process must be running before launching Lightwalletd.
+///
+/// Use `extra_options` to add or override lightwalletd.yml options and `extra_args` to pass additional command-line
+/// arguments to Lightwalletd. Options managed by this crate (listen address, log file and zcashd config path) can
+/// not be overridden.
 pub struct LightwalletdConfig {
     /// Lightwalletd binary location
     pub lightwalletd_bin: Option<PathBuf>,
@@ -37,6 +68,10 @@ pub struct LightwalletdConfig {
     pub listen_port: Option<Port>,
     /// Zcashd configuration file location
     pub zcashd_conf: PathBuf,
+    /// Extra lightwalletd.yml options
+    pub extra_options: serde_yaml::Mapping,
+    /// Extra command-line arguments
+    pub extra_args: Vec<String>,
 }
 
 /// Functionality for indexer/light-node processes.
@@ -47,6 +82,9 @@ pub trait Indexer: Sized {
     /// Indexer config struct
     type Config;
 
+    /// Typed model of the config file
+    type ConfigFile: config::ConfigFile;
+
     /// Launch the process.
     fn launch(config: Self::Config) -> Result<Self, LaunchError>;
 
@@ -59,11 +97,168 @@ pub trait Indexer: Sized {
     /// Get temporary logs directory.
     fn logs_dir(&self) -> &TempDir;
 
+    /// Get the gRPC listen port.
+    fn port(&self) -> Port;
+
+    /// Get the RPC port of the validator the indexer is attached to.
+    fn validator_port(&self) -> Port;
+
+    /// Returns the gRPC URI.
+    fn uri(&self) -> http::Uri {
+        network::localhost_uri(self.port())
+    }
+
+    /// Returns the RPC URI of the validator the indexer is attached to.
+    fn validator_uri(&self) -> http::Uri {
+        network::localhost_uri(self.validator_port())
+    }
+
+    /// Builds a client for creating gRPC requests to the indexer.
+    #[cfg(feature = "client")]
+    fn client(
+        &self,
+    ) -> impl std::future::Future<
+        Output = Result<CompactTxStreamerClient<UnderlyingService>, GetClientError>,
+    > + Send {
+        crate::client::build_client(self.uri())
+    }
+
+    /// Gets the network served by the indexer from the chain name returned by `GetLightdInfo`.
+    #[cfg(feature = "client")]
+    fn network(&self) -> impl std::future::Future<Output = Result<Network, RpcError>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let info = self
+                .client()
+                .await
+                .map_err(|e| request_error("GetLightdInfo", e))?
+                .get_lightd_info(service::Empty {})
+                .await
+                .map_err(|e| request_error("GetLightdInfo", e))?
+                .into_inner();
+
+            match info.chain_name.as_str() {
+                "main" => Ok(Network::Mainnet),
+                "test" => Ok(Network::Testnet),
+                "regtest" => Ok(Network::Regtest),
+                chain_name => Err(RpcError::InvalidResponse {
+                    method: "GetLightdInfo".to_string(),
+                    reason: format!("unknown chain name `{chain_name}`"),
+                }),
+            }
+        }
+    }
+
+    /// Polls `GetMempoolTx` until the transaction `txid` is in the indexer's mempool.
+    ///
+    /// Returns [`WaitError::MempoolTimeout`] if the transaction is not in the mempool within `timeout`.
+    #[cfg(feature = "client")]
+    fn wait_for_mempool(
+        &self,
+        txid: &str,
+        timeout: std::time::Duration,
+    ) -> impl std::future::Future<Output = Result<(), WaitError>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let client = self
+                .client()
+                .await
+                .map_err(|e| request_error("GetMempoolTx", e))?;
+            utils::poll_until(timeout, utils::POLL_INTERVAL, || {
+                let mut client = client.clone();
+                async move {
+                    let mut response = client
+                        .get_mempool_tx(service::Exclude { txid: Vec::new() })
+                        .await
+                        .map_err(|e| request_error("GetMempoolTx", e))?
+                        .into_inner();
+                    while let Some(compact_tx) = response
+                        .message()
+                        .await
+                        .map_err(|e| request_error("GetMempoolTx", e))?
+                    {
+                        // compact transaction hashes are in internal byte order, the reverse of the txid
+                        let mut hash = compact_tx.hash;
+                        hash.reverse();
+                        if hex::encode(hash) == txid {
+                            return Ok(ControlFlow::Break(()));
+                        }
+                    }
+
+                    Ok::<_, WaitError>(ControlFlow::Continue(()))
+                }
+            })
+            .await?
+            .map_err(|()| WaitError::MempoolTimeout {
+                txid: txid.to_string(),
+                timeout,
+            })
+        }
+    }
+
+    /// Polls `GetLatestBlock` until the indexer's chain height reaches `height`.
+    ///
+    /// Returns [`WaitError::HeightTimeout`] with the indexer's chain height if `height` is not reached within
+    /// `timeout`.
+    #[cfg(feature = "client")]
+    fn wait_for_height(
+        &self,
+        height: BlockHeight,
+        timeout: std::time::Duration,
+    ) -> impl std::future::Future<Output = Result<(), WaitError>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let client = self
+                .client()
+                .await
+                .map_err(|e| request_error("GetLatestBlock", e))?;
+            utils::poll_until(timeout, utils::POLL_INTERVAL, || {
+                let mut client = client.clone();
+                async move {
+                    let indexer_height = client
+                        .get_latest_block(service::ChainSpec {})
+                        .await
+                        .map_err(|e| request_error("GetLatestBlock", e))?
+                        .into_inner()
+                        .height;
+                    let indexer_height =
+                        u32::try_from(indexer_height).map_err(|_| RpcError::InvalidResponse {
+                            method: "GetLatestBlock".to_string(),
+                            reason: format!("height {indexer_height} out of range"),
+                        })?;
+
+                    Ok::<_, RpcError>(if indexer_height >= u32::from(height) {
+                        ControlFlow::Break(())
+                    } else {
+                        ControlFlow::Continue(indexer_height)
+                    })
+                }
+            })
+            .await?
+            .map_err(|indexer_height| WaitError::HeightTimeout {
+                height: height.into(),
+                indexer_height,
+                timeout,
+            })
+        }
+    }
+
     /// Returns path to config file.
     fn config_path(&self) -> PathBuf {
         self.config_dir().path().join(Self::CONFIG_FILENAME)
     }
 
+    /// Parses the config file written for this process.
+    fn read_config(&self) -> Result<Self::ConfigFile, ConfigError> {
+        <Self::ConfigFile as config::ConfigFile>::read(&self.config_path())
+    }
+
     /// Prints the stdout log.
     fn print_stdout(&self) {
         let stdout_log_path = self.logs_dir().path().join(logs::STDOUT_LOG);
@@ -87,6 +282,9 @@ pub struct Zainod {
     #[getset(skip)]
     #[getset(get_copy = "pub")]
     port: Port,
+    /// Validator RPC port
+    #[getset(skip)]
+    validator_port: Port,
     /// Logs directory
     logs_dir: TempDir,
     /// Config directory
@@ -98,13 +296,21 @@ impl Indexer for Zainod {
 
     type Config = ZainodConfig;
 
+    type ConfigFile = config::ZainodToml;
+
     fn launch(config: Self::Config) -> Result<Self, LaunchError> {
+        config::validate_zainod_extras(&config.extra_options, &config.extra_args)?;
+
         let logs_dir = tempfile::tempdir().unwrap();
 
         let port = network::pick_unused_port(config.listen_port);
         let config_dir = tempfile::tempdir().unwrap();
-        let config_file_path =
-            config::zainod(config_dir.path(), port, config.validator_port).unwrap();
+        let config_file_path = config::zainod(
+            config_dir.path(),
+            port,
+            config.validator_port,
+            &config.extra_options,
+        )?;
 
         let mut command = match config.zainod_bin {
             Some(path) => std::process::Command::new(path),
@@ -115,6 +321,7 @@ impl Indexer for Zainod {
                 "--config",
                 config_file_path.to_str().expect("should be valid UTF-8"),
             ])
+            .args(&config.extra_args)
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped());
 
@@ -133,6 +340,7 @@ impl Indexer for Zainod {
         Ok(Zainod {
             handle,
             port,
+            validator_port: config.validator_port,
             logs_dir,
             config_dir,
         })
@@ -149,6 +357,14 @@ impl Indexer for Zainod {
     fn logs_dir(&self) -> &TempDir {
         &self.logs_dir
     }
+
+    fn port(&self) -> Port {
+        self.port
+    }
+
+    fn validator_port(&self) -> Port {
+        self.validator_port
+    }
 }
 
 impl Drop for Zainod {
@@ -167,6 +383,9 @@ pub struct Lightwalletd {
     #[getset(skip)]
     #[getset(get_copy = "pub")]
     port: Port,
+    /// Validator RPC port
+    #[getset(skip)]
+    validator_port: Port,
     /// Data directory
     _data_dir: TempDir,
     /// Logs directory
@@ -188,7 +407,12 @@ impl Indexer for Lightwalletd {
 
     type Config = LightwalletdConfig;
 
+    type ConfigFile = config::LightwalletdYml;
+
     fn launch(config: Self::Config) -> Result<Self, LaunchError> {
+        config::validate_lightwalletd_extras(&config.extra_options, &config.extra_args)?;
+        let validator_port = config::ZcashConf::read_rpcport(&config.zcashd_conf)?;
+
         let logs_dir = tempfile::tempdir().unwrap();
         let lwd_log_file_path = logs_dir.path().join(logs::LIGHTWALLETD_LOG);
         let _lwd_log_file = File::create(&lwd_log_file_path).unwrap();
@@ -202,8 +426,8 @@ impl Indexer for Lightwalletd {
             port,
             lwd_log_file_path.clone(),
             config.zcashd_conf.clone(),
-        )
-        .unwrap();
+            &config.extra_options,
+        )?;
 
         let mut command = match config.lightwalletd_bin {
             Some(path) => std::process::Command::new(path),
@@ -221,6 +445,7 @@ impl Indexer for Lightwalletd {
                 "--config",
                 config_file_path.to_str().unwrap(),
             ])
+            .args(&config.extra_args)
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped());
 
@@ -239,6 +464,7 @@ impl Indexer for Lightwalletd {
         Ok(Lightwalletd {
             handle,
             port,
+            validator_port,
             _data_dir: data_dir,
             logs_dir,
             config_dir,
@@ -256,6 +482,14 @@ impl Indexer for Lightwalletd {
     fn logs_dir(&self) -> &TempDir {
         &self.logs_dir
     }
+
+    fn port(&self) -> Port {
+        self.port
+    }
+
+    fn validator_port(&self) -> Port {
+        self.validator_port
+    }
 }
 
 impl Drop for Lightwalletd {
@@ -263,3 +497,11 @@ impl Drop for Lightwalletd {
         self.stop();
     }
 }
+
+#[cfg(feature = "client")]
+fn request_error(method: &str, reason: impl std::fmt::Display) -> RpcError {
+    RpcError::Request {
+        method: method.to_string(),
+        reason: reason.to_string(),
+    }
+}
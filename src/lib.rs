@@ -26,17 +26,34 @@
 //! - Run `cargo test generate_zebrad_large_chain_cache --features test_fixtures -- --ignored` or `cargo nextest run generate_zebrad_large_chain_cache --run-ignored ignored-only --features test_fixtures`
 //! - To run the `get_subtree_roots` tests, sync Zebrad in testnet mode and copy the cache to `zcash_local_net/chain_cache/testnet_get_subtree_roots_sapling` and `zcash_local_net/chain_cache/testnet_get_subtree_roots_orchard` directories. At least 2 shards for each protocol must be synced to pass. See `zcash_local_net::test_fixtures::get_subtree_roots_sapling` doc comments for more details.
 //!
+//! The `client_rpc_tests` chain cache used by the client rpc test fixtures is regenerated automatically when it is missing or was generated from a different scenario or zcashd, zcash-cli or lightwalletd version. Generated chain caches are not tracked by git.
+//!
+//! Chain caches are read from and written to the `chain_cache` directory of this repository, set by `.cargo/config.toml`. Outside of this repository the chain cache directory is resolved from [`utils::set_chain_cache_dir`], the `ZCASH_LOCAL_NET_CHAIN_CACHE_DIR` environment variable or the `zcash_local_net` directory in the user cache directory, in that order. See [`utils::chain_cache_dir`].
+//!
 //! See `src/test_fixtures.rs` doc comments for running client rpc tests from external crates for indexer/validator development.
 
+use std::path::Path;
+
+use config::{ConfigFile as _, LocalNetToml};
+use error::LaunchError;
+#[cfg(feature = "client")]
+use error::WaitError;
 use indexer::{Indexer, Lightwalletd, LightwalletdConfig, Zainod, ZainodConfig};
 use validator::{Validator, Zcashd, ZcashdConfig, Zebrad, ZebradConfig};
 
-pub(crate) mod config;
+#[cfg(feature = "auto_mine")]
+pub mod auto_mine;
+pub mod chain_cache;
+pub mod config;
 pub mod error;
+pub mod faucet;
+#[cfg(feature = "funding")]
+pub mod funding;
 pub mod indexer;
 pub(crate) mod launch;
 pub(crate) mod logs;
 pub mod network;
+pub mod replay;
 pub mod utils;
 pub mod validator;
 
@@ -46,6 +63,9 @@ pub mod test_fixtures;
 #[cfg(feature = "client")]
 pub mod client;
 
+#[cfg(feature = "client")]
+pub mod scenario;
+
 #[derive(Clone, Copy)]
 enum Process {
     Zcashd,
@@ -100,18 +120,82 @@ where
     pub fn validator_mut(&mut self) -> &mut V {
         &mut self.validator
     }
+
+    /// Starts mining blocks in the background when `mode` is satisfied. The returned auto-miner owns the local network
+    /// until [`auto_mine::AutoMiner::stop`] returns it. See [`crate::auto_mine`].
+    #[cfg(feature = "auto_mine")]
+    pub fn auto_mine(self, mode: auto_mine::AutoMine) -> auto_mine::AutoMiner<I, V>
+    where
+        V: Send + Sync + 'static,
+    {
+        auto_mine::AutoMiner::spawn(self, mode)
+    }
+
+    /// Waits for the indexer to sync to the validator's chain height and returns the height.
+    ///
+    /// Returns [`WaitError::SyncTimeout`] with both chain heights if the indexer does not sync within `timeout`. The
+    /// validator's chain height is queried again on timeout, so it includes blocks mined while waiting.
+    #[cfg(feature = "client")]
+    pub async fn sync_all(
+        &self,
+        timeout: std::time::Duration,
+    ) -> Result<zcash_protocol::consensus::BlockHeight, WaitError>
+    where
+        I: Sync,
+    {
+        let height = self.validator.get_chain_height().await;
+        match self.indexer.wait_for_height(height, timeout).await {
+            Ok(()) => Ok(height),
+            Err(WaitError::HeightTimeout {
+                indexer_height,
+                timeout,
+                ..
+            }) => Err(WaitError::SyncTimeout {
+                validator_height: self.validator.get_chain_height().await.into(),
+                indexer_height,
+                timeout,
+            }),
+            Err(e) => Err(e),
+        }
+    }
 }
 
 impl LocalNet<Zainod, Zcashd> {
     /// Launch LocalNet.
     ///
     /// The `validator_port` field of [`crate::indexer::ZainodConfig`] will be overwritten to match the validator's RPC port.
-    pub async fn launch(mut indexer_config: ZainodConfig, validator_config: ZcashdConfig) -> Self {
-        let validator = Zcashd::launch(validator_config).await.unwrap();
+    ///
+    /// # Panics
+    ///
+    /// Panics if the validator or indexer fails to launch. See [`Self::try_launch`].
+    pub async fn launch(indexer_config: ZainodConfig, validator_config: ZcashdConfig) -> Self {
+        Self::try_launch(indexer_config, validator_config)
+            .await
+            .unwrap()
+    }
+
+    /// Launch LocalNet, returning an error if the validator or indexer fails to launch.
+    ///
+    /// The `validator_port` field of [`crate::indexer::ZainodConfig`] will be overwritten to match the validator's RPC port.
+    pub async fn try_launch(
+        mut indexer_config: ZainodConfig,
+        validator_config: ZcashdConfig,
+    ) -> Result<Self, LaunchError> {
+        let validator = Zcashd::launch(validator_config).await?;
         indexer_config.validator_port = validator.port();
-        let indexer = Zainod::launch(indexer_config).unwrap();
+        let indexer = Zainod::launch(indexer_config)?;
+
+        Ok(LocalNet { indexer, validator })
+    }
+
+    /// Launch LocalNet from a declarative config file. See [`crate::config::LocalNetToml`].
+    ///
+    /// Returns an error if the config file can not be read or does not describe a Zcashd validator and Zainod
+    /// indexer, or if either process fails to launch.
+    pub async fn launch_from_file(path: &Path) -> Result<Self, LaunchError> {
+        let config = LocalNetToml::read(path)?;
 
-        LocalNet { indexer, validator }
+        Self::try_launch(config.zainod_config()?, config.zcashd_config()?).await
     }
 }
 
@@ -119,12 +203,38 @@ impl LocalNet<Zainod, Zebrad> {
     /// Launch LocalNet.
     ///
     /// The `validator_port` field of [`crate::indexer::ZainodConfig`] will be overwritten to match the validator's RPC port.
-    pub async fn launch(mut indexer_config: ZainodConfig, validator_config: ZebradConfig) -> Self {
-        let validator = Zebrad::launch(validator_config).await.unwrap();
+    ///
+    /// # Panics
+    ///
+    /// Panics if the validator or indexer fails to launch. See [`Self::try_launch`].
+    pub async fn launch(indexer_config: ZainodConfig, validator_config: ZebradConfig) -> Self {
+        Self::try_launch(indexer_config, validator_config)
+            .await
+            .unwrap()
+    }
+
+    /// Launch LocalNet, returning an error if the validator or indexer fails to launch.
+    ///
+    /// The `validator_port` field of [`crate::indexer::ZainodConfig`] will be overwritten to match the validator's RPC port.
+    pub async fn try_launch(
+        mut indexer_config: ZainodConfig,
+        validator_config: ZebradConfig,
+    ) -> Result<Self, LaunchError> {
+        let validator = Zebrad::launch(validator_config).await?;
         indexer_config.validator_port = validator.rpc_listen_port();
-        let indexer = Zainod::launch(indexer_config).unwrap();
+        let indexer = Zainod::launch(indexer_config)?;
 
-        LocalNet { indexer, validator }
+        Ok(LocalNet { indexer, validator })
+    }
+
+    /// Launch LocalNet from a declarative config file. See [`crate::config::LocalNetToml`].
+    ///
+    /// Returns an error if the config file can not be read or does not describe a Zebrad validator and Zainod
+    /// indexer, or if either process fails to launch.
+    pub async fn launch_from_file(path: &Path) -> Result<Self, LaunchError> {
+        let config = LocalNetToml::read(path)?;
+
+        Self::try_launch(config.zainod_config()?, config.zebrad_config()?).await
     }
 }
 
@@ -132,15 +242,41 @@ impl LocalNet<Lightwalletd, Zcashd> {
     /// Launch LocalNet.
     ///
     /// The `validator_conf` field of [`crate::indexer::LightwalletdConfig`] will be overwritten to match the validator's config path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the validator or indexer fails to launch. See [`Self::try_launch`].
     pub async fn launch(
-        mut indexer_config: LightwalletdConfig,
+        indexer_config: LightwalletdConfig,
         validator_config: ZcashdConfig,
     ) -> Self {
-        let validator = Zcashd::launch(validator_config).await.unwrap();
+        Self::try_launch(indexer_config, validator_config)
+            .await
+            .unwrap()
+    }
+
+    /// Launch LocalNet, returning an error if the validator or indexer fails to launch.
+    ///
+    /// The `validator_conf` field of [`crate::indexer::LightwalletdConfig`] will be overwritten to match the validator's config path.
+    pub async fn try_launch(
+        mut indexer_config: LightwalletdConfig,
+        validator_config: ZcashdConfig,
+    ) -> Result<Self, LaunchError> {
+        let validator = Zcashd::launch(validator_config).await?;
         indexer_config.zcashd_conf = validator.config_path();
-        let indexer = Lightwalletd::launch(indexer_config).unwrap();
+        let indexer = Lightwalletd::launch(indexer_config)?;
 
-        LocalNet { indexer, validator }
+        Ok(LocalNet { indexer, validator })
+    }
+
+    /// Launch LocalNet from a declarative config file. See [`crate::config::LocalNetToml`].
+    ///
+    /// Returns an error if the config file can not be read or does not describe a Zcashd validator and Lightwalletd
+    /// indexer, or if either process fails to launch.
+    pub async fn launch_from_file(path: &Path) -> Result<Self, LaunchError> {
+        let config = LocalNetToml::read(path)?;
+
+        Self::try_launch(config.lightwalletd_config()?, config.zcashd_config()?).await
     }
 }
 
@@ -148,14 +284,40 @@ impl LocalNet<Lightwalletd, Zebrad> {
     /// Launch LocalNet.
     ///
     /// The `validator_conf` field of [`crate::indexer::LightwalletdConfig`] will be overwritten to match the validator's config path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the validator or indexer fails to launch. See [`Self::try_launch`].
     pub async fn launch(
-        mut indexer_config: LightwalletdConfig,
+        indexer_config: LightwalletdConfig,
         validator_config: ZebradConfig,
     ) -> Self {
-        let validator = Zebrad::launch(validator_config).await.unwrap();
+        Self::try_launch(indexer_config, validator_config)
+            .await
+            .unwrap()
+    }
+
+    /// Launch LocalNet, returning an error if the validator or indexer fails to launch.
+    ///
+    /// The `validator_conf` field of [`crate::indexer::LightwalletdConfig`] will be overwritten to match the validator's config path.
+    pub async fn try_launch(
+        mut indexer_config: LightwalletdConfig,
+        validator_config: ZebradConfig,
+    ) -> Result<Self, LaunchError> {
+        let validator = Zebrad::launch(validator_config).await?;
         indexer_config.zcashd_conf = validator.config_dir().path().join(config::ZCASHD_FILENAME);
-        let indexer = Lightwalletd::launch(indexer_config).unwrap();
+        let indexer = Lightwalletd::launch(indexer_config)?;
+
+        Ok(LocalNet { indexer, validator })
+    }
+
+    /// Launch LocalNet from a declarative config file. See [`crate::config::LocalNetToml`].
+    ///
+    /// Returns an error if the config file can not be read or does not describe a Zebrad validator and Lightwalletd
+    /// indexer, or if either process fails to launch.
+    pub async fn launch_from_file(path: &Path) -> Result<Self, LaunchError> {
+        let config = LocalNetToml::read(path)?;
 
-        LocalNet { indexer, validator }
+        Self::try_launch(config.lightwalletd_config()?, config.zebrad_config()?).await
     }
 }
@@ -1,12 +1,21 @@
 //! Structs and utility functions associated with local network configuration
 
 use portpicker::Port;
+use serde::{Deserialize, Serialize};
+use zcash_keys::address::Address;
 use zcash_primitives::consensus::BlockHeight;
+use zcash_protocol::{consensus, local_consensus::LocalNetwork};
+
+use crate::error::CoinbaseError;
 
 pub(crate) const LOCALHOST_IPV4: &str = "http://127.0.0.1";
 
+/// Number of blocks mined on top of a coinbase output before it can be spent
+pub const COINBASE_MATURITY: u32 = 100;
+
 /// Network types
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Network {
     /// Regtest
     Regtest,
@@ -27,6 +36,8 @@ impl std::fmt::Display for Network {
 }
 
 /// Activation heights for local network upgrades
+///
+/// By default, all network upgrades up to NU5 are active from block height 1 and NU6 is not activated.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct ActivationHeights {
     /// Overwinter network upgrade activation height
@@ -41,6 +52,8 @@ pub struct ActivationHeights {
     pub canopy: BlockHeight,
     /// Nu5 (a.k.a. Orchard) network upgrade activation height
     pub nu5: BlockHeight,
+    /// Nu6 network upgrade activation height. If `None`, NU6 is not activated.
+    pub nu6: Option<BlockHeight>,
 }
 
 impl Default for ActivationHeights {
@@ -52,6 +65,123 @@ impl Default for ActivationHeights {
             heartwood: 1.into(),
             canopy: 1.into(),
             nu5: 1.into(),
+            nu6: None,
+        }
+    }
+}
+
+impl From<&ActivationHeights> for LocalNetwork {
+    fn from(activation_heights: &ActivationHeights) -> Self {
+        LocalNetwork {
+            overwinter: Some(activation_heights.overwinter),
+            sapling: Some(activation_heights.sapling),
+            blossom: Some(activation_heights.blossom),
+            heartwood: Some(activation_heights.heartwood),
+            canopy: Some(activation_heights.canopy),
+            nu5: Some(activation_heights.nu5),
+            nu6: activation_heights.nu6,
+        }
+    }
+}
+
+/// Funding stream receivers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FundingStreamReceiver {
+    /// Electric Coin Company (Bootstrap Foundation) funding stream. Pre-NU6 only.
+    Ecc,
+    /// Zcash Foundation funding stream. Pre-NU6 only.
+    ZcashFoundation,
+    /// Major Grants (Zcash Community Grants) funding stream
+    MajorGrants,
+    /// Deferred development fund, paid into the NU6 lockbox instead of a coinbase output. Post-NU6 only.
+    Deferred,
+}
+
+/// Funding stream recipient
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FundingStreamRecipient {
+    /// Funding stream receiver
+    pub receiver: FundingStreamReceiver,
+    /// Share of the block subsidy in hundredths.
+    ///
+    /// Zcashd numerators are fixed by consensus rules so zcashd streams must use the consensus numerator of the
+    /// receiver.
+    pub numerator: u64,
+    /// Recipient addresses, one for each address change interval in the funding stream height range.
+    ///
+    /// Must be empty for the [`FundingStreamReceiver::Deferred`] lockbox stream.
+    pub addresses: Vec<String>,
+}
+
+/// Funding stream recipients active for a range of block heights
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FundingStreamPeriod {
+    /// Block heights the funding streams are active for. The start height is inclusive and the end height is
+    /// exclusive.
+    pub height_range: std::ops::Range<BlockHeight>,
+    /// Funding stream recipients
+    pub recipients: Vec<FundingStreamRecipient>,
+}
+
+/// Regtest funding streams and NU6 lockbox configuration
+///
+/// By default, no funding streams are configured and the validator's regtest defaults are used.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FundingStreams {
+    /// Funding streams before NU6 activation (ZIP 214)
+    pub pre_nu6: Option<FundingStreamPeriod>,
+    /// Funding streams and lockbox contribution after NU6 activation (ZIP 1015)
+    pub post_nu6: Option<FundingStreamPeriod>,
+}
+
+/// Returns the number of blocks mined after a coinbase output paid to `address` on the `network` before the output
+/// can be spent.
+///
+/// Transparent coinbase outputs mature after [`COINBASE_MATURITY`] blocks. Shielded coinbase outputs (ZIP 213) can be
+/// spent by light clients once mined, but can only be paid to Sapling or unified addresses once Heartwood is active.
+/// Unified addresses are paid to their Orchard receiver once NU5 is active, otherwise to their Sapling receiver once
+/// Heartwood is active, otherwise to their transparent receiver.
+pub(crate) fn coinbase_maturity(
+    address: &str,
+    network: Network,
+    heartwood_active: bool,
+    nu5_active: bool,
+) -> Result<u32, CoinbaseError> {
+    let upgrade_not_active = |network_upgrade: &str| CoinbaseError::UpgradeNotActive {
+        address: address.to_string(),
+        network_upgrade: network_upgrade.to_string(),
+    };
+
+    match decode_address(address, network) {
+        Some(Address::Transparent(_)) => Ok(COINBASE_MATURITY),
+        Some(Address::Sapling(_)) if heartwood_active => Ok(1),
+        Some(Address::Sapling(_)) => Err(upgrade_not_active("Heartwood")),
+        Some(Address::Unified(address)) => {
+            if address.orchard().is_some() && nu5_active
+                || address.sapling().is_some() && heartwood_active
+            {
+                Ok(1)
+            } else if address.transparent().is_some() {
+                Ok(COINBASE_MATURITY)
+            } else if address.sapling().is_some() {
+                Err(upgrade_not_active("Heartwood"))
+            } else {
+                Err(upgrade_not_active("NU5"))
+            }
+        }
+        _ => Err(CoinbaseError::UnsupportedAddress(address.to_string())),
+    }
+}
+
+/// Decodes `address` for the `network`. Returns `None` if the address is invalid or for another network.
+fn decode_address(address: &str, network: Network) -> Option<Address> {
+    match network {
+        Network::Mainnet => Address::decode(&consensus::MAIN_NETWORK, address),
+        Network::Testnet => Address::decode(&consensus::TEST_NETWORK, address),
+        // only the network type of the parameters is used to decode addresses
+        Network::Regtest => {
+            Address::decode(&LocalNetwork::from(&ActivationHeights::default()), address)
         }
     }
 }
@@ -73,3 +203,45 @@ pub(crate) fn pick_unused_port(fixed_port: Option<Port>) -> Port {
 pub fn localhost_uri(port: Port) -> http::Uri {
     format!("{}:{}", LOCALHOST_IPV4, port).try_into().unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Network, COINBASE_MATURITY};
+    use crate::error::CoinbaseError;
+
+    #[test]
+    fn coinbase_maturity() {
+        const T_ADDR: &str = "tmBsTi2xWTjUdEXnuTceL7fecEQKeWaPDJd";
+        const Z_ADDR: &str = "zregtestsapling1fmq2ufux3gm0v8qf7x585wj56le4wjfsqsj27zprjghntrerntggg507hxh2ydcdkn7sx8kya7p";
+        // transparent, Sapling and Orchard receivers
+        const U_ADDR: &str = "uregtest1zkuzfv5m3yhv2j4fmvq5rjurkxenxyq8r7h4daun2zkznrjaa8ra8asgdm8wwgwjvlwwrxx7347r8w0ee6dqyw4rufw4wg9djwcr6frzkezmdw6dud3wsm99eany5r8wgsctlxquu009nzd6hsme2tcsk0v3sgjvxa70er7h27z5epr67p5q767s2z5gt88paru56mxpm6pwz0cu35m";
+        // Orchard receiver of U_ADDR only
+        const U_ADDR_ORCHARD: &str = "uregtest1h8fnf3vrmswwj0r6nfvq24nxzmyjzaq5jvyxyc2afjtuze8tn93zjqt87kv9wm0ew4rkprpuphf08tc7f5nnd3j3kxnngyxf0cv9k9lc";
+        let maturity = |address, heartwood_active, nu5_active| {
+            super::coinbase_maturity(address, Network::Regtest, heartwood_active, nu5_active)
+        };
+
+        assert_eq!(maturity(T_ADDR, false, false).unwrap(), COINBASE_MATURITY);
+        assert_eq!(maturity(Z_ADDR, true, false).unwrap(), 1);
+        assert!(matches!(
+            maturity(Z_ADDR, false, false),
+            Err(CoinbaseError::UpgradeNotActive { network_upgrade, .. }) if network_upgrade == "Heartwood"
+        ));
+        assert_eq!(maturity(U_ADDR, true, true).unwrap(), 1);
+        assert_eq!(maturity(U_ADDR, true, false).unwrap(), 1);
+        assert_eq!(maturity(U_ADDR, false, false).unwrap(), COINBASE_MATURITY);
+        assert_eq!(maturity(U_ADDR_ORCHARD, true, true).unwrap(), 1);
+        assert!(matches!(
+            maturity(U_ADDR_ORCHARD, true, false),
+            Err(CoinbaseError::UpgradeNotActive { network_upgrade, .. }) if network_upgrade == "NU5"
+        ));
+        assert!(matches!(
+            super::coinbase_maturity(T_ADDR, Network::Mainnet, true, true),
+            Err(CoinbaseError::UnsupportedAddress(_))
+        ));
+        assert!(matches!(
+            maturity("zregtestsapling1abc", true, true),
+            Err(CoinbaseError::UnsupportedAddress(_))
+        ));
+    }
+}
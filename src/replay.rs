@@ -0,0 +1,213 @@
+//! Module for exporting raw blocks from a validator and replaying them into another validator
+//!
+//! Zcashd and zebrad chain caches are incompatible, so identical chains are built on both validators by exporting the
+//! blocks from one validator to a [`BlockFile`] with [`export_blocks`] and submitting them to the other with
+//! [`replay_blocks`]. The target validator must be configured with the same network, activation heights and funding
+//! streams as the source validator, otherwise the replayed blocks are rejected.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use zcash_protocol::consensus::BlockHeight;
+
+use crate::{error::ReplayError, network::Network, validator::Validator};
+
+/// Current block file format version
+pub const BLOCK_FILE_VERSION: u32 = 1;
+
+/// Portable file of consecutive raw blocks
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BlockFile {
+    /// Block file format version
+    pub version: u32,
+    /// Network type
+    pub network: Network,
+    /// Height of the first block
+    pub start_height: u32,
+    /// Hex encoded raw blocks, in height order
+    pub blocks: Vec<String>,
+}
+
+impl BlockFile {
+    /// Reads a block file from `path`.
+    pub fn read(path: &Path) -> Result<Self, ReplayError> {
+        let block_file: Self = toml::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| ReplayError::InvalidBlockFile(e.to_string()))?;
+        if block_file.version > BLOCK_FILE_VERSION {
+            return Err(ReplayError::InvalidBlockFile(format!(
+                "unsupported block file version {}",
+                block_file.version
+            )));
+        }
+        block_file.check_heights()?;
+
+        Ok(block_file)
+    }
+
+    /// Writes the block file to `path`.
+    pub fn write(&self, path: &Path) -> Result<(), ReplayError> {
+        let block_file = toml::to_string(self).expect("block file should serialize to TOML");
+        std::fs::write(path, block_file)?;
+
+        Ok(())
+    }
+
+    /// Returns the height of the last block, or `None` if the block file is empty or the height of the last block
+    /// overflows.
+    pub fn end_height(&self) -> Option<BlockHeight> {
+        let last_block = u32::try_from(self.blocks.len().checked_sub(1)?).ok()?;
+        self.start_height
+            .checked_add(last_block)
+            .map(BlockHeight::from_u32)
+    }
+
+    /// Returns [`ReplayError::InvalidBlockFile`] if the height of the last block overflows.
+    fn check_heights(&self) -> Result<(), ReplayError> {
+        if !self.blocks.is_empty() && self.end_height().is_none() {
+            return Err(ReplayError::InvalidBlockFile(format!(
+                "{} blocks starting at height {} exceed the maximum block height",
+                self.blocks.len(),
+                self.start_height
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Exports the blocks from `start_height` to `end_height` (inclusive) from the `validator`.
+pub async fn export_blocks<V: Validator + Sync>(
+    validator: &V,
+    start_height: BlockHeight,
+    end_height: BlockHeight,
+) -> Result<BlockFile, ReplayError> {
+    let mut blocks = Vec::new();
+    for height in u32::from(start_height)..=u32::from(end_height) {
+        blocks.push(validator.get_raw_block(height.into()).await?);
+    }
+
+    Ok(BlockFile {
+        version: BLOCK_FILE_VERSION,
+        network: validator.network(),
+        start_height: start_height.into(),
+        blocks,
+    })
+}
+
+/// Submits the blocks in the `block_file` to the `validator` in height order.
+/// Returns the chain height once all blocks are accepted.
+///
+/// The validator chain tip must be the block before the first block in the block file.
+pub async fn replay_blocks<V: Validator + Sync>(
+    validator: &V,
+    block_file: &BlockFile,
+) -> Result<BlockHeight, ReplayError> {
+    block_file.check_heights()?;
+    if validator.network() != block_file.network {
+        return Err(ReplayError::NetworkMismatch {
+            expected: validator.network().to_string(),
+            found: block_file.network.to_string(),
+        });
+    }
+    let tip_height = validator.get_chain_height().await;
+    if u32::from(tip_height) + 1 != block_file.start_height {
+        return Err(ReplayError::HeightMismatch {
+            start_height: block_file.start_height,
+            tip_height: tip_height.into(),
+        });
+    }
+
+    let Some(end_height) = block_file.end_height() else {
+        return Ok(tip_height);
+    };
+    for (height, block) in (block_file.start_height..=u32::from(end_height)).zip(&block_file.blocks)
+    {
+        validator
+            .submit_block(block)
+            .await
+            .map_err(|e| ReplayError::Rejected {
+                height,
+                reason: e.to_string(),
+            })?;
+    }
+    validator.poll_chain_height(end_height).await;
+
+    Ok(end_height)
+}
+
+/// Replays the chain of the `source` validator into the `target` validator, from the block after the `target` chain
+/// tip up to the `source` chain tip.
+/// Returns the chain height of the `target` validator.
+pub async fn replay_chain<S, T>(source: &S, target: &T) -> Result<BlockHeight, ReplayError>
+where
+    S: Validator + Sync,
+    T: Validator + Sync,
+{
+    let start_height = target.get_chain_height().await + 1;
+    let end_height = source.get_chain_height().await;
+    if start_height > end_height {
+        return Ok(target.get_chain_height().await);
+    }
+
+    let block_file = export_blocks(source, start_height, end_height).await?;
+    replay_blocks(target, &block_file).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::ReplayError, network::Network};
+
+    use super::{BlockFile, BLOCK_FILE_VERSION};
+
+    #[test]
+    fn block_file_round_trip() {
+        let block_file = BlockFile {
+            version: BLOCK_FILE_VERSION,
+            network: Network::Regtest,
+            start_height: 1,
+            blocks: vec!["0400".to_string(), "0500".to_string()],
+        };
+        assert_eq!(block_file.end_height(), Some(2.into()));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.toml");
+        block_file.write(&path).unwrap();
+
+        assert_eq!(BlockFile::read(&path).unwrap(), block_file);
+    }
+
+    #[test]
+    fn empty_block_file() {
+        let block_file = BlockFile {
+            version: BLOCK_FILE_VERSION,
+            network: Network::Regtest,
+            start_height: 0,
+            blocks: Vec::new(),
+        };
+
+        assert_eq!(block_file.end_height(), None);
+    }
+
+    #[test]
+    fn block_file_height_overflow() {
+        let mut block_file = BlockFile {
+            version: BLOCK_FILE_VERSION,
+            network: Network::Regtest,
+            start_height: u32::MAX,
+            blocks: vec!["0400".to_string()],
+        };
+        assert_eq!(block_file.end_height(), Some(u32::MAX.into()));
+
+        block_file.blocks.push("0500".to_string());
+        assert_eq!(block_file.end_height(), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.toml");
+        block_file.write(&path).unwrap();
+
+        assert!(matches!(
+            BlockFile::read(&path),
+            Err(ReplayError::InvalidBlockFile(_))
+        ));
+    }
+}
@@ -0,0 +1,601 @@
+//! Module for describing chain scenarios and executing them against a local network
+//!
+//! A [`Scenario`] is a list of [`Step`]s (sends between actors, shielding and mined blocks) which is executed against
+//! any [`LocalNet`] with [`run`]. The resulting txids and heights are returned as a [`ScenarioRecord`] so test
+//! fixtures can assert on the generated chain. [`generate_chain_cache`] also caches the chain and writes the record to
+//! the chain cache directory.
+//!
+//! ## Reproducible generation
+//!
+//! Setting [`Scenario::block_times`] mines every block at a fixed time derived from its height. The record includes
+//! the tip block hash, which commits to every block in the chain, as a checksum so regenerated chains can be compared
+//! with [`ScenarioRecord::same_chain`].
+//!
+//! Setting [`Scenario::seed`] builds the faucet's transactions with a [`crate::funding::Funder`] seeded with the
+//! seed instead of the faucet lightclient. This requires the `funding` feature and a validator mining to
+//! [`crate::funding::FUNDING_ADDRESS`]: faucet sends are paid from the funding address and faucet shield steps sweep
+//! the funding address to the faucet's Orchard address. Seeded faucet sends can not have a memo.
+//!
+//! Scenarios with fixed block times run against zebrad are reproducible bit-for-bit if they only consist of
+//! [`Step::Mine`] steps and seeded faucet steps. Any other scenario produces a different chain on every run:
+//! - zingolib builds the recipient's transactions, and the faucet's without a seed, with an OS random number generator
+//!   (note commitment randomness, value commitment trapdoors and proof randomness) and its API does not accept a
+//!   seeded RNG.
+//! - zcashd randomises the equihash nonce of each mined block.
+//!
+//! For these chains the checksum only detects that a regenerated chain differs, and fixtures should assert on the
+//! values in the [`Scenario`] and its [`ScenarioRecord`] rather than hard-coded txids or block hashes.
+//!
+//! Scenarios can be built in code:
+//! ```ignore(incomplete)
+//! let scenario = Scenario::new()
+//!     .mine(2)
+//!     .send(Actor::Faucet, Actor::Recipient, Pool::Orchard, 100_000, Some("orchard test memo"))
+//!     .mine(1)
+//!     .shield(Actor::Recipient)
+//!     .mine(1);
+//! ```
+//! or deserialized, for example from TOML:
+//! ```toml
+//! [[steps]]
+//! action = "mine"
+//! blocks = 2
+//!
+//! [[steps]]
+//! action = "send"
+//! from = "faucet"
+//! to = "recipient"
+//! pool = "orchard"
+//! value = 100000
+//! memo = "orchard test memo"
+//! ```
+
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "funding")]
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use zcash_protocol::{PoolType, ShieldedProtocol};
+use zingolib::testutils::lightclient::{from_inputs, get_base_address};
+
+#[cfg(feature = "funding")]
+use crate::funding::{self, Funder};
+use crate::{
+    chain_cache::{self, OverwritePolicy},
+    client,
+    error::ChainCacheError,
+    indexer::Indexer,
+    validator::{ChainClock, Validator},
+    LocalNet,
+};
+
+/// Scenario record filename
+pub const RECORD_FILENAME: &str = "scenario.toml";
+
+/// Lightclient taking part in a scenario
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Actor {
+    /// Faucet (miner) lightclient
+    Faucet,
+    /// Recipient lightclient
+    Recipient,
+}
+
+/// Value pool of the receiving address
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pool {
+    /// Transparent pool
+    Transparent,
+    /// Sapling pool
+    Sapling,
+    /// Orchard pool
+    Orchard,
+}
+
+impl From<Pool> for PoolType {
+    fn from(pool: Pool) -> Self {
+        match pool {
+            Pool::Transparent => PoolType::Transparent,
+            Pool::Sapling => PoolType::Shielded(ShieldedProtocol::Sapling),
+            Pool::Orchard => PoolType::Shielded(ShieldedProtocol::Orchard),
+        }
+    }
+}
+
+/// Scenario step
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum Step {
+    /// Sends `value` zatoshis from `from` to the `pool` address of `to`
+    Send {
+        /// Sending actor
+        from: Actor,
+        /// Receiving actor
+        to: Actor,
+        /// Value pool of the receiving address
+        pool: Pool,
+        /// Value in zatoshis
+        value: u64,
+        /// Memo. Only valid for shielded pools.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        memo: Option<String>,
+    },
+    /// Shields the transparent funds of `actor`
+    Shield {
+        /// Shielding actor
+        actor: Actor,
+    },
+    /// Mines `blocks` blocks, confirming all pending transactions in the first block
+    Mine {
+        /// Number of blocks
+        blocks: u32,
+    },
+}
+
+/// Fixed block times, in seconds since the unix epoch
+///
+/// The block at height `h` is mined at `start + h * spacing`. Scenarios fail to run if the time of a mined block
+/// overflows a `u32`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockTimes {
+    /// Time of the genesis block
+    pub start: u32,
+    /// Seconds between blocks
+    pub spacing: u32,
+}
+
+impl BlockTimes {
+    /// Returns the time of the block at `height`, or `None` if the time overflows a `u32`.
+    pub fn at(&self, height: u32) -> Option<u32> {
+        height
+            .checked_mul(self.spacing)
+            .and_then(|offset| self.start.checked_add(offset))
+    }
+}
+
+/// Declarative description of a chain
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Scenario {
+    /// Steps executed in order
+    pub steps: Vec<Step>,
+    /// Fixed block times. If `None`, blocks are mined at the current time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_times: Option<BlockTimes>,
+    /// Seed of the RNG used to build the faucet's transactions. If `None`, the faucet lightclient builds them. See
+    /// the [module docs](self).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl Scenario {
+    /// Creates an empty scenario.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a [`Step::Send`] step.
+    pub fn send(
+        mut self,
+        from: Actor,
+        to: Actor,
+        pool: Pool,
+        value: u64,
+        memo: Option<&str>,
+    ) -> Self {
+        self.steps.push(Step::Send {
+            from,
+            to,
+            pool,
+            value,
+            memo: memo.map(String::from),
+        });
+        self
+    }
+
+    /// Adds a [`Step::Shield`] step.
+    pub fn shield(mut self, actor: Actor) -> Self {
+        self.steps.push(Step::Shield { actor });
+        self
+    }
+
+    /// Adds a [`Step::Mine`] step.
+    pub fn mine(mut self, blocks: u32) -> Self {
+        self.steps.push(Step::Mine { blocks });
+        self
+    }
+
+    /// Returns a fingerprint of the scenario, this crate version and the `binary_versions` of the processes used to
+    /// generate the chain. See [`chain_cache::fingerprint`].
+    pub fn fingerprint(&self, binary_versions: &[String]) -> String {
+        let mut inputs = vec![
+            toml::to_string(self).expect("scenario should serialize to TOML"),
+            env!("CARGO_PKG_VERSION").to_string(),
+        ];
+        inputs.extend_from_slice(binary_versions);
+
+        chain_cache::fingerprint(&inputs)
+    }
+
+    /// Mines blocks at fixed times. See [`BlockTimes`].
+    pub fn with_block_times(mut self, start: u32, spacing: u32) -> Self {
+        self.block_times = Some(BlockTimes { start, spacing });
+        self
+    }
+
+    /// Builds the faucet's transactions with an RNG seeded with `seed`. See [`Scenario::seed`].
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// Transactions created by a scenario step
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScenarioTransaction {
+    /// Index of the step in [`Scenario::steps`]
+    pub step: usize,
+    /// Transaction ids
+    pub txids: Vec<String>,
+    /// Height of the block the transactions were mined in, or `None` if the scenario ended before they were mined
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+}
+
+/// Record of the chain created by a scenario
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScenarioRecord {
+    /// Transactions created by [`Step::Send`] and [`Step::Shield`] steps, in step order
+    pub transactions: Vec<ScenarioTransaction>,
+    /// Chain tip height when the scenario finished
+    pub tip_height: u32,
+    /// Chain tip block hash when the scenario finished. Used as a checksum of the whole chain.
+    pub tip_hash: String,
+}
+
+impl ScenarioRecord {
+    /// Returns the transactions created by the step at index `step`.
+    pub fn step(&self, step: usize) -> Option<&ScenarioTransaction> {
+        self.transactions
+            .iter()
+            .find(|transaction| transaction.step == step)
+    }
+
+    /// Returns true if `other` records the same chain, comparing the tip heights and tip block hashes.
+    pub fn same_chain(&self, other: &Self) -> bool {
+        self.tip_height == other.tip_height && self.tip_hash == other.tip_hash
+    }
+
+    /// Reads the record from the `chain_cache` directory.
+    /// Returns `None` if the chain cache was not generated from a scenario.
+    pub fn read(chain_cache: &Path) -> Result<Option<Self>, ChainCacheError> {
+        let record_path = chain_cache.join(RECORD_FILENAME);
+        if !record_path.exists() {
+            return Ok(None);
+        }
+
+        toml::from_str(&std::fs::read_to_string(record_path)?)
+            .map(Some)
+            .map_err(|e| ChainCacheError::InvalidScenarioRecord(e.to_string()))
+    }
+
+    /// Writes the record to the `chain_cache` directory.
+    pub fn write(&self, chain_cache: &Path) -> Result<(), ChainCacheError> {
+        let record = toml::to_string(self).expect("scenario record should serialize to TOML");
+        std::fs::write(chain_cache.join(RECORD_FILENAME), record)?;
+
+        Ok(())
+    }
+}
+
+/// Executes the `scenario` against the `local_net`.
+/// Lightclient wallets are created in `lightclient_dir`.
+///
+/// Panics if any step fails, or if the scenario is seeded without the `funding` feature or a validator mining to
+/// the funding address.
+pub async fn run<I, V>(
+    local_net: &LocalNet<I, V>,
+    scenario: &Scenario,
+    lightclient_dir: PathBuf,
+) -> ScenarioRecord
+where
+    I: Indexer,
+    V: Validator + Sync,
+{
+    let (faucet, recipient) =
+        client::build_lightclients(lightclient_dir, local_net.indexer().port()).await;
+    let lightclient = |actor: Actor| match actor {
+        Actor::Faucet => &faucet,
+        Actor::Recipient => &recipient,
+    };
+    #[cfg(feature = "funding")]
+    let funder = scenario
+        .seed
+        .map(|seed| seeded_funder(local_net.validator(), seed));
+    #[cfg(not(feature = "funding"))]
+    assert!(
+        scenario.seed.is_none(),
+        "seeded scenarios require the `funding` feature"
+    );
+
+    if let Some(block_times) = scenario.block_times {
+        let next_height = u32::from(local_net.validator().get_chain_height().await) + 1;
+        local_net
+            .validator()
+            .set_chain_clock(ChainClock::advancing(
+                block_times.at(next_height).unwrap_or_else(|| {
+                    panic!("block time at height {next_height} should fit in a u32")
+                }),
+                block_times.spacing,
+            ))
+            .await
+            .unwrap();
+    }
+
+    let mut record = ScenarioRecord::default();
+    let mut pending = Vec::new();
+    for (step_index, step) in scenario.steps.iter().enumerate() {
+        match step {
+            Step::Send {
+                from,
+                to,
+                pool,
+                value,
+                memo,
+            } => {
+                let address = get_base_address(lightclient(*to), (*pool).into()).await;
+                #[cfg(feature = "funding")]
+                if let (Actor::Faucet, Some(funder)) = (from, &funder) {
+                    assert!(
+                        memo.is_none(),
+                        "scenario step {step_index} failed to send. seeded faucet sends can not have a memo"
+                    );
+                    let txid = funder
+                        .fund(local_net.validator(), &[(address.as_str(), *value)])
+                        .await
+                        .unwrap_or_else(|e| {
+                            panic!("scenario step {step_index} failed to send. {e:?}")
+                        });
+                    pending.push(record.transactions.len());
+                    record.transactions.push(ScenarioTransaction {
+                        step: step_index,
+                        txids: vec![txid],
+                        height: None,
+                    });
+                    continue;
+                }
+                let sender = lightclient(*from);
+                sender.do_sync(false).await.unwrap();
+                let txids = from_inputs::quick_send(
+                    sender,
+                    vec![(address.as_str(), *value, memo.as_deref())],
+                )
+                .await
+                .unwrap_or_else(|e| panic!("scenario step {step_index} failed to send. {e:?}"));
+                pending.push(record.transactions.len());
+                record.transactions.push(ScenarioTransaction {
+                    step: step_index,
+                    txids: txids.iter().map(|txid| txid.to_string()).collect(),
+                    height: None,
+                });
+            }
+            Step::Shield { actor } => {
+                #[cfg(feature = "funding")]
+                if let (Actor::Faucet, Some(funder)) = (actor, &funder) {
+                    let address =
+                        get_base_address(&faucet, PoolType::Shielded(ShieldedProtocol::Orchard))
+                            .await;
+                    let txid = funder
+                        .sweep(local_net.validator(), &address)
+                        .await
+                        .unwrap_or_else(|e| {
+                            panic!("scenario step {step_index} failed to shield. {e:?}")
+                        });
+                    pending.push(record.transactions.len());
+                    record.transactions.push(ScenarioTransaction {
+                        step: step_index,
+                        txids: vec![txid],
+                        height: None,
+                    });
+                    continue;
+                }
+                let shielder = lightclient(*actor);
+                shielder.do_sync(false).await.unwrap();
+                let txids = shielder.quick_shield().await.unwrap_or_else(|e| {
+                    panic!("scenario step {step_index} failed to shield. {e:?}")
+                });
+                pending.push(record.transactions.len());
+                record.transactions.push(ScenarioTransaction {
+                    step: step_index,
+                    txids: txids.iter().map(|txid| txid.to_string()).collect(),
+                    height: None,
+                });
+            }
+            Step::Mine { blocks } => {
+                let mined_height = u32::from(local_net.validator().get_chain_height().await) + 1;
+                local_net
+                    .validator()
+                    .generate_blocks(*blocks)
+                    .await
+                    .unwrap();
+                if *blocks > 0 {
+                    for index in pending.drain(..) {
+                        record.transactions[index].height = Some(mined_height);
+                    }
+                }
+            }
+        }
+    }
+    if scenario.block_times.is_some() {
+        local_net
+            .validator()
+            .set_chain_clock(ChainClock::System)
+            .await
+            .unwrap();
+    }
+    record.tip_height = local_net.validator().get_chain_height().await.into();
+    record.tip_hash = local_net
+        .validator()
+        .call_rpc("getbestblockhash", Vec::new())
+        .await
+        .unwrap()
+        .as_str()
+        .expect("getbestblockhash should return a hash")
+        .to_string();
+
+    record
+}
+
+/// Creates a funder for the `validator` which builds transactions with an RNG seeded with `seed`.
+///
+/// Panics if the validator does not mine to the funding address.
+#[cfg(feature = "funding")]
+fn seeded_funder<V: Validator>(validator: &V, seed: u64) -> Funder<StdRng> {
+    assert_eq!(
+        validator.coinbase_address(),
+        Some(funding::FUNDING_ADDRESS),
+        "seeded scenarios require a validator mining to the funding address"
+    );
+
+    Funder::with_rng(validator.activation_heights(), StdRng::seed_from_u64(seed))
+}
+
+/// Executes the `scenario` against the `local_net` and caches the resulting chain in `chain_cache`, writing the
+/// [`ScenarioRecord`] alongside the chain cache manifest. This stops the validator process.
+pub async fn generate_chain_cache<I, V>(
+    local_net: &mut LocalNet<I, V>,
+    scenario: &Scenario,
+    chain_cache: PathBuf,
+    overwrite: OverwritePolicy,
+) -> Result<ScenarioRecord, ChainCacheError>
+where
+    I: Indexer,
+    V: Validator + Send + Sync,
+{
+    let lightclient_dir = tempfile::tempdir()?;
+    let record = run(local_net, scenario, lightclient_dir.path().to_path_buf()).await;
+    local_net
+        .validator_mut()
+        .cache_chain(chain_cache.clone(), overwrite)
+        .await?;
+    record.write(&chain_cache)?;
+
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Actor, Pool, Scenario, ScenarioRecord, ScenarioTransaction, Step};
+
+    #[test]
+    fn scenario_from_toml() {
+        let scenario: Scenario = toml::from_str(
+            r#"
+block_times = { start = 1700000000, spacing = 75 }
+seed = 7
+
+[[steps]]
+action = "mine"
+blocks = 2
+
+[[steps]]
+action = "send"
+from = "faucet"
+to = "recipient"
+pool = "orchard"
+value = 100000
+memo = "orchard test memo"
+
+[[steps]]
+action = "shield"
+actor = "recipient"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            scenario,
+            Scenario::new()
+                .mine(2)
+                .send(
+                    Actor::Faucet,
+                    Actor::Recipient,
+                    Pool::Orchard,
+                    100_000,
+                    Some("orchard test memo"),
+                )
+                .shield(Actor::Recipient)
+                .with_block_times(1_700_000_000, 75)
+                .with_seed(7)
+        );
+        assert_eq!(
+            scenario
+                .block_times
+                .and_then(|block_times| block_times.at(2)),
+            Some(1_700_000_150)
+        );
+        assert_eq!(
+            scenario
+                .block_times
+                .and_then(|block_times| block_times.at(u32::MAX)),
+            None
+        );
+        assert_eq!(
+            scenario.steps[2],
+            Step::Shield {
+                actor: Actor::Recipient
+            }
+        );
+    }
+
+    #[test]
+    fn scenario_fingerprint() {
+        let scenario = Scenario::new().mine(2).with_block_times(1_700_000_000, 75);
+        let versions = vec!["zcashd v6.0.0".to_string()];
+
+        assert_eq!(
+            scenario.fingerprint(&versions),
+            scenario.clone().fingerprint(&versions)
+        );
+        assert_ne!(
+            scenario.fingerprint(&versions),
+            scenario.clone().mine(1).fingerprint(&versions)
+        );
+        assert_ne!(
+            scenario.fingerprint(&versions),
+            scenario.clone().with_seed(7).fingerprint(&versions)
+        );
+        assert_ne!(
+            scenario.fingerprint(&versions),
+            scenario.fingerprint(&["zcashd v6.1.0".to_string()])
+        );
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let chain_cache = tempfile::tempdir().unwrap();
+        assert_eq!(ScenarioRecord::read(chain_cache.path()).unwrap(), None);
+
+        let record = ScenarioRecord {
+            transactions: vec![ScenarioTransaction {
+                step: 1,
+                txids: vec!["ab".repeat(32)],
+                height: Some(3),
+            }],
+            tip_height: 3,
+            tip_hash: "00".repeat(32),
+        };
+        record.write(chain_cache.path()).unwrap();
+
+        let read = ScenarioRecord::read(chain_cache.path()).unwrap().unwrap();
+        assert_eq!(read, record);
+        assert_eq!(read.step(1).unwrap().height, Some(3));
+        assert_eq!(read.step(0), None);
+        assert!(read.same_chain(&record));
+        assert!(!read.same_chain(&ScenarioRecord {
+            tip_hash: "11".repeat(32),
+            ..record
+        }));
+    }
+}
@@ -4,7 +4,8 @@
 //! and the test fixtures must be expanded to include the additional process
 //!
 //! If running test fixtures from an external crate, the chain cache should be generated by running
-//! `generate_chain_cache` which will cache the chain in a `CARGO_MANIFEST_DIR/chain_cache/client_rpcs` directory
+//! `generate_chain_cache` which will cache the chain in the [`CLIENT_RPC_TESTS`] directory of the chain cache
+//! directory. See [`crate::utils::chain_cache_dir`] for how the chain cache directory is resolved.
 //!
 //! ```ignore(incomplete)
 //! #[ignore = "not a test. generates chain cache for client_rpc tests."]
@@ -19,7 +20,10 @@
 //! }
 //! ```
 
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use tokio::sync::mpsc::unbounded_channel;
 use zcash_client_backend::proto;
@@ -37,14 +41,34 @@ use zingolib::{
 };
 
 use crate::{
+    chain_cache::{self, OverwritePolicy},
     client, config,
+    error::ChainCacheError,
     indexer::{Indexer as _, Lightwalletd, LightwalletdConfig, Zainod, ZainodConfig},
     network::{self, Network},
+    scenario::{self, Actor, Pool, Scenario, ScenarioRecord},
     utils,
     validator::{Validator as _, Zcashd, ZcashdConfig, Zebrad, ZebradConfig, ZEBRAD_DEFAULT_MINER},
     LocalNet,
 };
 
+/// Name of the zcashd chain cache generated by [`generate_zcashd_chain_cache`]
+pub const CLIENT_RPC_TESTS: &str = "client_rpc_tests";
+/// Name of the zebrad chain cache generated by [`generate_zebrad_large_chain_cache`]
+pub const CLIENT_RPC_TESTS_LARGE: &str = "client_rpc_tests_large";
+/// Name of the synced zebrad testnet chain cache used by [`get_subtree_roots_sapling`]
+pub const TESTNET_GET_SUBTREE_ROOTS_SAPLING: &str = "testnet_get_subtree_roots_sapling";
+/// Name of the synced zebrad testnet chain cache used by [`get_subtree_roots_orchard`]
+pub const TESTNET_GET_SUBTREE_ROOTS_ORCHARD: &str = "testnet_get_subtree_roots_orchard";
+
+/// Genesis block time of the generated chain caches. See [`scenario::BlockTimes`].
+pub const BLOCK_TIMES_START: u32 = 1_700_000_000;
+/// Seconds between blocks of the generated chain caches, matching the zcash target block spacing
+pub const BLOCK_TIMES_SPACING: u32 = 75;
+
+/// Maximum time fixtures wait for a transaction to reach the validator or indexers, or for the indexers to sync
+pub const WAIT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
 /// Generates zebrad chain cache for client RPC test fixtures requiring a large chain
 pub async fn generate_zebrad_large_chain_cache(
     zebrad_bin: Option<PathBuf>,
@@ -55,170 +79,280 @@ pub async fn generate_zebrad_large_chain_cache(
             lightwalletd_bin,
             listen_port: None,
             zcashd_conf: PathBuf::new(),
+            extra_options: serde_yaml::Mapping::new(),
+            extra_args: Vec::new(),
         },
         ZebradConfig {
             zebrad_bin,
             network_listen_port: None,
             rpc_listen_port: None,
             activation_heights: network::ActivationHeights::default(),
+            funding_streams: network::FundingStreams::default(),
             miner_address: ZEBRAD_DEFAULT_MINER,
             chain_cache: None,
             network: Network::Regtest,
+            offline: false,
+            extra_options: toml::Table::new(),
+            extra_args: Vec::new(),
         },
     )
     .await;
 
-    local_net.validator().generate_blocks(150).await.unwrap();
-
-    let chain_cache_dir = utils::chain_cache_dir();
-    if !chain_cache_dir.exists() {
-        std::fs::create_dir_all(chain_cache_dir.clone()).unwrap();
-    }
-    local_net
-        .validator_mut()
-        .cache_chain(chain_cache_dir.join("client_rpc_tests_large"));
+    scenario::generate_chain_cache(
+        &mut local_net,
+        &Scenario::new()
+            .mine(150)
+            .with_block_times(BLOCK_TIMES_START, BLOCK_TIMES_SPACING),
+        utils::chain_cache(CLIENT_RPC_TESTS_LARGE),
+        OverwritePolicy::Error,
+    )
+    .await
+    .unwrap();
 }
 
 /// Generates zcashd chain cache for client RPC test fixtures
+///
+/// Fixtures regenerate the chain cache automatically when it is missing or stale, see
+/// [`client_rpc_tests_chain_cache`].
 pub async fn generate_zcashd_chain_cache(
     zcashd_bin: Option<PathBuf>,
     zcash_cli_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
+    let chain_cache = utils::chain_cache(CLIENT_RPC_TESTS);
+    let fingerprint = client_rpc_tests_fingerprint(
+        zcashd_bin.as_deref(),
+        zcash_cli_bin.as_deref(),
+        lightwalletd_bin.as_deref(),
+    );
+    write_zcashd_chain_cache(
+        zcashd_bin,
+        zcash_cli_bin,
+        lightwalletd_bin,
+        OverwritePolicy::Error,
+    )
+    .await
+    .unwrap();
+    chain_cache::write_fingerprint(&chain_cache, &fingerprint).unwrap();
+}
+
+/// Returns the [`CLIENT_RPC_TESTS`] chain cache, regenerating it if it is missing or was generated from a different
+/// [`client_rpc_tests_scenario`] or zcashd, zcash-cli or lightwalletd version.
+pub async fn client_rpc_tests_chain_cache(
+    zcashd_bin: Option<&Path>,
+    zcash_cli_bin: Option<&Path>,
+    lightwalletd_bin: Option<&Path>,
+) -> PathBuf {
+    let chain_cache = utils::chain_cache(CLIENT_RPC_TESTS);
+    chain_cache::ensure_chain_cache(
+        &chain_cache,
+        &client_rpc_tests_fingerprint(zcashd_bin, zcash_cli_bin, lightwalletd_bin),
+        || {
+            write_zcashd_chain_cache(
+                zcashd_bin.map(Path::to_path_buf),
+                zcash_cli_bin.map(Path::to_path_buf),
+                lightwalletd_bin.map(Path::to_path_buf),
+                OverwritePolicy::Replace,
+            )
+        },
+    )
+    .await
+    .unwrap();
+
+    chain_cache
+}
+
+fn client_rpc_tests_fingerprint(
+    zcashd_bin: Option<&Path>,
+    zcash_cli_bin: Option<&Path>,
+    lightwalletd_bin: Option<&Path>,
+) -> String {
+    client_rpc_tests_scenario().fingerprint(&[
+        utils::binary_version(zcashd_bin, "zcashd").expect("zcashd version should be readable"),
+        utils::binary_version(zcash_cli_bin, "zcash-cli")
+            .expect("zcash-cli version should be readable"),
+        utils::command_version(lightwalletd_bin, "lightwalletd", "version")
+            .expect("lightwalletd version should be readable"),
+    ])
+}
+
+/// Launches zcashd from the [`CLIENT_RPC_TESTS`] chain cache, regenerating the chain cache first if it is missing or
+/// stale. See [`client_rpc_tests_chain_cache`].
+async fn launch_client_rpc_tests_zcashd(
+    zcashd_bin: Option<PathBuf>,
+    zcash_cli_bin: Option<PathBuf>,
+    lightwalletd_bin: Option<&Path>,
+) -> Zcashd {
+    let chain_cache = client_rpc_tests_chain_cache(
+        zcashd_bin.as_deref(),
+        zcash_cli_bin.as_deref(),
+        lightwalletd_bin,
+    )
+    .await;
+
+    Zcashd::launch(ZcashdConfig {
+        zcashd_bin,
+        zcash_cli_bin,
+        rpc_port: None,
+        activation_heights: network::ActivationHeights::default(),
+        funding_streams: network::FundingStreams::default(),
+        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
+        chain_cache: Some(chain_cache),
+        extra_options: Vec::new(),
+        extra_args: Vec::new(),
+    })
+    .await
+    .unwrap()
+}
+
+/// Returns the [`ScenarioRecord`] of the [`CLIENT_RPC_TESTS`] chain cache.
+fn client_rpc_tests_record() -> ScenarioRecord {
+    ScenarioRecord::read(&utils::chain_cache(CLIENT_RPC_TESTS))
+        .unwrap()
+        .expect("client rpc tests chain cache should have a scenario record")
+}
+
+/// Returns the transparent balance of the `actor` at the end of the `scenario`, i.e. the value sent to its
+/// transparent address since it last shielded. Lightclients send from shielded notes so sends do not spend
+/// transparent funds.
+fn transparent_balance(scenario: &Scenario, actor: Actor) -> u64 {
+    scenario.steps.iter().fold(0, |balance, step| match step {
+        scenario::Step::Send {
+            to,
+            pool: Pool::Transparent,
+            value,
+            ..
+        } if *to == actor => balance + value,
+        scenario::Step::Shield { actor: shielder } if *shielder == actor => 0,
+        _ => balance,
+    })
+}
+
+/// Asserts each of the `raw_txs` was created by the scenario and mined at the height in the `record`.
+fn assert_recorded_heights(
+    record: &ScenarioRecord,
+    raw_txs: &[proto::service::RawTransaction],
+    chain_type: &ChainType,
+) {
+    for raw_tx in raw_txs {
+        let height = u32::try_from(raw_tx.height).expect("height should fit in a u32");
+        let txid = Transaction::read(
+            &raw_tx.data[..],
+            BranchId::for_height(chain_type, BlockHeight::from_u32(height)),
+        )
+        .unwrap()
+        .txid()
+        .to_string();
+        let recorded = record
+            .transactions
+            .iter()
+            .find(|transaction| transaction.txids.contains(&txid))
+            .unwrap_or_else(|| panic!("transaction {txid} is not in the scenario record"));
+        assert_eq!(recorded.height, Some(height));
+    }
+}
+
+async fn write_zcashd_chain_cache(
+    zcashd_bin: Option<PathBuf>,
+    zcash_cli_bin: Option<PathBuf>,
+    lightwalletd_bin: Option<PathBuf>,
+    overwrite: OverwritePolicy,
+) -> Result<ScenarioRecord, ChainCacheError> {
     let mut local_net = LocalNet::<Lightwalletd, Zcashd>::launch(
         LightwalletdConfig {
             lightwalletd_bin,
             listen_port: None,
             zcashd_conf: PathBuf::new(),
+            extra_options: serde_yaml::Mapping::new(),
+            extra_args: Vec::new(),
         },
         ZcashdConfig {
             zcashd_bin,
             zcash_cli_bin,
             rpc_port: None,
             activation_heights: network::ActivationHeights::default(),
+            funding_streams: network::FundingStreams::default(),
             miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
             chain_cache: None,
+            extra_options: Vec::new(),
+            extra_args: Vec::new(),
         },
     )
     .await;
 
-    local_net.validator().generate_blocks(2).await.unwrap();
-
-    let lightclient_dir = tempfile::tempdir().unwrap();
-    let (faucet, recipient) = client::build_lightclients(
-        lightclient_dir.path().to_path_buf(),
-        local_net.indexer().port(),
+    scenario::generate_chain_cache(
+        &mut local_net,
+        &client_rpc_tests_scenario(),
+        utils::chain_cache(CLIENT_RPC_TESTS),
+        overwrite,
     )
-    .await;
-
-    // TODO: use second recipient taddr
-    // recipient.do_new_address("ozt").await.unwrap();
-    // let recipient_addresses = recipient.do_addresses().await;
-    // recipient taddr child index 0:
-    // tmFLszfkjgim4zoUMAXpuohnFBAKy99rr2i
-    //
-    // recipient taddr child index 1:
-    // tmAtLC3JkTDrXyn5okUbb6qcMGE4Xq4UdhD
-    //
-    // faucet taddr child index 0:
-    // tmBsTi2xWTjUdEXnuTceL7fecEQKeWaPDJd
+    .await
+}
 
-    faucet.do_sync(false).await.unwrap();
-    from_inputs::quick_send(
-        &faucet,
-        vec![(
-            &get_base_address(&recipient, PoolType::Shielded(ShieldedProtocol::Orchard)).await,
+/// Scenario executed by [`generate_zcashd_chain_cache`]
+///
+/// Transparent sends are paid to the first transparent address of each lightclient,
+/// `tmFLszfkjgim4zoUMAXpuohnFBAKy99rr2i` for the recipient and `tmBsTi2xWTjUdEXnuTceL7fecEQKeWaPDJd` for the faucet.
+pub fn client_rpc_tests_scenario() -> Scenario {
+    Scenario::new()
+        .mine(2)
+        .send(
+            Actor::Faucet,
+            Actor::Recipient,
+            Pool::Orchard,
             100_000,
             Some("orchard test memo"),
-        )],
-    )
-    .await
-    .unwrap();
-    from_inputs::quick_send(
-        &faucet,
-        vec![(
-            &get_base_address(&recipient, PoolType::Shielded(ShieldedProtocol::Sapling)).await,
+        )
+        .send(
+            Actor::Faucet,
+            Actor::Recipient,
+            Pool::Sapling,
             100_000,
             Some("sapling test memo"),
-        )],
-    )
-    .await
-    .unwrap();
-    from_inputs::quick_send(
-        &faucet,
-        vec![(
-            &get_base_address(&recipient, PoolType::Transparent).await,
+        )
+        .send(
+            Actor::Faucet,
+            Actor::Recipient,
+            Pool::Transparent,
             100_000,
             None,
-        )],
-    )
-    .await
-    .unwrap();
-    local_net.validator().generate_blocks(1).await.unwrap();
-
-    recipient.do_sync(false).await.unwrap();
-    recipient.quick_shield().await.unwrap();
-    local_net.validator().generate_blocks(1).await.unwrap();
-
-    faucet.do_sync(false).await.unwrap();
-    from_inputs::quick_send(
-        &faucet,
-        vec![(
-            &get_base_address(&recipient, PoolType::Transparent).await,
+        )
+        .mine(1)
+        .shield(Actor::Recipient)
+        .mine(1)
+        .send(
+            Actor::Faucet,
+            Actor::Recipient,
+            Pool::Transparent,
             200_000,
             None,
-        )],
-    )
-    .await
-    .unwrap();
-    local_net.validator().generate_blocks(1).await.unwrap();
-
-    recipient.do_sync(false).await.unwrap();
-    from_inputs::quick_send(
-        &recipient,
-        vec![(
-            &get_base_address(&faucet, PoolType::Transparent).await,
+        )
+        .mine(1)
+        .send(
+            Actor::Recipient,
+            Actor::Faucet,
+            Pool::Transparent,
             10_000,
             None,
-        )],
-    )
-    .await
-    .unwrap();
-    local_net.validator().generate_blocks(1).await.unwrap();
-
-    recipient.do_sync(false).await.unwrap();
-    from_inputs::quick_send(
-        &recipient,
-        vec![(
-            &get_base_address(&recipient, PoolType::Shielded(ShieldedProtocol::Orchard)).await,
+        )
+        .mine(1)
+        .send(
+            Actor::Recipient,
+            Actor::Recipient,
+            Pool::Orchard,
             10_000,
             Some("orchard test memo"),
-        )],
-    )
-    .await
-    .unwrap();
-    local_net.validator().generate_blocks(2).await.unwrap();
-
-    faucet.do_sync(false).await.unwrap();
-    from_inputs::quick_send(
-        &faucet,
-        vec![(
-            &get_base_address(&recipient, PoolType::Shielded(ShieldedProtocol::Sapling)).await,
+        )
+        .mine(2)
+        .send(
+            Actor::Faucet,
+            Actor::Recipient,
+            Pool::Sapling,
             100_000,
             None,
-        )],
-    )
-    .await
-    .unwrap();
-    local_net.validator().generate_blocks(1).await.unwrap();
-
-    let chain_cache_dir = utils::chain_cache_dir();
-    if !chain_cache_dir.exists() {
-        std::fs::create_dir_all(chain_cache_dir.clone()).unwrap();
-    }
-    local_net
-        .validator_mut()
-        .cache_chain(chain_cache_dir.join("client_rpc_tests"));
+        )
+        .mine(1)
+        .with_block_times(BLOCK_TIMES_START, BLOCK_TIMES_SPACING)
 }
 
 /// GetLightdInfo RPC test
@@ -228,32 +362,27 @@ pub async fn get_lightd_info(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(proto::service::Empty {});
     let zainod_response = zainod_client
         .get_lightd_info(request)
@@ -261,9 +390,7 @@ pub async fn get_lightd_info(
         .unwrap()
         .into_inner();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(proto::service::Empty {});
     let lwd_response = lwd_client
         .get_lightd_info(request)
@@ -333,32 +460,27 @@ pub async fn get_latest_block(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(proto::service::ChainSpec {});
     let zainod_response = zainod_client
         .get_latest_block(request)
@@ -366,9 +488,7 @@ pub async fn get_latest_block(
         .unwrap()
         .into_inner();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(proto::service::ChainSpec {});
     let lwd_response = lwd_client
         .get_latest_block(request)
@@ -396,26 +516,23 @@ pub async fn get_block(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -424,15 +541,11 @@ pub async fn get_block(
         hash: vec![],
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(block_id.clone());
     let zainod_response = zainod_client.get_block(request).await.unwrap().into_inner();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(block_id.clone());
     let lwd_response = lwd_client.get_block(request).await.unwrap().into_inner();
 
@@ -456,26 +569,23 @@ pub async fn get_block_out_of_bounds(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -484,15 +594,11 @@ pub async fn get_block_out_of_bounds(
         hash: vec![],
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(block_id.clone());
     let zainod_err_status = zainod_client.get_block(request).await.unwrap_err();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(block_id.clone());
     let lwd_err_status = lwd_client.get_block(request).await.unwrap_err();
 
@@ -517,26 +623,23 @@ pub async fn get_block_nullifiers(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -545,9 +648,7 @@ pub async fn get_block_nullifiers(
         hash: vec![],
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(block_id.clone());
     let zainod_response = zainod_client
         .get_block_nullifiers(request)
@@ -555,9 +656,7 @@ pub async fn get_block_nullifiers(
         .unwrap()
         .into_inner();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(block_id.clone());
     let lwd_response = lwd_client
         .get_block_nullifiers(request)
@@ -585,26 +684,23 @@ pub async fn get_block_range_nullifiers(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -619,9 +715,7 @@ pub async fn get_block_range_nullifiers(
         }),
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(block_range.clone());
     let mut zainod_response = zainod_client
         .get_block_range_nullifiers(request)
@@ -633,9 +727,7 @@ pub async fn get_block_range_nullifiers(
         zainod_blocks.push(compact_block);
     }
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(block_range.clone());
     let mut lwd_response = lwd_client
         .get_block_range_nullifiers(request)
@@ -667,26 +759,23 @@ pub async fn get_block_range_nullifiers_reverse(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -701,9 +790,7 @@ pub async fn get_block_range_nullifiers_reverse(
         }),
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(block_range.clone());
     let mut zainod_response = zainod_client
         .get_block_range_nullifiers(request)
@@ -715,9 +802,7 @@ pub async fn get_block_range_nullifiers_reverse(
         zainod_blocks.push(compact_block);
     }
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(block_range.clone());
     let mut lwd_response = lwd_client
         .get_block_range_nullifiers(request)
@@ -749,26 +834,23 @@ pub async fn get_block_range_lower(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -783,9 +865,7 @@ pub async fn get_block_range_lower(
         }),
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(block_range.clone());
     let mut zainod_response = zainod_client
         .get_block_range(request)
@@ -797,9 +877,7 @@ pub async fn get_block_range_lower(
         zainod_blocks.push(compact_block);
     }
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(block_range.clone());
     let mut lwd_response = lwd_client
         .get_block_range(request)
@@ -831,26 +909,23 @@ pub async fn get_block_range_upper(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -865,9 +940,7 @@ pub async fn get_block_range_upper(
         }),
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(block_range.clone());
     let mut zainod_response = zainod_client
         .get_block_range(request)
@@ -879,9 +952,7 @@ pub async fn get_block_range_upper(
         zainod_blocks.push(compact_block);
     }
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(block_range.clone());
     let mut lwd_response = lwd_client
         .get_block_range(request)
@@ -913,26 +984,23 @@ pub async fn get_block_range_reverse(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -947,9 +1015,7 @@ pub async fn get_block_range_reverse(
         }),
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(block_range.clone());
     let mut zainod_response = zainod_client
         .get_block_range(request)
@@ -961,9 +1027,7 @@ pub async fn get_block_range_reverse(
         zainod_blocks.push(compact_block);
     }
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(block_range.clone());
     let mut lwd_response = lwd_client
         .get_block_range(request)
@@ -995,26 +1059,23 @@ pub async fn get_block_range_out_of_bounds(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -1029,9 +1090,7 @@ pub async fn get_block_range_out_of_bounds(
         }),
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(block_range.clone());
     let mut zainod_response = zainod_client
         .get_block_range(request)
@@ -1050,9 +1109,7 @@ pub async fn get_block_range_out_of_bounds(
         zainod_blocks.push(compact_block);
     }
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(block_range.clone());
     let mut lwd_response = lwd_client
         .get_block_range(request)
@@ -1098,26 +1155,23 @@ pub async fn get_transaction(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -1137,6 +1191,12 @@ pub async fn get_transaction(
     .await
     .unwrap();
     zcashd.generate_blocks(1).await.unwrap();
+    let height = zcashd.get_chain_height().await;
+    zainod.wait_for_height(height, WAIT_TIMEOUT).await.unwrap();
+    lightwalletd
+        .wait_for_height(height, WAIT_TIMEOUT)
+        .await
+        .unwrap();
 
     let tx_filter = proto::service::TxFilter {
         block: None,
@@ -1144,9 +1204,7 @@ pub async fn get_transaction(
         hash: txids.first().as_ref().to_vec(),
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(tx_filter.clone());
     let zainod_response = zainod_client
         .get_transaction(request)
@@ -1154,9 +1212,7 @@ pub async fn get_transaction(
         .unwrap()
         .into_inner();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(tx_filter.clone());
     let lwd_response = lwd_client
         .get_transaction(request)
@@ -1184,21 +1240,32 @@ pub async fn send_transaction(
     zcashd_bin: Option<PathBuf>,
     zcash_cli_bin: Option<PathBuf>,
     zainod_bin: Option<PathBuf>,
-    _lightwalletd_bin: Option<PathBuf>,
+    lightwalletd_bin: Option<PathBuf>,
 ) {
+    let chain_cache = client_rpc_tests_chain_cache(
+        zcashd_bin.as_deref(),
+        zcash_cli_bin.as_deref(),
+        lightwalletd_bin.as_deref(),
+    )
+    .await;
     let local_net = LocalNet::<Zainod, Zcashd>::launch(
         ZainodConfig {
             zainod_bin: zainod_bin.clone(),
             listen_port: None,
             validator_port: 0,
+            extra_options: toml::Table::new(),
+            extra_args: Vec::new(),
         },
         ZcashdConfig {
             zcashd_bin: zcashd_bin.clone(),
             zcash_cli_bin: zcash_cli_bin.clone(),
             rpc_port: None,
             activation_heights: network::ActivationHeights::default(),
+            funding_streams: network::FundingStreams::default(),
             miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-            chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
+            chain_cache: Some(chain_cache.clone()),
+            extra_options: Vec::new(),
+            extra_args: Vec::new(),
         },
     )
     .await;
@@ -1221,6 +1288,7 @@ pub async fn send_transaction(
     .await
     .unwrap();
     local_net.validator().generate_blocks(1).await.unwrap();
+    local_net.sync_all(WAIT_TIMEOUT).await.unwrap();
 
     let tx_filter = proto::service::TxFilter {
         block: None,
@@ -1228,10 +1296,7 @@ pub async fn send_transaction(
         hash: txids.first().as_ref().to_vec(),
     };
 
-    let mut zainod_client =
-        client::build_client(network::localhost_uri(local_net.indexer().port()))
-            .await
-            .unwrap();
+    let mut zainod_client = local_net.indexer().client().await.unwrap();
     let request = tonic::Request::new(tx_filter.clone());
     let zainod_response = zainod_client
         .get_transaction(request)
@@ -1246,14 +1311,19 @@ pub async fn send_transaction(
             zainod_bin,
             listen_port: None,
             validator_port: 0,
+            extra_options: toml::Table::new(),
+            extra_args: Vec::new(),
         },
         ZcashdConfig {
             zcashd_bin,
             zcash_cli_bin,
             rpc_port: None,
             activation_heights: network::ActivationHeights::default(),
+            funding_streams: network::FundingStreams::default(),
             miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-            chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
+            chain_cache: Some(chain_cache),
+            extra_options: Vec::new(),
+            extra_args: Vec::new(),
         },
     )
     .await;
@@ -1276,6 +1346,7 @@ pub async fn send_transaction(
     .await
     .unwrap();
     local_net.validator().generate_blocks(1).await.unwrap();
+    local_net.sync_all(WAIT_TIMEOUT).await.unwrap();
 
     let tx_filter = proto::service::TxFilter {
         block: None,
@@ -1283,10 +1354,7 @@ pub async fn send_transaction(
         hash: txids.first().as_ref().to_vec(),
     };
 
-    let mut zainod_client =
-        client::build_client(network::localhost_uri(local_net.indexer().port()))
-            .await
-            .unwrap();
+    let mut zainod_client = local_net.indexer().client().await.unwrap();
     let request = tonic::Request::new(tx_filter.clone());
     let lwd_response = zainod_client
         .get_transaction(request)
@@ -1332,26 +1400,24 @@ pub async fn get_taddress_txids_all(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
+    let record = client_rpc_tests_record();
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -1373,9 +1439,7 @@ pub async fn get_taddress_txids_all(
         range: Some(block_range),
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(taddr_block_filter.clone());
     let mut zainod_response = zainod_client
         .get_taddress_txids(request)
@@ -1398,9 +1462,7 @@ pub async fn get_taddress_txids_all(
         .collect::<Vec<_>>();
     zainod_txs.sort_by(|a, b| a.txid().cmp(&b.txid()));
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(taddr_block_filter.clone());
     let mut lwd_response = lwd_client
         .get_taddress_txids(request)
@@ -1434,6 +1496,7 @@ pub async fn get_taddress_txids_all(
     println!("");
 
     assert_eq!(lwd_txs.len(), 3);
+    assert_recorded_heights(&record, &lwd_raw_txs, &chain_type);
     assert_eq!(zainod_raw_txs, lwd_raw_txs);
 }
 
@@ -1444,26 +1507,24 @@ pub async fn get_taddress_txids_lower(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
+    let record = client_rpc_tests_record();
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -1485,9 +1546,7 @@ pub async fn get_taddress_txids_lower(
         range: Some(block_range),
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(taddr_block_filter.clone());
     let mut zainod_response = zainod_client
         .get_taddress_txids(request)
@@ -1510,9 +1569,7 @@ pub async fn get_taddress_txids_lower(
         .collect::<Vec<_>>();
     zainod_txs.sort_by(|a, b| a.txid().cmp(&b.txid()));
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(taddr_block_filter.clone());
     let mut lwd_response = lwd_client
         .get_taddress_txids(request)
@@ -1546,6 +1603,7 @@ pub async fn get_taddress_txids_lower(
     println!("");
 
     assert_eq!(lwd_txs.len(), 1);
+    assert_recorded_heights(&record, &lwd_raw_txs, &chain_type);
     assert_eq!(zainod_raw_txs, lwd_raw_txs);
 }
 
@@ -1556,26 +1614,24 @@ pub async fn get_taddress_txids_upper(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
+    let record = client_rpc_tests_record();
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -1597,9 +1653,7 @@ pub async fn get_taddress_txids_upper(
         range: Some(block_range),
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(taddr_block_filter.clone());
     let mut zainod_response = zainod_client
         .get_taddress_txids(request)
@@ -1622,9 +1676,7 @@ pub async fn get_taddress_txids_upper(
         .collect::<Vec<_>>();
     zainod_txs.sort_by(|a, b| a.txid().cmp(&b.txid()));
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(taddr_block_filter.clone());
     let mut lwd_response = lwd_client
         .get_taddress_txids(request)
@@ -1658,6 +1710,7 @@ pub async fn get_taddress_txids_upper(
     println!("");
 
     assert_eq!(lwd_txs.len(), 2);
+    assert_recorded_heights(&record, &lwd_raw_txs, &chain_type);
     assert_eq!(zainod_raw_txs, lwd_raw_txs);
 }
 
@@ -1668,26 +1721,23 @@ pub async fn get_taddress_balance(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -1698,9 +1748,7 @@ pub async fn get_taddress_balance(
         ],
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(address_list.clone());
     let zainod_response = zainod_client
         .get_taddress_balance(request)
@@ -1708,9 +1756,7 @@ pub async fn get_taddress_balance(
         .unwrap()
         .into_inner();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(address_list.clone());
     let lwd_response = lwd_client
         .get_taddress_balance(request)
@@ -1728,7 +1774,10 @@ pub async fn get_taddress_balance(
 
     println!("");
 
-    assert_eq!(zainod_response.value_zat, 210_000i64);
+    let scenario = client_rpc_tests_scenario();
+    let expected_balance = transparent_balance(&scenario, Actor::Recipient)
+        + transparent_balance(&scenario, Actor::Faucet);
+    assert_eq!(zainod_response.value_zat, expected_balance as i64);
     assert_eq!(zainod_response, lwd_response);
 }
 
@@ -1739,26 +1788,23 @@ pub async fn get_taddress_balance_stream(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -1771,9 +1817,7 @@ pub async fn get_taddress_balance_stream(
         },
     ];
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(tokio_stream::iter(address_list.clone()));
     let zainod_response = zainod_client
         .get_taddress_balance_stream(request)
@@ -1781,9 +1825,7 @@ pub async fn get_taddress_balance_stream(
         .unwrap()
         .into_inner();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(tokio_stream::iter(address_list.clone()));
     let lwd_response = lwd_client
         .get_taddress_balance_stream(request)
@@ -1801,7 +1843,10 @@ pub async fn get_taddress_balance_stream(
 
     println!("");
 
-    assert_eq!(zainod_response.value_zat, 210_000i64);
+    let scenario = client_rpc_tests_scenario();
+    let expected_balance = transparent_balance(&scenario, Actor::Recipient)
+        + transparent_balance(&scenario, Actor::Faucet);
+    assert_eq!(zainod_response.value_zat, expected_balance as i64);
     assert_eq!(zainod_response, lwd_response);
 }
 
@@ -1812,26 +1857,23 @@ pub async fn get_mempool_tx(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -1882,7 +1924,14 @@ pub async fn get_mempool_tx(
     )
     .await
     .unwrap();
-    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+    for txids in [&txids_1, &txids_2, &txids_3, &txids_4] {
+        let txid = txids.first().to_string();
+        zainod.wait_for_mempool(&txid, WAIT_TIMEOUT).await.unwrap();
+        lightwalletd
+            .wait_for_mempool(&txid, WAIT_TIMEOUT)
+            .await
+            .unwrap();
+    }
 
     let full_txid_2 = txids_2.first().as_ref().to_vec();
     // the excluded list only accepts truncated txids when they are truncated at the start, not end.
@@ -1893,9 +1942,7 @@ pub async fn get_mempool_tx(
         txid: vec![full_txid_2, truncated_txid_4],
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(exclude_list.clone());
     let mut zainod_response = zainod_client
         .get_mempool_tx(request)
@@ -1908,9 +1955,7 @@ pub async fn get_mempool_tx(
     }
     zainod_txs.sort_by(|a, b| a.hash.cmp(&b.hash));
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(exclude_list.clone());
     let mut lwd_response = lwd_client
         .get_mempool_tx(request)
@@ -1954,26 +1999,23 @@ pub async fn get_mempool_stream_zingolib_mempool_monitor(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -1982,7 +2024,7 @@ pub async fn get_mempool_stream_zingolib_mempool_monitor(
         client::build_lightclients(lightclient_dir.path().to_path_buf(), lightwalletd.port()).await;
 
     faucet.do_sync(false).await.unwrap();
-    let _txids_1 = from_inputs::quick_send(
+    let txids_1 = from_inputs::quick_send(
         &faucet,
         vec![(
             &get_base_address(&recipient, PoolType::Shielded(ShieldedProtocol::Orchard)).await,
@@ -1992,7 +2034,7 @@ pub async fn get_mempool_stream_zingolib_mempool_monitor(
     )
     .await
     .unwrap();
-    let _txids_2 = from_inputs::quick_send(
+    let txids_2 = from_inputs::quick_send(
         &faucet,
         vec![(
             &get_base_address(&recipient, PoolType::Shielded(ShieldedProtocol::Sapling)).await,
@@ -2004,7 +2046,7 @@ pub async fn get_mempool_stream_zingolib_mempool_monitor(
     .unwrap();
 
     recipient.do_sync(false).await.unwrap();
-    let _txids_3 = from_inputs::quick_send(
+    let txids_3 = from_inputs::quick_send(
         &recipient,
         vec![(
             &get_base_address(&faucet, PoolType::Shielded(ShieldedProtocol::Orchard)).await,
@@ -2014,7 +2056,7 @@ pub async fn get_mempool_stream_zingolib_mempool_monitor(
     )
     .await
     .unwrap();
-    let _txids_4 = from_inputs::quick_send(
+    let txids_4 = from_inputs::quick_send(
         &recipient,
         vec![(
             &get_base_address(&faucet, PoolType::Shielded(ShieldedProtocol::Sapling)).await,
@@ -2024,7 +2066,14 @@ pub async fn get_mempool_stream_zingolib_mempool_monitor(
     )
     .await
     .unwrap();
-    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+    for txids in [&txids_1, &txids_2, &txids_3, &txids_4] {
+        let txid = txids.first().to_string();
+        zainod.wait_for_mempool(&txid, WAIT_TIMEOUT).await.unwrap();
+        lightwalletd
+            .wait_for_mempool(&txid, WAIT_TIMEOUT)
+            .await
+            .unwrap();
+    }
 
     drop(recipient);
     drop(faucet);
@@ -2126,26 +2175,23 @@ pub async fn get_mempool_stream(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -2457,26 +2503,23 @@ pub async fn get_tree_state_by_height(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -2485,9 +2528,7 @@ pub async fn get_tree_state_by_height(
         hash: vec![],
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(block_id.clone());
     let zainod_response = zainod_client
         .get_tree_state(request)
@@ -2495,9 +2536,7 @@ pub async fn get_tree_state_by_height(
         .unwrap()
         .into_inner();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(block_id.clone());
     let lwd_response = lwd_client
         .get_tree_state(request)
@@ -2525,26 +2564,23 @@ pub async fn get_tree_state_by_hash(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -2553,9 +2589,7 @@ pub async fn get_tree_state_by_hash(
         hash: vec![],
     };
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(block_id.clone());
     let block = lwd_client.get_block(request).await.unwrap().into_inner();
     let mut block_hash = block.hash().clone().0.to_vec();
@@ -2566,9 +2600,7 @@ pub async fn get_tree_state_by_hash(
         hash: block_hash,
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(block_id.clone());
     let zainod_response = zainod_client
         .get_tree_state(request)
@@ -2603,26 +2635,23 @@ pub async fn get_tree_state_out_of_bounds(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -2631,15 +2660,11 @@ pub async fn get_tree_state_out_of_bounds(
         hash: vec![],
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(block_id.clone());
     let zainod_err_status = zainod_client.get_tree_state(request).await.unwrap_err();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(block_id.clone());
     let lwd_err_status = lwd_client.get_tree_state(request).await.unwrap_err();
 
@@ -2664,32 +2689,27 @@ pub async fn get_latest_tree_state(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(proto::service::Empty {});
     let zainod_response = zainod_client
         .get_latest_tree_state(request)
@@ -2697,9 +2717,7 @@ pub async fn get_latest_tree_state(
         .unwrap()
         .into_inner();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(proto::service::Empty {});
     let lwd_response = lwd_client
         .get_latest_tree_state(request)
@@ -2723,10 +2741,12 @@ pub async fn get_latest_tree_state(
 /// GetSubtreeRoots RPC test
 ///
 /// This test requires Zebrad testnet to be already synced to at least 2 sapling shards with the cache at
-/// `CARGO_MANIFEST_DIR/chain_cache/testnet_get_subtree_roots`
+/// [`TESTNET_GET_SUBTREE_ROOTS_SAPLING`] in the chain cache directory
+///
+/// Zebrad is launched in offline mode so the cached chain is served without network access.
 ///
 /// Example directory tree:
-/// zcash-local-net/chain_cache/testnet_get_subtree_roots/
+/// chain_cache/testnet_get_subtree_roots_sapling/
 /// └── state
 ///     └── v26
 ///         └── testnet
@@ -2743,9 +2763,13 @@ pub async fn get_subtree_roots_sapling(
         network_listen_port: None,
         rpc_listen_port: None,
         activation_heights: network::ActivationHeights::default(),
+        funding_streams: network::FundingStreams::default(),
         miner_address: ZEBRAD_DEFAULT_MINER,
-        chain_cache: Some(utils::chain_cache_dir().join("testnet_get_subtree_roots_sapling")),
+        chain_cache: Some(utils::chain_cache(TESTNET_GET_SUBTREE_ROOTS_SAPLING)),
         network: Network::Testnet,
+        offline: true,
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .await
     .unwrap();
@@ -2753,12 +2777,16 @@ pub async fn get_subtree_roots_sapling(
         zainod_bin,
         listen_port: None,
         validator_port: zebrad.rpc_listen_port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zebrad.config_dir().path().join(config::ZCASHD_FILENAME),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -2768,9 +2796,7 @@ pub async fn get_subtree_roots_sapling(
         max_entries: 0,
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(subtree_roots_arg.clone());
     let mut zainod_response = zainod_client
         .get_subtree_roots(request)
@@ -2782,9 +2808,7 @@ pub async fn get_subtree_roots_sapling(
         zainod_subtree_roots.push(subtree_root);
     }
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(subtree_roots_arg.clone());
     let mut lwd_response = lwd_client
         .get_subtree_roots(request)
@@ -2815,10 +2839,12 @@ pub async fn get_subtree_roots_sapling(
 /// GetSubtreeRoots RPC test
 ///
 /// This test requires Zebrad testnet to be already synced to at least 2 orchard shards with the cache at
-/// `CARGO_MANIFEST_DIR/chain_cache/testnet_get_subtree_roots`
+/// [`TESTNET_GET_SUBTREE_ROOTS_ORCHARD`] in the chain cache directory
+///
+/// Zebrad is launched in offline mode so the cached chain is served without network access.
 ///
 /// Example directory tree:
-/// zcash-local-net/chain_cache/testnet_get_subtree_roots/
+/// chain_cache/testnet_get_subtree_roots_orchard/
 /// └── state
 ///     └── v26
 ///         └── testnet
@@ -2835,9 +2861,13 @@ pub async fn get_subtree_roots_orchard(
         network_listen_port: None,
         rpc_listen_port: None,
         activation_heights: network::ActivationHeights::default(),
+        funding_streams: network::FundingStreams::default(),
         miner_address: ZEBRAD_DEFAULT_MINER,
-        chain_cache: Some(utils::chain_cache_dir().join("testnet_get_subtree_roots_orchard")),
+        chain_cache: Some(utils::chain_cache(TESTNET_GET_SUBTREE_ROOTS_ORCHARD)),
         network: Network::Testnet,
+        offline: true,
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .await
     .unwrap();
@@ -2845,12 +2875,16 @@ pub async fn get_subtree_roots_orchard(
         zainod_bin,
         listen_port: None,
         validator_port: zebrad.rpc_listen_port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zebrad.config_dir().path().join(config::ZCASHD_FILENAME),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -2860,9 +2894,7 @@ pub async fn get_subtree_roots_orchard(
         max_entries: 0,
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(subtree_roots_arg.clone());
     let mut zainod_response = zainod_client
         .get_subtree_roots(request)
@@ -2874,9 +2906,7 @@ pub async fn get_subtree_roots_orchard(
         zainod_subtree_roots.push(subtree_root);
     }
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(subtree_roots_arg.clone());
     let mut lwd_response = lwd_client
         .get_subtree_roots(request)
@@ -2911,26 +2941,23 @@ pub async fn get_address_utxos_all(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -2943,9 +2970,7 @@ pub async fn get_address_utxos_all(
         max_entries: 0,
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let zainod_response = zainod_client
         .get_address_utxos(request)
@@ -2953,9 +2978,7 @@ pub async fn get_address_utxos_all(
         .unwrap()
         .into_inner();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let lwd_response = lwd_client
         .get_address_utxos(request)
@@ -2984,26 +3007,23 @@ pub async fn get_address_utxos_lower(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -3016,9 +3036,7 @@ pub async fn get_address_utxos_lower(
         max_entries: 1,
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let zainod_response = zainod_client
         .get_address_utxos(request)
@@ -3026,9 +3044,7 @@ pub async fn get_address_utxos_lower(
         .unwrap()
         .into_inner();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let lwd_response = lwd_client
         .get_address_utxos(request)
@@ -3058,26 +3074,23 @@ pub async fn get_address_utxos_upper(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -3090,9 +3103,7 @@ pub async fn get_address_utxos_upper(
         max_entries: 1,
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let zainod_response = zainod_client
         .get_address_utxos(request)
@@ -3100,9 +3111,7 @@ pub async fn get_address_utxos_upper(
         .unwrap()
         .into_inner();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let lwd_response = lwd_client
         .get_address_utxos(request)
@@ -3132,26 +3141,23 @@ pub async fn get_address_utxos_out_of_bounds(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -3164,9 +3170,7 @@ pub async fn get_address_utxos_out_of_bounds(
         max_entries: 0,
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let zainod_response = zainod_client
         .get_address_utxos(request)
@@ -3174,9 +3178,7 @@ pub async fn get_address_utxos_out_of_bounds(
         .unwrap()
         .into_inner();
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let lwd_response = lwd_client
         .get_address_utxos(request)
@@ -3205,26 +3207,23 @@ pub async fn get_address_utxos_stream_all(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -3237,9 +3236,7 @@ pub async fn get_address_utxos_stream_all(
         max_entries: 0,
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let mut zainod_response = zainod_client
         .get_address_utxos_stream(request)
@@ -3251,9 +3248,7 @@ pub async fn get_address_utxos_stream_all(
         zainod_address_utxo_replies.push(address_utxo_reply);
     }
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let mut lwd_response = lwd_client
         .get_address_utxos_stream(request)
@@ -3286,26 +3281,23 @@ pub async fn get_address_utxos_stream_lower(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -3318,9 +3310,7 @@ pub async fn get_address_utxos_stream_lower(
         max_entries: 1,
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let mut zainod_response = zainod_client
         .get_address_utxos_stream(request)
@@ -3332,9 +3322,7 @@ pub async fn get_address_utxos_stream_lower(
         zainod_address_utxo_replies.push(address_utxo_reply);
     }
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let mut lwd_response = lwd_client
         .get_address_utxos_stream(request)
@@ -3368,26 +3356,23 @@ pub async fn get_address_utxos_stream_upper(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -3400,9 +3385,7 @@ pub async fn get_address_utxos_stream_upper(
         max_entries: 1,
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let mut zainod_response = zainod_client
         .get_address_utxos_stream(request)
@@ -3414,9 +3397,7 @@ pub async fn get_address_utxos_stream_upper(
         zainod_address_utxo_replies.push(address_utxo_reply);
     }
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let mut lwd_response = lwd_client
         .get_address_utxos_stream(request)
@@ -3450,26 +3431,23 @@ pub async fn get_address_utxos_stream_out_of_bounds(
     zainod_bin: Option<PathBuf>,
     lightwalletd_bin: Option<PathBuf>,
 ) {
-    let zcashd = Zcashd::launch(ZcashdConfig {
-        zcashd_bin,
-        zcash_cli_bin,
-        rpc_port: None,
-        activation_heights: network::ActivationHeights::default(),
-        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests")),
-    })
-    .await
-    .unwrap();
+    let zcashd =
+        launch_client_rpc_tests_zcashd(zcashd_bin, zcash_cli_bin, lightwalletd_bin.as_deref())
+            .await;
     let zainod = Zainod::launch(ZainodConfig {
         zainod_bin,
         listen_port: None,
         validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
     let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
         lightwalletd_bin,
         listen_port: None,
         zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
     })
     .unwrap();
 
@@ -3482,9 +3460,7 @@ pub async fn get_address_utxos_stream_out_of_bounds(
         max_entries: 0,
     };
 
-    let mut zainod_client = client::build_client(network::localhost_uri(zainod.port()))
-        .await
-        .unwrap();
+    let mut zainod_client = zainod.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let mut zainod_response = zainod_client
         .get_address_utxos_stream(request)
@@ -3496,9 +3472,7 @@ pub async fn get_address_utxos_stream_out_of_bounds(
         zainod_address_utxo_replies.push(address_utxo_reply);
     }
 
-    let mut lwd_client = client::build_client(network::localhost_uri(lightwalletd.port()))
-        .await
-        .unwrap();
+    let mut lwd_client = lightwalletd.client().await.unwrap();
     let request = tonic::Request::new(address_utxos_arg.clone());
     let mut lwd_response = lwd_client
         .get_address_utxos_stream(request)
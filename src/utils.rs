@@ -1,13 +1,319 @@
 //! Utilities module
 
-use std::path::PathBuf;
+use std::{
+    ffi::OsString,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::{Duration, Instant},
+};
 
-/// Returns path to cargo manifest directory (project root)
-pub(crate) fn cargo_manifest_dir() -> PathBuf {
-    PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap())
+/// Environment variable used to set the chain cache directory
+pub const CHAIN_CACHE_DIR_ENV: &str = "ZCASH_LOCAL_NET_CHAIN_CACHE_DIR";
+
+/// Chain cache directory set with [`set_chain_cache_dir`]
+static CHAIN_CACHE_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Sets the chain cache directory for this process, overriding [`CHAIN_CACHE_DIR_ENV`] and the user cache directory.
+/// Passing `None` clears the setting.
+pub fn set_chain_cache_dir(chain_cache_dir: Option<PathBuf>) {
+    *CHAIN_CACHE_DIR
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = chain_cache_dir;
 }
 
 /// Returns path to chain cache directory
+///
+/// The directory is resolved in the following order:
+/// 1. the directory set with [`set_chain_cache_dir`]
+/// 2. the [`CHAIN_CACHE_DIR_ENV`] environment variable
+/// 3. `zcash_local_net` in the user cache directory, i.e. `$XDG_CACHE_HOME/zcash_local_net` on linux
+/// 4. `zcash_local_net` in the system temporary directory
 pub fn chain_cache_dir() -> PathBuf {
-    cargo_manifest_dir().join("chain_cache")
+    let explicit = CHAIN_CACHE_DIR
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+
+    resolve_chain_cache_dir(
+        explicit,
+        std::env::var_os(CHAIN_CACHE_DIR_ENV),
+        dirs::cache_dir(),
+    )
+}
+
+/// Returns path to the chain cache called `name` in the [`chain_cache_dir`].
+pub fn chain_cache(name: &str) -> PathBuf {
+    chain_cache_dir().join(name)
+}
+
+/// Returns the first line printed by running the binary at `bin` (or `default_bin` from $PATH) with `--version`.
+pub fn binary_version(bin: Option<&Path>, default_bin: &str) -> std::io::Result<String> {
+    command_version(bin, default_bin, "--version")
+}
+
+/// Returns the first line printed by running the binary at `bin` (or `default_bin` from $PATH) with `version_arg`,
+/// for binaries which print their version with a subcommand, i.e. `lightwalletd version`.
+pub(crate) fn command_version(
+    bin: Option<&Path>,
+    default_bin: &str,
+    version_arg: &str,
+) -> std::io::Result<String> {
+    let output = std::process::Command::new(bin.unwrap_or(Path::new(default_bin)))
+        .arg(version_arg)
+        .output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "{default_bin} {version_arg} failed. {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .to_string())
+}
+
+fn resolve_chain_cache_dir(
+    explicit: Option<PathBuf>,
+    env: Option<OsString>,
+    user_cache_dir: Option<PathBuf>,
+) -> PathBuf {
+    explicit
+        .or_else(|| env.filter(|env| !env.is_empty()).map(PathBuf::from))
+        .unwrap_or_else(|| {
+            user_cache_dir
+                .unwrap_or_else(std::env::temp_dir)
+                .join("zcash_local_net")
+        })
+}
+
+/// Recursively copies the `src` directory into `dst`.
+///
+/// Files are reflinked on filesystems which support copy-on-write (btrfs, xfs, APFS etc.), falling back to a copy.
+pub(crate) fn copy_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+    copy_dir_with_hardlinks(src, dst, &|_| false)
+}
+
+/// Snapshots the `src` database directory into `dst` so the validator can not write to the original files.
+///
+/// RocksDB never modifies `.sst` table files after they are written so they are hardlinked, falling back to a
+/// reflink or copy when `src` and `dst` are on different filesystems or the filesystem does not support hardlinks.
+/// All other files (manifests, write-ahead logs, lock files etc.) are mutable so are reflinked or copied.
+pub(crate) fn snapshot_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+    copy_dir_with_hardlinks(src, dst, &|path| {
+        path.extension().is_some_and(|extension| extension == "sst")
+    })
+}
+
+/// Creates a temporary directory for a chain cache snapshot in the `.snapshots` directory of the [`chain_cache_dir`],
+/// so snapshots of chain caches in the chain cache directory can hardlink the immutable database files.
+///
+/// Falls back to the system temporary directory if the snapshots directory can not be created, in which case the
+/// snapshot is copied if the chain cache is on a different filesystem.
+pub(crate) fn snapshot_scratch_dir() -> std::io::Result<tempfile::TempDir> {
+    let snapshots_dir = chain_cache_dir().join(".snapshots");
+    std::fs::create_dir_all(&snapshots_dir)
+        .and_then(|()| tempfile::tempdir_in(&snapshots_dir))
+        .or_else(|_| tempfile::tempdir())
+}
+
+/// Recursively copies the `src` directory into `dst`, hardlinking files which match `hardlink`.
+fn copy_dir_with_hardlinks(
+    src: &Path,
+    dst: &Path,
+    hardlink: &dyn Fn(&Path) -> bool,
+) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let src_path = entry.path();
+        let dst_path = dst.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            copy_dir_with_hardlinks(&src_path, &dst_path, hardlink)?;
+        } else if hardlink(&src_path) {
+            match std::fs::hard_link(&src_path, &dst_path) {
+                Ok(()) => (),
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        std::io::ErrorKind::CrossesDevices
+                            | std::io::ErrorKind::Unsupported
+                            | std::io::ErrorKind::TooManyLinks
+                    ) =>
+                {
+                    reflink_copy::reflink_or_copy(&src_path, &dst_path)?;
+                }
+                Err(error) => return Err(error),
+            }
+        } else {
+            reflink_copy::reflink_or_copy(&src_path, &dst_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Interval between polls of a validator or indexer while waiting for a transaction or height
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Calls `poll` every `interval` until it breaks with a value, which is returned, or `timeout` elapses. On timeout the
+/// state `poll` last continued with is returned as the inner error, for diagnostics. Errors returned by `poll` stop
+/// polling.
+pub(crate) async fn poll_until<T, S, E, F, Fut>(
+    timeout: Duration,
+    interval: Duration,
+    mut poll: F,
+) -> Result<Result<T, S>, E>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<ControlFlow<T, S>, E>>,
+{
+    let start = Instant::now();
+    loop {
+        let state = match poll().await? {
+            ControlFlow::Break(value) => return Ok(Ok(value)),
+            ControlFlow::Continue(state) => state,
+        };
+
+        if start.elapsed() > timeout {
+            return Ok(Err(state));
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    #[test]
+    fn resolve_chain_cache_dir() {
+        let explicit = Some(PathBuf::from("/explicit"));
+        let env = Some("/env".into());
+        let user_cache_dir = Some(PathBuf::from("/home/user/.cache"));
+
+        assert_eq!(
+            super::resolve_chain_cache_dir(explicit, env.clone(), user_cache_dir.clone()),
+            PathBuf::from("/explicit")
+        );
+        assert_eq!(
+            super::resolve_chain_cache_dir(None, env, user_cache_dir.clone()),
+            PathBuf::from("/env")
+        );
+        assert_eq!(
+            super::resolve_chain_cache_dir(None, Some("".into()), user_cache_dir),
+            PathBuf::from("/home/user/.cache/zcash_local_net")
+        );
+        assert_eq!(
+            super::resolve_chain_cache_dir(None, None, None),
+            std::env::temp_dir().join("zcash_local_net")
+        );
+    }
+
+    #[test]
+    fn copy_dir() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let blocks_dir = src.path().join("regtest").join("blocks");
+        std::fs::create_dir_all(&blocks_dir).unwrap();
+        std::fs::write(blocks_dir.join("blk00000.dat"), "blocks").unwrap();
+
+        super::copy_dir(src.path(), &dst.path().join("copy")).unwrap();
+
+        let copy_path = dst
+            .path()
+            .join("copy")
+            .join("regtest")
+            .join("blocks")
+            .join("blk00000.dat");
+        assert_eq!(std::fs::read_to_string(&copy_path).unwrap(), "blocks");
+        std::fs::write(&copy_path, "modified").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(blocks_dir.join("blk00000.dat")).unwrap(),
+            "blocks"
+        );
+        assert!(super::copy_dir(&src.path().join("missing"), dst.path()).is_err());
+    }
+
+    #[test]
+    fn snapshot_dir() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let db_dir = src.path().join("v26").join("testnet");
+        std::fs::create_dir_all(&db_dir).unwrap();
+        std::fs::write(db_dir.join("000008.sst"), "table").unwrap();
+        std::fs::write(db_dir.join("MANIFEST-000005"), "manifest").unwrap();
+
+        super::snapshot_dir(src.path(), dst.path()).unwrap();
+
+        let snapshot_dir = dst.path().join("v26").join("testnet");
+        assert_eq!(
+            std::fs::read_to_string(snapshot_dir.join("000008.sst")).unwrap(),
+            "table"
+        );
+        std::fs::write(snapshot_dir.join("MANIFEST-000005"), "modified").unwrap();
+        std::fs::remove_file(snapshot_dir.join("000008.sst")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(db_dir.join("MANIFEST-000005")).unwrap(),
+            "manifest"
+        );
+        assert!(db_dir.join("000008.sst").exists());
+    }
+
+    #[test]
+    fn snapshot_dir_hardlink_error() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        std::fs::write(src.path().join("000008.sst"), "table").unwrap();
+        std::fs::write(dst.path().join("000008.sst"), "existing").unwrap();
+
+        let error = super::snapshot_dir(src.path(), dst.path()).unwrap_err();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(
+            std::fs::read_to_string(dst.path().join("000008.sst")).unwrap(),
+            "existing"
+        );
+    }
+
+    #[tokio::test]
+    async fn poll_until() {
+        use std::{ops::ControlFlow, time::Duration};
+
+        let mut polls = 0;
+        let result: Result<Result<u32, u32>, ()> =
+            super::poll_until(Duration::from_secs(10), Duration::ZERO, || {
+                polls += 1;
+                let polls = polls;
+                async move {
+                    Ok(if polls == 3 {
+                        ControlFlow::Break(polls)
+                    } else {
+                        ControlFlow::Continue(polls)
+                    })
+                }
+            })
+            .await;
+        assert_eq!(result, Ok(Ok(3)));
+
+        let result: Result<Result<(), u32>, ()> =
+            super::poll_until(Duration::ZERO, Duration::ZERO, || async {
+                Ok(ControlFlow::Continue(1))
+            })
+            .await;
+        assert_eq!(result, Ok(Err(1)));
+
+        let result: Result<Result<(), u32>, &str> =
+            super::poll_until(Duration::from_secs(10), Duration::ZERO, || async {
+                Err("failed")
+            })
+            .await;
+        assert_eq!(result, Err("failed"));
+    }
 }
@@ -2,29 +2,91 @@
 
 use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    ops::ControlFlow,
     path::PathBuf,
     process::Child,
+    str::FromStr as _,
+    sync::{Arc, Mutex},
 };
 
 use zcash_protocol::consensus::BlockHeight;
 
 use getset::{CopyGetters, Getters};
 use portpicker::Port;
+use serde::Deserialize;
 use tempfile::TempDir;
-use zebra_chain::{parameters::NetworkUpgrade, serialization::ZcashSerialize as _};
+use zebra_chain::{
+    block::{merkle::AuthDataRoot, Block, ChainHistoryBlockTxAuthCommitmentHash},
+    parameters::NetworkUpgrade,
+    sapling,
+    serialization::{ZcashDeserializeInto as _, ZcashSerialize as _},
+    transaction::Transaction,
+    transparent,
+};
 use zebra_node_services::rpc_client::RpcRequestClient;
 use zebra_rpc::methods::get_block_template_rpcs::get_block_template::{
     proposal::TimeSource, proposal_block_from_template, GetBlockTemplate,
 };
 
 use crate::{
+    chain_cache::{self, ChainCacheManifest, OverwritePolicy, ValidatorKind},
     config,
-    error::LaunchError,
-    launch, logs,
+    error::{
+        ChainCacheError, CoinbaseError, ConfigError, GenerateBlockError, LaunchError, RpcError,
+        WaitError,
+    },
+    faucet, launch, logs,
     network::{self, Network},
-    Process,
+    utils, Process,
 };
 
+/// Clock used for the time of generated blocks, see [`Validator::set_chain_clock`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChainClock {
+    /// Blocks are generated at the validator's system time.
+    #[default]
+    System,
+    /// The next block is generated at `time`, in seconds since the unix epoch, and the clock advances by `step`
+    /// seconds after each generated block. A `step` of 0 holds the clock at `time`.
+    Mock {
+        /// Time of the next generated block
+        time: u32,
+        /// Seconds added to `time` after each generated block
+        step: u32,
+    },
+}
+
+impl ChainClock {
+    /// Creates a mock clock which generates every block at `time`.
+    pub fn fixed(time: u32) -> Self {
+        ChainClock::Mock { time, step: 0 }
+    }
+
+    /// Creates a mock clock which generates the next block at `start` and each following block `step` seconds later.
+    pub fn advancing(start: u32, step: u32) -> Self {
+        ChainClock::Mock { time: start, step }
+    }
+
+    /// Returns the time of the next generated block or `None` for the system clock.
+    pub fn time(&self) -> Option<u32> {
+        match self {
+            ChainClock::System => None,
+            ChainClock::Mock { time, .. } => Some(*time),
+        }
+    }
+
+    /// Returns the clock after a block is generated.
+    pub fn advance(self) -> Self {
+        match self {
+            ChainClock::System => ChainClock::System,
+            ChainClock::Mock { time, step } => ChainClock::Mock {
+                time: time.saturating_add(step),
+                step,
+            },
+        }
+    }
+}
+
 /// Zebrad default miner address.
 pub const ZEBRAD_DEFAULT_MINER: &str = "t27eWDgjFYJGVXmzrXeVjnb5J3uXDM9xH9v";
 
@@ -37,7 +99,14 @@ pub const ZEBRAD_DEFAULT_MINER: &str = "t27eWDgjFYJGVXmzrXeVjnb5J3uXDM9xH9v";
 ///
 /// Use `activation_heights` to specify custom network upgrade activation heights
 ///
+/// Use `funding_streams` to configure regtest funding stream recipients and the NU6 lockbox stream. Zcashd funding
+/// stream numerators are fixed by consensus rules and launching fails if a different numerator is configured.
+///
 /// Use `miner_address` to specify the target address for the block rewards when blocks are generated.
+///
+/// Use `extra_options` to add or override zcash.conf options and `extra_args` to pass additional command-line
+/// arguments to zcashd. Options managed by this crate (network, RPC port and credentials, activation heights and
+/// miner address) can not be overridden.
 pub struct ZcashdConfig {
     /// Zcashd binary location
     pub zcashd_bin: Option<PathBuf>,
@@ -47,10 +116,16 @@ pub struct ZcashdConfig {
     pub rpc_port: Option<Port>,
     /// Local network upgrade activation heights
     pub activation_heights: network::ActivationHeights,
+    /// Regtest funding streams and NU6 lockbox configuration
+    pub funding_streams: network::FundingStreams,
     /// Miner address
     pub miner_address: Option<&'static str>,
     /// Chain cache location. If `None`, launches a new chain.
     pub chain_cache: Option<PathBuf>,
+    /// Extra zcash.conf options as key/value pairs
+    pub extra_options: Vec<(String, String)>,
+    /// Extra command-line arguments
+    pub extra_args: Vec<String>,
 }
 
 /// Zebrad configuration
@@ -62,7 +137,14 @@ pub struct ZcashdConfig {
 ///
 /// Use `activation_heights` to specify custom network upgrade activation heights
 ///
+/// Use `funding_streams` to configure regtest funding stream recipients, numerators and the NU6 lockbox stream.
+/// Zebrad requires one address for each funding stream address change interval within the height range.
+///
 /// Use `miner_address` to specify the target address for the block rewards when blocks are generated.
+///
+/// Use `extra_options` to add or override zebrad.toml options, tables are merged with the generated config. Use
+/// `extra_args` to pass additional command-line arguments to zebrad. Options managed by this crate (network, listen
+/// addresses, cache directory, miner address and activation heights) can not be overridden.
 pub struct ZebradConfig {
     /// Zebrad binary location
     pub zebrad_bin: Option<PathBuf>,
@@ -72,6 +154,8 @@ pub struct ZebradConfig {
     pub rpc_listen_port: Option<Port>,
     /// Local network upgrade activation heights
     pub activation_heights: network::ActivationHeights,
+    /// Regtest funding streams and NU6 lockbox configuration
+    pub funding_streams: network::FundingStreams,
     /// Miner address
     pub miner_address: &'static str,
     /// Chain cache location. If `None`, launches a new chain.
@@ -81,6 +165,15 @@ pub struct ZebradConfig {
     /// Can be used for testing against cached testnet / mainnet chains where large chains are needed.
     /// `activation_heights` and `miner_address` will be ignored while not using regtest network.
     pub network: Network,
+    /// Serve the cached chain without network access.
+    ///
+    /// Writes empty initial peer lists, effectively disables peer crawling and forces zebrad to report the chain as
+    /// synced. Used for testing against cached testnet / mainnet chains in sandboxed environments.
+    pub offline: bool,
+    /// Extra zebrad.toml options
+    pub extra_options: toml::Table,
+    /// Extra command-line arguments
+    pub extra_args: Vec<String>,
 }
 
 impl Default for ZebradConfig {
@@ -90,13 +183,71 @@ impl Default for ZebradConfig {
             network_listen_port: None,
             rpc_listen_port: None,
             activation_heights: network::ActivationHeights::default(),
+            funding_streams: network::FundingStreams::default(),
             miner_address: &ZEBRAD_DEFAULT_MINER,
             chain_cache: None,
             network: Network::Regtest,
+            offline: false,
+            extra_options: toml::Table::new(),
+            extra_args: Vec::new(),
         }
     }
 }
 
+/// Block subsidy returned by the `getblocksubsidy` RPC
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct BlockSubsidy {
+    /// Funding stream outputs
+    #[serde(rename = "fundingstreams", default)]
+    pub funding_streams: Vec<FundingStreamSubsidy>,
+    /// Lockbox (deferred pool) contributions
+    #[serde(rename = "lockboxstreams", default)]
+    pub lockbox_streams: Vec<FundingStreamSubsidy>,
+}
+
+impl BlockSubsidy {
+    /// Total value paid into the lockbox in zatoshis.
+    pub fn lockbox_total_zat(&self) -> u64 {
+        self.lockbox_streams
+            .iter()
+            .map(|stream| stream.value_zat)
+            .sum()
+    }
+}
+
+/// Funding stream or lockbox stream value of a [`BlockSubsidy`]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct FundingStreamSubsidy {
+    /// Funding stream recipient
+    pub recipient: String,
+    /// Value in zatoshis
+    #[serde(rename = "valueZat")]
+    pub value_zat: u64,
+    /// Recipient address. Lockbox streams have no address.
+    #[serde(default)]
+    pub address: Option<String>,
+}
+
+/// Transparent output of a coinbase transaction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoinbaseOutput {
+    /// Raw output script
+    pub script_pub_key: Vec<u8>,
+    /// Value in zatoshis
+    pub value_zat: u64,
+}
+
+impl CoinbaseOutput {
+    /// Returns true if the output pays to the transparent `address`.
+    ///
+    /// Returns false if `address` is not a valid transparent address.
+    pub fn pays_to(&self, address: &str) -> bool {
+        transparent::Address::from_str(address).is_ok_and(|address| {
+            address.create_script_from_address().as_raw_bytes() == self.script_pub_key.as_slice()
+        })
+    }
+}
+
 /// Functionality for validator/full-node processes.
 pub trait Validator: Sized {
     /// Config filename
@@ -105,6 +256,9 @@ pub trait Validator: Sized {
     /// Validator config struct
     type Config;
 
+    /// Typed model of the config file
+    type ConfigFile: config::ConfigFile;
+
     /// Launch the process.
     fn launch(
         config: Self::Config,
@@ -120,9 +274,318 @@ pub trait Validator: Sized {
         n: u32,
     ) -> impl std::future::Future<Output = std::io::Result<()>> + Send;
 
+    /// Generates a block containing the hex encoded raw `transactions` and returns its height.
+    ///
+    /// Zebrad builds the block from a block template, so transactions which were never broadcast can be included,
+    /// mempool transactions which are not chosen are left in the mempool and `coinbase` replaces the hex encoded
+    /// coinbase transaction of the block. The default coinbase pays the block subsidy to the miner address.
+    ///
+    /// Zcashd can only mine its whole mempool. The `transactions` are broadcast before mining,
+    /// [`GenerateBlockError::UnexpectedMempoolTransactions`] is returned if the mempool contains other transactions
+    /// and a custom `coinbase` returns [`GenerateBlockError::Unsupported`].
+    fn generate_block_with_transactions(
+        &self,
+        transactions: &[String],
+        coinbase: Option<&str>,
+    ) -> impl std::future::Future<Output = Result<BlockHeight, GenerateBlockError>> + Send;
+
     /// Get chain height
     fn get_chain_height(&self) -> impl std::future::Future<Output = BlockHeight> + Send;
 
+    /// Shields the validator's mature transparent coinbase outputs to the shielded `address` and returns the txid of
+    /// the shielding transaction. The transaction is not mined.
+    fn shield_coinbase(
+        &self,
+        address: &str,
+    ) -> impl std::future::Future<Output = Result<String, CoinbaseError>> + Send;
+
+    /// Sets the clock used for the time of subsequently generated blocks. [`ChainClock::System`] restores the
+    /// system clock.
+    ///
+    /// Validators only accept block times after the median time of the previous 11 blocks, so mock clocks should
+    /// start after the chain tip and advance with each block.
+    fn set_chain_clock(
+        &self,
+        clock: ChainClock,
+    ) -> impl std::future::Future<Output = Result<(), RpcError>> + Send;
+
+    /// Returns the clock used for the time of the next generated block.
+    fn chain_clock(&self) -> ChainClock;
+
+    /// Calls the JSON-RPC `method` with `params` and returns the result.
+    fn call_rpc(
+        &self,
+        method: &str,
+        params: Vec<serde_json::Value>,
+    ) -> impl std::future::Future<Output = Result<serde_json::Value, RpcError>> + Send;
+
+    /// Gets the block subsidy at `height`, including funding stream and lockbox values.
+    fn get_block_subsidy(
+        &self,
+        height: BlockHeight,
+    ) -> impl std::future::Future<Output = Result<BlockSubsidy, RpcError>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let response = self
+                .call_rpc("getblocksubsidy", vec![u32::from(height).into()])
+                .await?;
+            serde_json::from_value(response).map_err(|e| RpcError::InvalidResponse {
+                method: "getblocksubsidy".to_string(),
+                reason: e.to_string(),
+            })
+        }
+    }
+
+    /// Gets the hex encoded raw block at `height`.
+    fn get_raw_block(
+        &self,
+        height: BlockHeight,
+    ) -> impl std::future::Future<Output = Result<String, RpcError>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let response = self
+                .call_rpc(
+                    "getblock",
+                    vec![u32::from(height).to_string().into(), 0.into()],
+                )
+                .await?;
+            response
+                .as_str()
+                .map(String::from)
+                .ok_or_else(|| RpcError::InvalidResponse {
+                    method: "getblock".to_string(),
+                    reason: "expected hex encoded block".to_string(),
+                })
+        }
+    }
+
+    /// Submits the hex encoded raw `block` to the validator.
+    ///
+    /// Returns [`RpcError::Response`] if the block is not accepted, for example if it is a duplicate or is rejected.
+    fn submit_block(
+        &self,
+        block: &str,
+    ) -> impl std::future::Future<Output = Result<(), RpcError>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let response = self.call_rpc("submitblock", vec![block.into()]).await?;
+            match response {
+                serde_json::Value::Null => Ok(()),
+                serde_json::Value::String(message) => Err(RpcError::Response {
+                    method: "submitblock".to_string(),
+                    message,
+                }),
+                response => Err(RpcError::Response {
+                    method: "submitblock".to_string(),
+                    message: response.to_string(),
+                }),
+            }
+        }
+    }
+
+    /// Mines blocks until a coinbase output paid to the validator's [`Self::coinbase_address`] can be spent in the
+    /// next block and returns the height of the coinbase.
+    ///
+    /// The validator always pays the coinbase to its configured miner address, so there is no address parameter; the
+    /// miner address is set with the `miner_address` field of the validator config.
+    ///
+    /// Transparent coinbase outputs are mined with [`network::COINBASE_MATURITY`] blocks on top. Shielded coinbase
+    /// outputs are spendable once mined. The network upgrades reported by `getblockchaininfo` determine the pool the
+    /// coinbase is paid to. The zcashd wallet only spends coinbase outputs with more than
+    /// [`network::COINBASE_MATURITY`] confirmations, so one more block is mined when zcashd mines to its wallet.
+    fn mine_until_spendable(
+        &self,
+    ) -> impl std::future::Future<Output = Result<BlockHeight, CoinbaseError>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let coinbase_height = u32::from(self.get_chain_height().await) + 1;
+            let blocks = match self.coinbase_address() {
+                Some(address) => {
+                    let blockchain_info = self.call_rpc("getblockchaininfo", Vec::new()).await?;
+                    let upgrade_active =
+                        |name| upgrade_active(&blockchain_info["upgrades"], name, coinbase_height);
+                    network::coinbase_maturity(
+                        address,
+                        self.network(),
+                        upgrade_active("Heartwood"),
+                        upgrade_active("NU5"),
+                    )?
+                }
+                // zcashd mines to a transparent address of its wallet
+                None => network::COINBASE_MATURITY + 1,
+            };
+            self.generate_blocks(blocks)
+                .await
+                .map_err(|e| RpcError::Request {
+                    method: "generate".to_string(),
+                    reason: e.to_string(),
+                })?;
+
+            Ok(BlockHeight::from_u32(coinbase_height))
+        }
+    }
+
+    /// Polls `getrawmempool` until the transaction `txid` is in the validator's mempool.
+    ///
+    /// Returns [`WaitError::MempoolTimeout`] if the transaction is not in the mempool within `timeout`.
+    fn wait_for_mempool(
+        &self,
+        txid: &str,
+        timeout: std::time::Duration,
+    ) -> impl std::future::Future<Output = Result<(), WaitError>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            utils::poll_until(timeout, utils::POLL_INTERVAL, || async move {
+                let mempool = self.call_rpc("getrawmempool", Vec::new()).await?;
+                let in_mempool = mempool
+                    .as_array()
+                    .is_some_and(|txids| txids.iter().any(|mempool_txid| mempool_txid == txid));
+                Ok::<_, RpcError>(if in_mempool {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                })
+            })
+            .await?
+            .map_err(|()| WaitError::MempoolTimeout {
+                txid: txid.to_string(),
+                timeout,
+            })
+        }
+    }
+
+    /// Polls `getrawtransaction` until the transaction `txid` is mined with at least `depth` confirmations, counting
+    /// the block it is mined in, and returns the height of that block. Blocks are not generated while waiting.
+    ///
+    /// Returns [`WaitError::ConfirmationTimeout`] if the transaction does not reach `depth` confirmations within
+    /// `timeout`.
+    fn wait_for_confirmation(
+        &self,
+        txid: &str,
+        depth: u32,
+        timeout: std::time::Duration,
+    ) -> impl std::future::Future<Output = Result<BlockHeight, WaitError>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            utils::poll_until(timeout, utils::POLL_INTERVAL, || async move {
+                let mined_height = match self
+                    .call_rpc("getrawtransaction", vec![txid.into(), serde_json::json!(1)])
+                    .await
+                {
+                    Ok(transaction) => transaction["height"]
+                        .as_u64()
+                        .and_then(|height| u32::try_from(height).ok()),
+                    // the transaction is unknown until it reaches the validator
+                    Err(RpcError::Response { .. }) => None,
+                    Err(e) => return Err(WaitError::from(e)),
+                };
+                let confirmations = match mined_height {
+                    Some(mined_height) => {
+                        (u32::from(self.get_chain_height().await) + 1).saturating_sub(mined_height)
+                    }
+                    None => 0,
+                };
+                Ok(match mined_height.filter(|_| confirmations >= depth) {
+                    Some(mined_height) => ControlFlow::Break(BlockHeight::from_u32(mined_height)),
+                    None => ControlFlow::Continue(confirmations),
+                })
+            })
+            .await?
+            .map_err(|confirmations| WaitError::ConfirmationTimeout {
+                txid: txid.to_string(),
+                depth,
+                confirmations,
+                timeout,
+            })
+        }
+    }
+
+    /// Gets the transparent outputs of the coinbase transaction at `height`.
+    fn get_coinbase_outputs(
+        &self,
+        height: BlockHeight,
+    ) -> impl std::future::Future<Output = Result<Vec<CoinbaseOutput>, RpcError>> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let invalid = |reason: String| RpcError::InvalidResponse {
+                method: "getblock".to_string(),
+                reason,
+            };
+
+            let block_data = self.get_raw_block(height).await?;
+            let block: Block = hex::decode(block_data)
+                .map_err(|e| invalid(e.to_string()))?
+                .zcash_deserialize_into()
+                .map_err(|e| invalid(e.to_string()))?;
+            let coinbase = block
+                .transactions
+                .first()
+                .ok_or_else(|| invalid("block has no transactions".to_string()))?;
+
+            Ok(coinbase
+                .outputs()
+                .iter()
+                .map(|output| CoinbaseOutput {
+                    script_pub_key: output.lock_script.as_raw_bytes().to_vec(),
+                    value_zat: output.value.into(),
+                })
+                .collect())
+        }
+    }
+
+    /// Asserts the coinbase transaction at `height` pays each funding stream with a transparent recipient address.
+    /// Returns the block subsidy so lockbox contributions, which have no coinbase output, can also be asserted.
+    fn assert_funding_stream_outputs(
+        &self,
+        height: BlockHeight,
+    ) -> impl std::future::Future<Output = BlockSubsidy> + Send
+    where
+        Self: Sync,
+    {
+        async move {
+            let subsidy = self.get_block_subsidy(height).await.unwrap();
+            let outputs = self.get_coinbase_outputs(height).await.unwrap();
+
+            for stream in &subsidy.funding_streams {
+                let Some(address) = &stream.address else {
+                    continue;
+                };
+                if transparent::Address::from_str(address).is_err() {
+                    tracing::warn!(
+                        "skipping {} funding stream with non-transparent address {address}",
+                        stream.recipient
+                    );
+                    continue;
+                }
+                assert!(
+                    outputs.iter().any(
+                        |output| output.pays_to(address) && output.value_zat == stream.value_zat
+                    ),
+                    "coinbase at height {height} does not pay {} zatoshis to {} funding stream address {address}. \
+                    coinbase outputs: {outputs:?}",
+                    stream.value_zat,
+                    stream.recipient,
+                );
+            }
+
+            subsidy
+        }
+    }
+
     /// Polls chain until it reaches target height
     fn poll_chain_height(
         &self,
@@ -143,36 +606,66 @@ pub trait Validator: Sized {
         self.config_dir().path().join(Self::CONFIG_FILENAME)
     }
 
+    /// Parses the config file written for this process.
+    fn read_config(&self) -> Result<Self::ConfigFile, ConfigError> {
+        <Self::ConfigFile as config::ConfigFile>::read(&self.config_path())
+    }
+
     /// Network type
     fn network(&self) -> Network;
 
-    /// Caches chain. This stops the zcashd process.
-    fn cache_chain(&mut self, chain_cache: PathBuf) -> std::process::Output {
-        if chain_cache.exists() {
-            panic!("chain cache already exists!");
-        }
+    /// Network upgrade activation heights
+    fn activation_heights(&self) -> &network::ActivationHeights;
 
-        self.stop();
-        std::thread::sleep(std::time::Duration::from_secs(3));
+    /// Returns the address coinbase outputs of generated blocks are paid to, or `None` if zcashd mines to its wallet.
+    fn coinbase_address(&self) -> Option<&str>;
+
+    /// Builds the chain cache manifest for the chain currently served by the validator.
+    fn chain_cache_manifest(
+        &self,
+    ) -> impl std::future::Future<Output = Result<ChainCacheManifest, ChainCacheError>> + Send;
+
+    /// Caches chain and writes the chain cache manifest. This stops the validator process.
+    /// Returns the manifest written to the chain cache.
+    ///
+    /// The chain cache is written to a temporary directory and renamed to `chain_cache` once complete so a partially
+    /// written chain cache is never left behind. `overwrite` decides whether an existing chain cache is replaced.
+    fn cache_chain(
+        &mut self,
+        chain_cache: PathBuf,
+        overwrite: OverwritePolicy,
+    ) -> impl std::future::Future<Output = Result<ChainCacheManifest, ChainCacheError>> + Send
+    where
+        Self: Send + Sync,
+    {
+        async move {
+            if chain_cache.exists() && overwrite == OverwritePolicy::Error {
+                return Err(ChainCacheError::AlreadyExists(chain_cache));
+            }
+            let manifest = self.chain_cache_manifest().await?;
+
+            self.stop();
+            std::thread::sleep(std::time::Duration::from_secs(3));
 
-        std::process::Command::new("cp")
-            .arg("-r")
-            .arg(self.data_dir().path().to_path_buf())
-            .arg(chain_cache)
-            .output()
-            .unwrap()
+            chain_cache::write_dir_atomic(&chain_cache, overwrite, |temp_dir| {
+                utils::copy_dir(self.data_dir().path(), temp_dir)?;
+                manifest.write(temp_dir)
+            })?;
+
+            Ok(manifest)
+        }
     }
 
-    /// Checks `chain cache` is valid and loads into `validator_data_dir`.
+    /// Checks `chain cache` is valid and compatible with the validator `config` and loads into `validator_data_dir`.
     /// Returns the path to the loaded chain cache.
     ///
-    /// If network is not `Regtest` variant, the chain cache will not be copied and the original cache path will be
-    /// returned instead
+    /// Chain caches created without a manifest are loaded without compatibility checks.
+    /// The original chain cache is never written to by the validator.
     fn load_chain(
         chain_cache: PathBuf,
         validator_data_dir: PathBuf,
-        validator_network: Network,
-    ) -> PathBuf;
+        config: &Self::Config,
+    ) -> Result<PathBuf, ChainCacheError>;
 
     /// Prints the stdout log.
     fn print_stdout(&self) {
@@ -207,6 +700,13 @@ pub struct Zcashd {
     zcash_cli_bin: Option<PathBuf>,
     /// Network upgrade activation heights
     activation_heights: network::ActivationHeights,
+    /// Funding streams
+    funding_streams: network::FundingStreams,
+    /// Miner address
+    miner_address: Option<&'static str>,
+    /// Clock set with [`Validator::set_chain_clock`]
+    #[getset(skip)]
+    chain_clock: Mutex<ChainClock>,
 }
 
 impl Zcashd {
@@ -232,12 +732,18 @@ impl Validator for Zcashd {
 
     type Config = ZcashdConfig;
 
+    type ConfigFile = config::ZcashConf;
+
     async fn launch(config: Self::Config) -> Result<Self, LaunchError> {
+        config::validate_activation_heights(Process::Zcashd, &config.activation_heights)?;
+        config::validate_funding_streams(Process::Zcashd, &config.funding_streams)?;
+        config::validate_zcashd_extras(&config.extra_options, &config.extra_args)?;
+
         let logs_dir = tempfile::tempdir().unwrap();
         let data_dir = tempfile::tempdir().unwrap();
 
         if let Some(cache) = config.chain_cache.clone() {
-            Self::load_chain(cache, data_dir.path().to_path_buf(), Network::Regtest);
+            Self::load_chain(cache, data_dir.path().to_path_buf(), &config)?;
         }
 
         let port = network::pick_unused_port(config.rpc_port);
@@ -246,9 +752,10 @@ impl Validator for Zcashd {
             config_dir.path(),
             port,
             &config.activation_heights,
+            &config.funding_streams,
             config.miner_address,
-        )
-        .unwrap();
+            &config.extra_options,
+        )?;
 
         let mut command = match config.zcashd_bin {
             Some(path) => std::process::Command::new(path),
@@ -269,6 +776,7 @@ impl Validator for Zcashd {
                 .as_str(),
                 "-debug=1",
             ])
+            .args(&config.extra_args)
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped());
 
@@ -292,6 +800,9 @@ impl Validator for Zcashd {
             data_dir,
             zcash_cli_bin: config.zcash_cli_bin,
             activation_heights: config.activation_heights,
+            funding_streams: config.funding_streams,
+            miner_address: config.miner_address,
+            chain_clock: Mutex::new(ChainClock::System),
         };
 
         if config.chain_cache.is_none() {
@@ -325,18 +836,140 @@ impl Validator for Zcashd {
 
     async fn generate_blocks(&self, n: u32) -> std::io::Result<()> {
         let chain_height = self.get_chain_height().await;
-        self.zcash_cli_command(&["generate", &n.to_string()])?;
+        match self.chain_clock() {
+            ChainClock::Mock { step, .. } if step > 0 => {
+                // the mock time is set before each block so every block is generated at the next clock time
+                for _ in 0..n {
+                    let clock = self.chain_clock();
+                    self.set_chain_clock(clock)
+                        .await
+                        .map_err(std::io::Error::other)?;
+                    self.zcash_cli_command(&["generate", "1"])?;
+                    *self.chain_clock.lock().unwrap() = clock.advance();
+                }
+            }
+            _ => {
+                self.zcash_cli_command(&["generate", &n.to_string()])?;
+            }
+        }
         self.poll_chain_height(chain_height + n).await;
 
         Ok(())
     }
 
+    /// Zcashd mines every mempool transaction with its own coinbase and ordering, so the chosen transactions are
+    /// broadcast and [`GenerateBlockError::UnexpectedMempoolTransactions`] is returned if the mempool contains any
+    /// other transactions. Custom coinbase transactions are not supported.
+    async fn generate_block_with_transactions(
+        &self,
+        transactions: &[String],
+        coinbase: Option<&str>,
+    ) -> Result<BlockHeight, GenerateBlockError> {
+        if coinbase.is_some() {
+            return Err(GenerateBlockError::Unsupported(
+                "zcashd can not mine a custom coinbase transaction".to_string(),
+            ));
+        }
+
+        let mut txids = Vec::new();
+        for transaction in transactions {
+            // transactions already in the mempool are accepted and return their txid
+            let txid = self
+                .call_rpc("sendrawtransaction", vec![transaction.as_str().into()])
+                .await?;
+            txids.push(
+                txid.as_str()
+                    .ok_or_else(|| RpcError::InvalidResponse {
+                        method: "sendrawtransaction".to_string(),
+                        reason: "expected txid".to_string(),
+                    })?
+                    .to_string(),
+            );
+        }
+        let mempool = self.call_rpc("getrawmempool", Vec::new()).await?;
+        let unexpected: Vec<String> = mempool
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|txid| txid.as_str())
+            .filter(|txid| !txids.iter().any(|chosen| chosen == txid))
+            .map(String::from)
+            .collect();
+        if !unexpected.is_empty() {
+            return Err(GenerateBlockError::UnexpectedMempoolTransactions(
+                unexpected,
+            ));
+        }
+
+        self.generate_blocks(1)
+            .await
+            .map_err(|e| GenerateBlockError::Rejected(e.to_string()))?;
+
+        Ok(self.get_chain_height().await)
+    }
+
     async fn get_chain_height(&self) -> BlockHeight {
         let output = self.zcash_cli_command(&["getchaintips"]).unwrap();
         let stdout_json = json::parse(&String::from_utf8_lossy(&output.stdout)).unwrap();
         BlockHeight::from_u32(stdout_json[0]["height"].as_u32().unwrap())
     }
 
+    /// Zcashd shields the coinbase outputs of its wallet with `z_shieldcoinbase`, so the miner address must belong to
+    /// the wallet, e.g. zcashd launched without a miner address.
+    async fn shield_coinbase(&self, address: &str) -> Result<String, CoinbaseError> {
+        Ok(faucet::shield_coinbase(self, address).await?)
+    }
+
+    async fn set_chain_clock(&self, clock: ChainClock) -> Result<(), RpcError> {
+        // a mock time of 0 restores the system clock
+        self.call_rpc("setmocktime", vec![clock.time().unwrap_or(0).into()])
+            .await?;
+        *self.chain_clock.lock().unwrap() = clock;
+        Ok(())
+    }
+
+    fn chain_clock(&self) -> ChainClock {
+        *self.chain_clock.lock().unwrap()
+    }
+
+    async fn call_rpc(
+        &self,
+        method: &str,
+        params: Vec<serde_json::Value>,
+    ) -> Result<serde_json::Value, RpcError> {
+        let params: Vec<String> = params
+            .into_iter()
+            .map(|param| match param {
+                serde_json::Value::String(param) => param,
+                param => param.to_string(),
+            })
+            .collect();
+        let mut args = vec![method];
+        args.extend(params.iter().map(String::as_str));
+
+        let output = self
+            .zcash_cli_command(&args)
+            .map_err(|e| RpcError::Request {
+                method: method.to_string(),
+                reason: e.to_string(),
+            })?;
+        if !output.status.success() {
+            return Err(RpcError::Response {
+                method: method.to_string(),
+                message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+
+        // zcash-cli prints string results without quotes and null results as an empty line
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stdout = stdout.trim();
+        if stdout.is_empty() {
+            return Ok(serde_json::Value::Null);
+        }
+        Ok(serde_json::from_str(stdout)
+            .unwrap_or_else(|_| serde_json::Value::String(stdout.to_string())))
+    }
+
     async fn poll_chain_height(&self, target_height: BlockHeight) {
         while self.get_chain_height().await < target_height {
             std::thread::sleep(std::time::Duration::from_millis(100));
@@ -356,26 +989,52 @@ impl Validator for Zcashd {
     }
 
     fn network(&self) -> Network {
-        unimplemented!();
+        Network::Regtest
+    }
+
+    fn activation_heights(&self) -> &network::ActivationHeights {
+        &self.activation_heights
+    }
+
+    fn coinbase_address(&self) -> Option<&str> {
+        self.miner_address
+    }
+
+    async fn chain_cache_manifest(&self) -> Result<ChainCacheManifest, ChainCacheError> {
+        ChainCacheManifest::from_validator(
+            self,
+            ValidatorKind::Zcashd,
+            &self.activation_heights,
+            &self.funding_streams,
+            self.miner_address,
+            None,
+        )
+        .await
     }
 
     fn load_chain(
         chain_cache: PathBuf,
         validator_data_dir: PathBuf,
-        _validator_network: Network,
-    ) -> PathBuf {
+        config: &Self::Config,
+    ) -> Result<PathBuf, ChainCacheError> {
         let regtest_dir = chain_cache.clone().join("regtest");
         if !regtest_dir.exists() {
-            panic!("regtest directory not found!");
+            return Err(ChainCacheError::NotFound(regtest_dir));
         }
+        chain_cache::check_manifest(
+            &chain_cache,
+            ValidatorKind::Zcashd,
+            Network::Regtest,
+            &config.activation_heights,
+            &config.funding_streams,
+            config.miner_address,
+            utils::binary_version(config.zcashd_bin.as_deref(), "zcashd")
+                .ok()
+                .as_deref(),
+        )?;
 
-        std::process::Command::new("cp")
-            .arg("-r")
-            .arg(regtest_dir)
-            .arg(validator_data_dir)
-            .output()
-            .unwrap();
-        chain_cache
+        utils::copy_dir(&regtest_dir, &validator_data_dir.join("regtest"))?;
+        Ok(chain_cache)
     }
 }
 
@@ -407,10 +1066,105 @@ pub struct Zebrad {
     data_dir: TempDir,
     /// Network upgrade activation heights
     activation_heights: network::ActivationHeights,
+    /// Funding streams
+    funding_streams: network::FundingStreams,
+    /// Miner address
+    miner_address: &'static str,
     /// RPC request client
     client: RpcRequestClient,
     /// Network type
     network: Network,
+    /// Clock set with [`Validator::set_chain_clock`]
+    #[getset(skip)]
+    chain_clock: Mutex<ChainClock>,
+    /// Time source of blocks generated with the system clock
+    #[getset(skip)]
+    time_source: Mutex<TimeSource>,
+}
+
+impl Zebrad {
+    /// Builds a block from a new block template at the time of the chain clock. `edit` can replace the transactions
+    /// of the block before the header commitments are computed.
+    async fn build_block(
+        &self,
+        edit: impl FnOnce(&GetBlockTemplate, &mut Block) -> Result<(), GenerateBlockError>,
+    ) -> Result<Block, GenerateBlockError> {
+        let block_template: GetBlockTemplate = self
+            .client
+            .json_result_from_call("getblocktemplate", "[]".to_string())
+            .await
+            .map_err(|e| RpcError::Request {
+                method: "getblocktemplate".to_string(),
+                reason: e.to_string(),
+            })?;
+
+        let network_upgrade =
+            network_upgrade(&self.activation_heights, block_template.height.into());
+
+        let time_source = match self.chain_clock() {
+            ChainClock::System => self.time_source(),
+            ChainClock::Mock { time, .. } => TimeSource::Clamped(
+                chrono::DateTime::from_timestamp(time.into(), 0)
+                    .expect("u32 timestamps should be in range"),
+            ),
+        };
+        // block templates do not include the pre-Heartwood header commitment so the block is built as a Canopy
+        // block and the commitment is replaced
+        let mut block = proposal_block_from_template(
+            &block_template,
+            time_source,
+            network_upgrade.max(NetworkUpgrade::Canopy),
+        )
+        .map_err(|e| RpcError::InvalidResponse {
+            method: "getblocktemplate".to_string(),
+            reason: e.to_string(),
+        })?;
+        edit(&block_template, &mut block)?;
+        if network_upgrade < NetworkUpgrade::Heartwood
+            && block
+                .transactions
+                .iter()
+                .any(|transaction| transaction.sapling_outputs().next().is_some())
+        {
+            return Err(GenerateBlockError::Unsupported(
+                "zebrad blocks with Sapling outputs can not be generated before Heartwood activates".to_string(),
+            ));
+        }
+
+        let header = Arc::make_mut(&mut block.header);
+        header.merkle_root = block.transactions.iter().collect();
+        if network_upgrade < NetworkUpgrade::Heartwood {
+            header.commitment_bytes.0 = pre_heartwood_commitment(network_upgrade);
+        } else if network_upgrade >= NetworkUpgrade::Nu5 {
+            // from NU5 the commitment includes the auth data root of the block's transactions
+            let auth_data_root: AuthDataRoot = block.transactions.iter().collect();
+            header.commitment_bytes.0 = ChainHistoryBlockTxAuthCommitmentHash::from_commitments(
+                &block_template.default_roots.chain_history_root,
+                &auth_data_root,
+            )
+            .into();
+        }
+
+        Ok(block)
+    }
+
+    fn advance_chain_clock(&self) {
+        let mut chain_clock = self.chain_clock.lock().unwrap();
+        *chain_clock = chain_clock.advance();
+    }
+
+    /// Returns the time source of blocks generated while the chain clock is [`ChainClock::System`].
+    pub fn time_source(&self) -> TimeSource {
+        *self.time_source.lock().unwrap()
+    }
+
+    /// Selects the time source of blocks generated while the chain clock is [`ChainClock::System`], e.g.
+    /// [`TimeSource::MinTime`] generates each block at the earliest time the block template allows.
+    ///
+    /// Mock clocks always use [`TimeSource::Clamped`] so the block time stays within the template's valid range.
+    pub fn set_time_source(&self, time_source: TimeSource) {
+        *self.time_source.lock().unwrap() = time_source;
+    }
 }
 
 impl Validator for Zebrad {
@@ -418,17 +1172,30 @@ impl Validator for Zebrad {
 
     type Config = ZebradConfig;
 
+    type ConfigFile = config::ZebradToml;
+
     async fn launch(config: Self::Config) -> Result<Self, LaunchError> {
+        config::validate_activation_heights(Process::Zebrad, &config.activation_heights)?;
+        config::validate_funding_streams(Process::Zebrad, &config.funding_streams)?;
+        config::validate_zebrad_extras(&config.extra_options, &config.extra_args)?;
+
         let logs_dir = tempfile::tempdir().unwrap();
-        let data_dir = tempfile::tempdir().unwrap();
 
         if !matches!(config.network, Network::Regtest) && config.chain_cache.is_none() {
             panic!("chain cache must be specified when not using a regtest network!")
         }
 
+        // testnet and mainnet chain caches are large so the data directory is created in the chain cache directory,
+        // allowing the snapshot to hardlink the immutable database files instead of copying them
+        let data_dir = match (&config.chain_cache, config.network) {
+            (Some(_), Network::Testnet | Network::Mainnet) => {
+                utils::snapshot_scratch_dir().map_err(ChainCacheError::from)?
+            }
+            _ => tempfile::tempdir().unwrap(),
+        };
+
         let cache_dir = if let Some(cache) = config.chain_cache.clone() {
-            Self::load_chain(cache.clone(), data_dir.path().to_path_buf(), config.network);
-            cache
+            Self::load_chain(cache, data_dir.path().to_path_buf(), &config)?
         } else {
             data_dir.path().to_path_buf()
         };
@@ -442,18 +1209,21 @@ impl Validator for Zebrad {
             network_listen_port,
             rpc_listen_port,
             &config.activation_heights,
+            &config.funding_streams,
             config.miner_address,
             config.network,
-        )
-        .unwrap();
+            config.offline,
+            &config.extra_options,
+        )?;
         // create zcashd conf necessary for lightwalletd
         config::zcashd(
             config_dir.path(),
             rpc_listen_port,
             &config.activation_heights,
+            &network::FundingStreams::default(),
             None,
-        )
-        .unwrap();
+            &[],
+        )?;
 
         let mut command = match config.zebrad_bin {
             Some(path) => std::process::Command::new(path),
@@ -467,8 +1237,9 @@ impl Validator for Zebrad {
                     config_file_path.to_str().expect("should be valid UTF-8")
                 )
                 .as_str(),
-                "start",
             ])
+            .args(&config.extra_args)
+            .arg("start")
             .stdout(std::process::Stdio::piped())
             .stderr(std::process::Stdio::piped());
 
@@ -496,8 +1267,12 @@ impl Validator for Zebrad {
             logs_dir,
             data_dir,
             activation_heights: config.activation_heights,
+            funding_streams: config.funding_streams,
+            miner_address: config.miner_address,
             client,
             network: config.network,
+            chain_clock: Mutex::new(ChainClock::System),
+            time_source: Mutex::new(TimeSource::default()),
         };
 
         if config.chain_cache.is_none() && matches!(config.network, Network::Regtest) {
@@ -517,44 +1292,70 @@ impl Validator for Zebrad {
         let chain_height = dbg!(self.get_chain_height().await);
 
         for _ in 0..n {
-            let block_template: GetBlockTemplate = self
-                .client
-                .json_result_from_call("getblocktemplate", "[]".to_string())
+            let block = self
+                .build_block(|_, _| Ok(()))
                 .await
-                .expect("response should be success output with a serialized `GetBlockTemplate`");
-
-            let network_upgrade = if block_template.height < self.activation_heights().nu5.into() {
-                NetworkUpgrade::Canopy
-            } else {
-                NetworkUpgrade::Nu5
-            };
-
-            let block_data = hex::encode(
-                proposal_block_from_template(
-                    &block_template,
-                    TimeSource::default(),
-                    network_upgrade,
-                )
-                .unwrap()
-                .zcash_serialize_to_vec()
-                .unwrap(),
-            );
+                .map_err(std::io::Error::other)?;
+            let block_data = hex::encode(block.zcash_serialize_to_vec()?);
 
-            let submit_block_response = self
-                .client
-                .text_from_call("submitblock", format!(r#"["{block_data}"]"#))
+            self.submit_block(&block_data)
                 .await
-                .unwrap();
-
-            if !submit_block_response.contains(r#""result":null"#) {
-                panic!("failed to submit block!")
-            };
+                .map_err(std::io::Error::other)?;
+            self.advance_chain_clock();
         }
         self.poll_chain_height(chain_height + n).await;
 
         Ok(())
     }
 
+    /// The template's transactions are replaced by the chosen `transactions`. The default coinbase is the template's
+    /// coinbase with the fees of the template's transactions removed from the miner output, as the fees of
+    /// transactions which were never broadcast are unknown.
+    async fn generate_block_with_transactions(
+        &self,
+        transactions: &[String],
+        coinbase: Option<&str>,
+    ) -> Result<BlockHeight, GenerateBlockError> {
+        let transactions = transactions
+            .iter()
+            .map(|transaction| decode_transaction(transaction))
+            .collect::<Result<Vec<_>, _>>()?;
+        let coinbase = coinbase.map(decode_transaction).transpose()?;
+
+        let chain_height = self.get_chain_height().await;
+        let block = self
+            .build_block(|block_template, block| {
+                let coinbase = match coinbase {
+                    Some(coinbase) => coinbase,
+                    None => {
+                        let mut coinbase = block.transactions[0].clone();
+                        remove_template_fees(
+                            Arc::make_mut(&mut coinbase),
+                            block_template,
+                            self.miner_address,
+                        )?;
+                        coinbase
+                    }
+                };
+                block.transactions = std::iter::once(coinbase).chain(transactions).collect();
+                Ok(())
+            })
+            .await?;
+        let block_data = hex::encode(
+            block
+                .zcash_serialize_to_vec()
+                .expect("serializing to a vec should not fail"),
+        );
+        self.submit_block(&block_data).await.map_err(|e| match e {
+            RpcError::Response { message, .. } => GenerateBlockError::Rejected(message),
+            e => e.into(),
+        })?;
+        self.advance_chain_clock();
+        self.poll_chain_height(chain_height + 1).await;
+
+        Ok(chain_height + 1)
+    }
+
     async fn get_chain_height(&self) -> BlockHeight {
         let response: serde_json::Value = self
             .client
@@ -571,6 +1372,60 @@ impl Validator for Zebrad {
         BlockHeight::from_u32(chain_height)
     }
 
+    /// Zebrad has no wallet, so coinbase outputs can only be shielded when mining to `funding::FUNDING_ADDRESS` with
+    /// the `funding` feature.
+    async fn shield_coinbase(&self, address: &str) -> Result<String, CoinbaseError> {
+        #[cfg(feature = "funding")]
+        if self.miner_address == crate::funding::FUNDING_ADDRESS {
+            return Ok(crate::funding::Funder::new(&self.activation_heights)
+                .sweep(self, address)
+                .await?);
+        }
+
+        Err(CoinbaseError::Unsupported(format!(
+            "zebrad has no wallet to shield the coinbase outputs of {} to {address}",
+            self.miner_address
+        )))
+    }
+
+    async fn set_chain_clock(&self, clock: ChainClock) -> Result<(), RpcError> {
+        // zebrad has no mock time so the clock is applied to the block proposals built in `generate_blocks`
+        *self.chain_clock.lock().unwrap() = clock;
+        Ok(())
+    }
+
+    fn chain_clock(&self) -> ChainClock {
+        *self.chain_clock.lock().unwrap()
+    }
+
+    async fn call_rpc(
+        &self,
+        method: &str,
+        params: Vec<serde_json::Value>,
+    ) -> Result<serde_json::Value, RpcError> {
+        let response = self
+            .client
+            .text_from_call(method, serde_json::Value::Array(params).to_string())
+            .await
+            .map_err(|e| RpcError::Request {
+                method: method.to_string(),
+                reason: e.to_string(),
+            })?;
+        let mut response: serde_json::Value =
+            serde_json::from_str(&response).map_err(|e| RpcError::InvalidResponse {
+                method: method.to_string(),
+                reason: e.to_string(),
+            })?;
+
+        match response.get("error") {
+            Some(error) if !error.is_null() => Err(RpcError::Response {
+                method: method.to_string(),
+                message: error.to_string(),
+            }),
+            _ => Ok(response["result"].take()),
+        }
+    }
+
     async fn poll_chain_height(&self, target_height: BlockHeight) {
         while self.get_chain_height().await < target_height {
             std::thread::sleep(std::time::Duration::from_millis(100));
@@ -593,27 +1448,49 @@ impl Validator for Zebrad {
         self.network
     }
 
+    fn activation_heights(&self) -> &network::ActivationHeights {
+        &self.activation_heights
+    }
+
+    fn coinbase_address(&self) -> Option<&str> {
+        Some(self.miner_address)
+    }
+
+    async fn chain_cache_manifest(&self) -> Result<ChainCacheManifest, ChainCacheError> {
+        ChainCacheManifest::from_validator(
+            self,
+            ValidatorKind::Zebrad,
+            &self.activation_heights,
+            &self.funding_streams,
+            Some(self.miner_address),
+            chain_cache::zebrad_state_version(&self.data_dir.path().join("state")),
+        )
+        .await
+    }
+
     fn load_chain(
         chain_cache: PathBuf,
         validator_data_dir: PathBuf,
-        validator_network: Network,
-    ) -> PathBuf {
+        config: &Self::Config,
+    ) -> Result<PathBuf, ChainCacheError> {
         let state_dir = chain_cache.clone().join("state");
         if !state_dir.exists() {
-            panic!("state directory not found!");
+            return Err(ChainCacheError::NotFound(state_dir));
         }
+        chain_cache::check_manifest(
+            &chain_cache,
+            ValidatorKind::Zebrad,
+            config.network,
+            &config.activation_heights,
+            &config.funding_streams,
+            Some(config.miner_address),
+            utils::binary_version(config.zebrad_bin.as_deref(), "zebrad")
+                .ok()
+                .as_deref(),
+        )?;
 
-        if matches!(validator_network, Network::Regtest) {
-            std::process::Command::new("cp")
-                .arg("-r")
-                .arg(state_dir)
-                .arg(validator_data_dir.clone())
-                .output()
-                .unwrap();
-            validator_data_dir
-        } else {
-            chain_cache
-        }
+        utils::snapshot_dir(&state_dir, &validator_data_dir.join("state"))?;
+        Ok(validator_data_dir)
     }
 }
 
@@ -622,3 +1499,119 @@ impl Drop for Zebrad {
         self.stop();
     }
 }
+
+/// Decodes a hex encoded raw transaction.
+fn decode_transaction(transaction: &str) -> Result<Arc<Transaction>, GenerateBlockError> {
+    let transaction: Transaction = hex::decode(transaction)
+        .map_err(|e| GenerateBlockError::InvalidTransaction(e.to_string()))?
+        .zcash_deserialize_into()
+        .map_err(|e| GenerateBlockError::InvalidTransaction(e.to_string()))?;
+
+    Ok(Arc::new(transaction))
+}
+
+/// Removes the fees of the block template's transactions from the miner output of the template's `coinbase`.
+///
+/// Returns [`GenerateBlockError::Unsupported`] if the miner output can not be found in the coinbase or does not
+/// include the fees, i.e. if the miner address is not a transparent address.
+fn remove_template_fees(
+    coinbase: &mut Transaction,
+    block_template: &GetBlockTemplate,
+    miner_address: &str,
+) -> Result<(), GenerateBlockError> {
+    let fees = i64::from(block_template.coinbase_txn.fee).unsigned_abs();
+    if fees == 0 {
+        return Ok(());
+    }
+
+    let miner_script = transparent::Address::from_str(miner_address)
+        .map_err(|e| {
+            GenerateBlockError::Unsupported(format!(
+                "can not remove the template fees from the coinbase, invalid transparent miner address {miner_address}. {e}"
+            ))
+        })?
+        .create_script_from_address();
+    let version = coinbase.version();
+    let outputs = match coinbase {
+        Transaction::V4 { outputs, .. } | Transaction::V5 { outputs, .. } => outputs,
+        _ => {
+            return Err(GenerateBlockError::Unsupported(format!(
+                "can not remove the template fees from a v{version} coinbase"
+            )))
+        }
+    };
+    let miner_output = outputs
+        .iter_mut()
+        .find(|output| output.lock_script == miner_script)
+        .ok_or_else(|| {
+            GenerateBlockError::Unsupported(format!(
+                "can not remove the template fees, block template coinbase does not pay {miner_address}"
+            ))
+        })?;
+    miner_output.value = u64::from(miner_output.value)
+        .checked_sub(fees)
+        .and_then(|value| value.try_into().ok())
+        .ok_or_else(|| {
+            GenerateBlockError::Unsupported(format!(
+                "can not remove {fees} zatoshis of template fees from a miner output of {} zatoshis",
+                u64::from(miner_output.value)
+            ))
+        })?;
+
+    Ok(())
+}
+
+/// Returns `true` if the network upgrade called `name` in the `upgrades` of a `getblockchaininfo` response is active
+/// at `height`.
+fn upgrade_active(upgrades: &serde_json::Value, name: &str, height: u32) -> bool {
+    upgrades.as_object().is_some_and(|upgrades| {
+        upgrades.values().any(|upgrade| {
+            upgrade["name"]
+                .as_str()
+                .is_some_and(|upgrade_name| upgrade_name.eq_ignore_ascii_case(name))
+                && upgrade["activationheight"]
+                    .as_u64()
+                    .is_some_and(|activation_height| activation_height <= height.into())
+        })
+    })
+}
+
+/// Returns the network upgrade active at `height` for block proposals.
+fn network_upgrade(
+    activation_heights: &network::ActivationHeights,
+    height: BlockHeight,
+) -> NetworkUpgrade {
+    [
+        (
+            Some(activation_heights.overwinter),
+            NetworkUpgrade::Overwinter,
+        ),
+        (Some(activation_heights.sapling), NetworkUpgrade::Sapling),
+        (Some(activation_heights.blossom), NetworkUpgrade::Blossom),
+        (
+            Some(activation_heights.heartwood),
+            NetworkUpgrade::Heartwood,
+        ),
+        (Some(activation_heights.canopy), NetworkUpgrade::Canopy),
+        (Some(activation_heights.nu5), NetworkUpgrade::Nu5),
+        (activation_heights.nu6, NetworkUpgrade::Nu6),
+    ]
+    .into_iter()
+    .rev()
+    .find(|(activation_height, _)| activation_height.is_some_and(|h| height >= h))
+    .map_or(NetworkUpgrade::BeforeOverwinter, |(_, network_upgrade)| {
+        network_upgrade
+    })
+}
+
+/// Returns the block header commitment for network upgrades before Heartwood.
+///
+/// Sapling and Blossom blocks commit to the final Sapling note commitment tree root. The empty tree root is used as
+/// [`Zebrad::build_block`] rejects blocks with Sapling outputs before Heartwood activates.
+fn pre_heartwood_commitment(network_upgrade: NetworkUpgrade) -> [u8; 32] {
+    if network_upgrade >= NetworkUpgrade::Sapling {
+        sapling::tree::NoteCommitmentTree::default().root().into()
+    } else {
+        [0; 32]
+    }
+}
@@ -10,10 +10,20 @@ use zingolib::{
 };
 
 use zcash_local_net::{
+    chain_cache::OverwritePolicy,
     client,
-    indexer::{Indexer as _, Lightwalletd, LightwalletdConfig, Zainod, ZainodConfig},
-    network, utils,
-    validator::{Validator, Zcashd, ZcashdConfig, Zebrad, ZebradConfig, ZEBRAD_DEFAULT_MINER},
+    error::{
+        ChainCacheError, CoinbaseError, ConfigError, FaucetError, GenerateBlockError, LaunchError,
+        ReplayError,
+    },
+    faucet::ZcashdFaucet,
+    indexer::{Indexer, Lightwalletd, LightwalletdConfig, Zainod, ZainodConfig},
+    network, replay,
+    scenario::{self, Scenario},
+    utils,
+    validator::{
+        ChainClock, Validator, Zcashd, ZcashdConfig, Zebrad, ZebradConfig, ZEBRAD_DEFAULT_MINER,
+    },
     LocalNet,
 };
 
@@ -22,6 +32,7 @@ const ZCASH_CLI_BIN: Option<PathBuf> = None;
 const ZEBRAD_BIN: Option<PathBuf> = None;
 const LIGHTWALLETD_BIN: Option<PathBuf> = None;
 const ZAINOD_BIN: Option<PathBuf> = None;
+const WAIT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
 
 #[tokio::test]
 async fn launch_zcashd() {
@@ -32,13 +43,24 @@ async fn launch_zcashd() {
         zcash_cli_bin: ZCASH_CLI_BIN,
         rpc_port: None,
         activation_heights: network::ActivationHeights::default(),
+        funding_streams: network::FundingStreams::default(),
         miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
         chain_cache: None,
+        extra_options: Vec::new(),
+        extra_args: Vec::new(),
     })
     .await
     .unwrap();
     zcashd.print_stdout();
     zcashd.print_stderr();
+
+    let config = zcashd.read_config().unwrap();
+    assert!(config.regtest);
+    assert_eq!(config.rpcport, zcashd.port());
+    assert_eq!(
+        config.mineraddress.as_deref(),
+        Some(REG_O_ADDR_FROM_ABANDONART)
+    );
 }
 
 #[tokio::test]
@@ -50,14 +72,143 @@ async fn launch_zebrad() {
         network_listen_port: None,
         rpc_listen_port: None,
         activation_heights: network::ActivationHeights::default(),
+        funding_streams: network::FundingStreams::default(),
+        miner_address: ZEBRAD_DEFAULT_MINER,
+        chain_cache: None,
+        network: network::Network::Regtest,
+        offline: false,
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
+    })
+    .await
+    .unwrap();
+    zebrad.print_stdout();
+    zebrad.print_stderr();
+
+    let config = zebrad.read_config().unwrap();
+    assert_eq!(config.network.network, "Regtest");
+    assert_eq!(
+        config.rpc.listen_addr,
+        format!("127.0.0.1:{}", zebrad.rpc_listen_port())
+    );
+    assert_eq!(
+        config.mining.miner_address.as_deref(),
+        Some(ZEBRAD_DEFAULT_MINER)
+    );
+}
+
+#[tokio::test]
+async fn launch_zebrad_nu6() {
+    tracing_subscriber::fmt().init();
+
+    let zebrad = Zebrad::launch(ZebradConfig {
+        zebrad_bin: ZEBRAD_BIN,
+        network_listen_port: None,
+        rpc_listen_port: None,
+        activation_heights: network::ActivationHeights {
+            nu6: Some(5.into()),
+            ..Default::default()
+        },
+        funding_streams: network::FundingStreams::default(),
         miner_address: ZEBRAD_DEFAULT_MINER,
         chain_cache: None,
         network: network::Network::Regtest,
+        offline: false,
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .await
     .unwrap();
+
+    zebrad.generate_blocks(8).await.unwrap();
     zebrad.print_stdout();
     zebrad.print_stderr();
+
+    assert_eq!(zebrad.get_chain_height().await, 8.into());
+}
+
+#[tokio::test]
+async fn launch_zebrad_network_upgrade_transitions() {
+    tracing_subscriber::fmt().init();
+
+    let zebrad = Zebrad::launch(ZebradConfig {
+        zebrad_bin: ZEBRAD_BIN,
+        network_listen_port: None,
+        rpc_listen_port: None,
+        activation_heights: network::ActivationHeights {
+            overwinter: 1.into(),
+            sapling: 1.into(),
+            blossom: 2.into(),
+            heartwood: 3.into(),
+            canopy: 4.into(),
+            nu5: 5.into(),
+            nu6: Some(6.into()),
+        },
+        funding_streams: network::FundingStreams::default(),
+        miner_address: ZEBRAD_DEFAULT_MINER,
+        chain_cache: None,
+        network: network::Network::Regtest,
+        offline: false,
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
+    })
+    .await
+    .unwrap();
+
+    zebrad.generate_blocks(7).await.unwrap();
+    zebrad.print_stdout();
+    zebrad.print_stderr();
+
+    assert_eq!(zebrad.get_chain_height().await, 7.into());
+}
+
+#[tokio::test]
+async fn zebrad_nu6_funding_streams() {
+    tracing_subscriber::fmt().init();
+
+    let zebrad = Zebrad::launch(ZebradConfig {
+        zebrad_bin: ZEBRAD_BIN,
+        network_listen_port: None,
+        rpc_listen_port: None,
+        activation_heights: network::ActivationHeights {
+            nu6: Some(2.into()),
+            ..Default::default()
+        },
+        funding_streams: network::FundingStreams {
+            pre_nu6: None,
+            post_nu6: Some(network::FundingStreamPeriod {
+                height_range: 2.into()..10.into(),
+                recipients: vec![
+                    network::FundingStreamRecipient {
+                        receiver: network::FundingStreamReceiver::MajorGrants,
+                        numerator: 8,
+                        addresses: vec![ZEBRAD_DEFAULT_MINER.to_string()],
+                    },
+                    network::FundingStreamRecipient {
+                        receiver: network::FundingStreamReceiver::Deferred,
+                        numerator: 12,
+                        addresses: Vec::new(),
+                    },
+                ],
+            }),
+        },
+        miner_address: ZEBRAD_DEFAULT_MINER,
+        chain_cache: None,
+        network: network::Network::Regtest,
+        offline: false,
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
+    })
+    .await
+    .unwrap();
+
+    zebrad.generate_blocks(3).await.unwrap();
+    zebrad.print_stdout();
+    zebrad.print_stderr();
+
+    let subsidy = zebrad.assert_funding_stream_outputs(3.into()).await;
+    assert_eq!(subsidy.funding_streams.len(), 1);
+    assert!(subsidy.lockbox_total_zat() > 0);
 }
 
 #[tokio::test]
@@ -69,9 +220,13 @@ async fn launch_zebrad_with_cache() {
         network_listen_port: None,
         rpc_listen_port: None,
         activation_heights: network::ActivationHeights::default(),
+        funding_streams: network::FundingStreams::default(),
         miner_address: ZEBRAD_DEFAULT_MINER,
-        chain_cache: Some(utils::chain_cache_dir().join("client_rpc_tests_large")),
+        chain_cache: Some(utils::chain_cache("client_rpc_tests_large")),
         network: network::Network::Regtest,
+        offline: false,
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
     })
     .await
     .unwrap();
@@ -81,6 +236,374 @@ async fn launch_zebrad_with_cache() {
     assert_eq!(zebrad.get_chain_height().await, 52.into());
 }
 
+#[tokio::test]
+async fn zebrad_chain_cache_manifest() {
+    tracing_subscriber::fmt().init();
+
+    let config = |activation_heights, chain_cache| ZebradConfig {
+        zebrad_bin: ZEBRAD_BIN,
+        network_listen_port: None,
+        rpc_listen_port: None,
+        activation_heights,
+        funding_streams: network::FundingStreams::default(),
+        miner_address: ZEBRAD_DEFAULT_MINER,
+        chain_cache,
+        network: network::Network::Regtest,
+        offline: false,
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
+    };
+    let mut zebrad = Zebrad::launch(config(network::ActivationHeights::default(), None))
+        .await
+        .unwrap();
+    zebrad.generate_blocks(2).await.unwrap();
+
+    let chain_cache_dir = tempfile::tempdir().unwrap();
+    let chain_cache = chain_cache_dir.path().join("chain");
+    let manifest = zebrad
+        .cache_chain(chain_cache.clone(), OverwritePolicy::Error)
+        .await
+        .unwrap();
+    assert_eq!(manifest.tip_height, 2);
+    assert!(matches!(
+        zebrad
+            .cache_chain(chain_cache.clone(), OverwritePolicy::Error)
+            .await,
+        Err(ChainCacheError::AlreadyExists(_))
+    ));
+
+    let zebrad = Zebrad::launch(config(
+        network::ActivationHeights::default(),
+        Some(chain_cache.clone()),
+    ))
+    .await
+    .unwrap();
+    assert_eq!(zebrad.get_chain_height().await, 2.into());
+    drop(zebrad);
+
+    let result = Zebrad::launch(config(
+        network::ActivationHeights {
+            nu6: Some(2.into()),
+            ..Default::default()
+        },
+        Some(chain_cache),
+    ))
+    .await;
+    assert!(matches!(
+        result,
+        Err(LaunchError::ChainCache(ChainCacheError::Incompatible { field, .. }))
+            if field == "activation_heights"
+    ));
+}
+
+#[tokio::test]
+async fn replay_zcashd_chain_into_zebrad() {
+    tracing_subscriber::fmt().init();
+
+    let zcashd = Zcashd::launch(ZcashdConfig {
+        zcashd_bin: ZCASHD_BIN,
+        zcash_cli_bin: ZCASH_CLI_BIN,
+        rpc_port: None,
+        activation_heights: network::ActivationHeights::default(),
+        funding_streams: network::FundingStreams::default(),
+        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
+        chain_cache: None,
+        extra_options: Vec::new(),
+        extra_args: Vec::new(),
+    })
+    .await
+    .unwrap();
+    let zebrad = Zebrad::launch(ZebradConfig::default()).await.unwrap();
+    zcashd.generate_blocks(5).await.unwrap();
+
+    let block_file = replay::export_blocks(&zcashd, 1.into(), 5.into())
+        .await
+        .unwrap();
+    let block_file_dir = tempfile::tempdir().unwrap();
+    let block_file_path = block_file_dir.path().join("blocks.toml");
+    block_file.write(&block_file_path).unwrap();
+
+    let height =
+        replay::replay_blocks(&zebrad, &replay::BlockFile::read(&block_file_path).unwrap())
+            .await
+            .unwrap();
+    assert_eq!(height, 5.into());
+    assert!(matches!(
+        replay::replay_blocks(&zebrad, &block_file).await,
+        Err(ReplayError::HeightMismatch { .. })
+    ));
+
+    zcashd.generate_blocks(2).await.unwrap();
+    assert_eq!(
+        replay::replay_chain(&zcashd, &zebrad).await.unwrap(),
+        zcashd.get_chain_height().await
+    );
+    assert_eq!(
+        zebrad
+            .call_rpc("getbestblockhash", Vec::new())
+            .await
+            .unwrap(),
+        zcashd
+            .call_rpc("getbestblockhash", Vec::new())
+            .await
+            .unwrap()
+    );
+}
+
+/// Returns the system time in seconds since the unix epoch.
+fn now() -> u32 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as u32
+}
+
+/// Returns the times of the blocks at `heights`.
+async fn block_times(
+    validator: &impl Validator,
+    heights: std::ops::RangeInclusive<u32>,
+) -> Vec<u32> {
+    let mut times = Vec::new();
+    for height in heights {
+        let block = validator
+            .call_rpc(
+                "getblock",
+                vec![height.to_string().into(), serde_json::json!(1)],
+            )
+            .await
+            .unwrap();
+        times.push(block["time"].as_u64().unwrap() as u32);
+    }
+    times
+}
+
+#[tokio::test]
+async fn zcashd_chain_clock() {
+    tracing_subscriber::fmt().init();
+
+    let zcashd = Zcashd::launch(ZcashdConfig {
+        zcashd_bin: ZCASHD_BIN,
+        zcash_cli_bin: ZCASH_CLI_BIN,
+        rpc_port: None,
+        activation_heights: network::ActivationHeights::default(),
+        funding_streams: network::FundingStreams::default(),
+        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
+        chain_cache: None,
+        extra_options: Vec::new(),
+        extra_args: Vec::new(),
+    })
+    .await
+    .unwrap();
+    // block times must be after the chain tip and not too far in the future
+    let start = now() + 600;
+    zcashd
+        .set_chain_clock(ChainClock::advancing(start, 60))
+        .await
+        .unwrap();
+    zcashd.generate_blocks(3).await.unwrap();
+
+    assert_eq!(
+        block_times(&zcashd, 2..=4).await,
+        vec![start, start + 60, start + 120]
+    );
+    assert_eq!(zcashd.chain_clock(), ChainClock::advancing(start + 180, 60));
+
+    zcashd.set_chain_clock(ChainClock::System).await.unwrap();
+}
+
+#[tokio::test]
+async fn zebrad_chain_clock() {
+    tracing_subscriber::fmt().init();
+
+    let zebrad = Zebrad::launch(ZebradConfig::default()).await.unwrap();
+    // block times must be after the chain tip and not too far in the future
+    let start = now() + 600;
+    zebrad
+        .set_chain_clock(ChainClock::advancing(start, 60))
+        .await
+        .unwrap();
+    zebrad.generate_blocks(3).await.unwrap();
+
+    assert_eq!(
+        block_times(&zebrad, 1..=3).await,
+        vec![start, start + 60, start + 120]
+    );
+    assert_eq!(zebrad.chain_clock(), ChainClock::advancing(start + 180, 60));
+}
+
+#[cfg(feature = "auto_mine")]
+#[tokio::test]
+async fn auto_mine_interval() {
+    use tokio_stream::StreamExt as _;
+    use zcash_local_net::auto_mine::AutoMine;
+
+    tracing_subscriber::fmt().init();
+
+    let local_net = LocalNet::<Zainod, Zebrad>::launch(
+        ZainodConfig {
+            zainod_bin: ZAINOD_BIN,
+            listen_port: None,
+            validator_port: 0,
+            extra_options: toml::Table::new(),
+            extra_args: Vec::new(),
+        },
+        ZebradConfig::default(),
+    )
+    .await;
+    let start_height = local_net.validator().get_chain_height().await;
+
+    let auto_miner = local_net.auto_mine(AutoMine::Interval(std::time::Duration::from_secs(1)));
+    let heights: Vec<_> = auto_miner.heights().take(3).collect().await;
+    assert_eq!(
+        heights,
+        vec![start_height + 1, start_height + 2, start_height + 3]
+    );
+
+    auto_miner.pause();
+    tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+    let paused_height = auto_miner.validator().get_chain_height().await;
+    tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+    assert_eq!(
+        auto_miner.validator().get_chain_height().await,
+        paused_height
+    );
+
+    let mut heights = auto_miner.heights();
+    auto_miner.resume();
+    assert_eq!(heights.next().await, Some(paused_height + 1));
+
+    let mut local_net = auto_miner.stop().await;
+    let stopped_height = local_net.validator().get_chain_height().await;
+    local_net.validator_mut().generate_blocks(1).await.unwrap();
+    assert_eq!(
+        local_net.validator().get_chain_height().await,
+        stopped_height + 1
+    );
+}
+
+#[cfg(feature = "funding")]
+#[tokio::test]
+async fn zebrad_funding() {
+    use zcash_local_net::{
+        error::FundingError,
+        funding::{self, Funder},
+    };
+
+    tracing_subscriber::fmt().init();
+
+    let zebrad = Zebrad::launch(ZebradConfig {
+        miner_address: funding::FUNDING_ADDRESS,
+        ..ZebradConfig::default()
+    })
+    .await
+    .unwrap();
+    let funder = Funder::new(&network::ActivationHeights::default());
+    assert!(matches!(
+        funder
+            .fund(&zebrad, &[(ZEBRAD_DEFAULT_MINER, 100_000)])
+            .await,
+        Err(FundingError::InsufficientFunds { available: 0, .. })
+    ));
+
+    zebrad
+        .generate_blocks(network::COINBASE_MATURITY + 1)
+        .await
+        .unwrap();
+    let txid = funder
+        .fund(&zebrad, &[(ZEBRAD_DEFAULT_MINER, 100_000)])
+        .await
+        .unwrap();
+    zebrad.generate_blocks(1).await.unwrap();
+
+    let transaction = zebrad
+        .call_rpc("getrawtransaction", vec![txid.into(), serde_json::json!(1)])
+        .await
+        .unwrap();
+    assert!(transaction["height"].as_u64().is_some());
+    let balance = zebrad
+        .call_rpc(
+            "getaddressbalance",
+            vec![serde_json::json!({ "addresses": [ZEBRAD_DEFAULT_MINER] })],
+        )
+        .await
+        .unwrap();
+    assert_eq!(balance["balance"].as_u64(), Some(100_000));
+}
+
+#[tokio::test]
+async fn zebrad_deterministic_scenario() {
+    tracing_subscriber::fmt().init();
+
+    let scenario = Scenario::new().mine(5).with_block_times(1_700_000_000, 75);
+    let mut records = Vec::new();
+    for _ in 0..2 {
+        let local_net = LocalNet::<Zainod, Zebrad>::launch(
+            ZainodConfig {
+                zainod_bin: ZAINOD_BIN,
+                listen_port: None,
+                validator_port: 0,
+                extra_options: toml::Table::new(),
+                extra_args: Vec::new(),
+            },
+            ZebradConfig::default(),
+        )
+        .await;
+        let lightclient_dir = tempfile::tempdir().unwrap();
+        records
+            .push(scenario::run(&local_net, &scenario, lightclient_dir.path().to_path_buf()).await);
+    }
+
+    assert_eq!(records[0].tip_height, 5);
+    assert!(records[0].same_chain(&records[1]));
+}
+
+#[cfg(feature = "funding")]
+#[tokio::test]
+async fn zebrad_seeded_scenario() {
+    use zcash_local_net::{funding, scenario::Actor, scenario::Pool};
+
+    tracing_subscriber::fmt().init();
+
+    let scenario = Scenario::new()
+        .mine(network::COINBASE_MATURITY + 1)
+        .send(
+            Actor::Faucet,
+            Actor::Recipient,
+            Pool::Orchard,
+            100_000,
+            None,
+        )
+        .mine(1)
+        .shield(Actor::Faucet)
+        .mine(1)
+        .with_block_times(1_700_000_000, 75)
+        .with_seed(7);
+    let mut records = Vec::new();
+    for _ in 0..2 {
+        let local_net = LocalNet::<Zainod, Zebrad>::launch(
+            ZainodConfig {
+                zainod_bin: ZAINOD_BIN,
+                listen_port: None,
+                validator_port: 0,
+                extra_options: toml::Table::new(),
+                extra_args: Vec::new(),
+            },
+            ZebradConfig {
+                miner_address: funding::FUNDING_ADDRESS,
+                ..ZebradConfig::default()
+            },
+        )
+        .await;
+        let lightclient_dir = tempfile::tempdir().unwrap();
+        records
+            .push(scenario::run(&local_net, &scenario, lightclient_dir.path().to_path_buf()).await);
+    }
+
+    assert_eq!(records[0].transactions.len(), 2);
+    assert_eq!(records[0].transactions, records[1].transactions);
+    assert!(records[0].same_chain(&records[1]));
+}
+
 #[tokio::test]
 async fn launch_localnet_zainod_zcashd() {
     tracing_subscriber::fmt().init();
@@ -90,14 +613,19 @@ async fn launch_localnet_zainod_zcashd() {
             zainod_bin: ZAINOD_BIN,
             listen_port: None,
             validator_port: 0,
+            extra_options: toml::Table::new(),
+            extra_args: Vec::new(),
         },
         ZcashdConfig {
             zcashd_bin: ZCASHD_BIN,
             zcash_cli_bin: ZCASH_CLI_BIN,
             rpc_port: None,
             activation_heights: network::ActivationHeights::default(),
+            funding_streams: network::FundingStreams::default(),
             miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
             chain_cache: None,
+            extra_options: Vec::new(),
+            extra_args: Vec::new(),
         },
     )
     .await;
@@ -106,6 +634,10 @@ async fn launch_localnet_zainod_zcashd() {
     local_net.validator().print_stderr();
     local_net.indexer().print_stdout();
     local_net.indexer().print_stderr();
+
+    let config = local_net.indexer().read_config().unwrap();
+    assert_eq!(config.listen_port, local_net.indexer().port());
+    assert_eq!(config.zebrad_port, local_net.validator().port());
 }
 
 #[tokio::test]
@@ -117,15 +649,21 @@ async fn launch_localnet_zainod_zebrad() {
             zainod_bin: ZAINOD_BIN,
             listen_port: None,
             validator_port: 0,
+            extra_options: toml::Table::new(),
+            extra_args: Vec::new(),
         },
         ZebradConfig {
             zebrad_bin: ZEBRAD_BIN,
             network_listen_port: None,
             rpc_listen_port: None,
             activation_heights: network::ActivationHeights::default(),
+            funding_streams: network::FundingStreams::default(),
             miner_address: ZEBRAD_DEFAULT_MINER,
             chain_cache: None,
             network: network::Network::Regtest,
+            offline: false,
+            extra_options: toml::Table::new(),
+            extra_args: Vec::new(),
         },
     )
     .await;
@@ -136,6 +674,50 @@ async fn launch_localnet_zainod_zebrad() {
     local_net.indexer().print_stderr();
 }
 
+#[tokio::test]
+async fn launch_localnet_zainod_zebrad_from_file() {
+    tracing_subscriber::fmt().init();
+
+    let config_dir = tempfile::tempdir().unwrap();
+    let config_path = config_dir.path().join("local_net.toml");
+    std::fs::write(
+        &config_path,
+        r#"
+        [validator]
+        kind = "zebrad"
+
+        [validator.extra_options.mempool]
+        tx_cost_limit = 1000
+
+        [indexer]
+        kind = "zainod"
+        "#,
+    )
+    .unwrap();
+
+    let local_net = LocalNet::<Zainod, Zebrad>::launch_from_file(&config_path)
+        .await
+        .unwrap();
+
+    local_net.validator().print_stdout();
+    local_net.validator().print_stderr();
+    local_net.indexer().print_stdout();
+    local_net.indexer().print_stderr();
+
+    let config = local_net.validator().read_config().unwrap();
+    assert_eq!(config.mempool.tx_cost_limit, 1000);
+    assert_eq!(
+        config.mining.miner_address.as_deref(),
+        Some(ZEBRAD_DEFAULT_MINER)
+    );
+    assert!(matches!(
+        LocalNet::<Lightwalletd, Zebrad>::launch_from_file(&config_path).await,
+        Err(LaunchError::InvalidConfig(
+            ConfigError::ProcessMismatch { .. }
+        ))
+    ));
+}
+
 #[tokio::test]
 async fn launch_localnet_lightwalletd_zcashd() {
     tracing_subscriber::fmt().init();
@@ -145,14 +727,19 @@ async fn launch_localnet_lightwalletd_zcashd() {
             lightwalletd_bin: LIGHTWALLETD_BIN,
             listen_port: None,
             zcashd_conf: PathBuf::new(),
+            extra_options: serde_yaml::Mapping::new(),
+            extra_args: Vec::new(),
         },
         ZcashdConfig {
             zcashd_bin: ZCASHD_BIN,
             zcash_cli_bin: ZCASH_CLI_BIN,
             rpc_port: None,
             activation_heights: network::ActivationHeights::default(),
+            funding_streams: network::FundingStreams::default(),
             miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
             chain_cache: None,
+            extra_options: Vec::new(),
+            extra_args: Vec::new(),
         },
     )
     .await;
@@ -162,6 +749,13 @@ async fn launch_localnet_lightwalletd_zcashd() {
     local_net.indexer().print_stdout();
     local_net.indexer().print_lwd_log();
     local_net.indexer().print_stderr();
+
+    let config = local_net.indexer().read_config().unwrap();
+    assert_eq!(
+        config.grpc_bind_addr,
+        format!("127.0.0.1:{}", local_net.indexer().port())
+    );
+    assert_eq!(config.zcash_conf_path, local_net.validator().config_path());
 }
 
 #[tokio::test]
@@ -173,15 +767,21 @@ async fn launch_localnet_lightwalletd_zebrad() {
             lightwalletd_bin: LIGHTWALLETD_BIN,
             listen_port: None,
             zcashd_conf: PathBuf::new(),
+            extra_options: serde_yaml::Mapping::new(),
+            extra_args: Vec::new(),
         },
         ZebradConfig {
             zebrad_bin: ZEBRAD_BIN,
             network_listen_port: None,
             rpc_listen_port: None,
             activation_heights: network::ActivationHeights::default(),
+            funding_streams: network::FundingStreams::default(),
             miner_address: ZEBRAD_DEFAULT_MINER,
             chain_cache: None,
             network: network::Network::Regtest,
+            offline: false,
+            extra_options: toml::Table::new(),
+            extra_args: Vec::new(),
         },
     )
     .await;
@@ -193,6 +793,334 @@ async fn launch_localnet_lightwalletd_zebrad() {
     local_net.indexer().print_stderr();
 }
 
+#[tokio::test]
+async fn zcashd_generate_block_with_transactions() {
+    tracing_subscriber::fmt().init();
+
+    let local_net = LocalNet::<Zainod, Zcashd>::launch(
+        ZainodConfig {
+            zainod_bin: ZAINOD_BIN,
+            listen_port: None,
+            validator_port: 0,
+            extra_options: toml::Table::new(),
+            extra_args: Vec::new(),
+        },
+        ZcashdConfig {
+            zcashd_bin: ZCASHD_BIN,
+            zcash_cli_bin: ZCASH_CLI_BIN,
+            rpc_port: None,
+            activation_heights: network::ActivationHeights::default(),
+            funding_streams: network::FundingStreams::default(),
+            miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
+            chain_cache: None,
+            extra_options: Vec::new(),
+            extra_args: Vec::new(),
+        },
+    )
+    .await;
+
+    let lightclient_dir = tempfile::tempdir().unwrap();
+    let (faucet, recipient) = client::build_lightclients(
+        lightclient_dir.path().to_path_buf(),
+        local_net.indexer().port(),
+    )
+    .await;
+
+    faucet.do_sync(false).await.unwrap();
+    let txids = from_inputs::quick_send(
+        &faucet,
+        vec![(
+            &get_base_address(&recipient, PoolType::Shielded(ShieldedProtocol::Orchard)).await,
+            100_000,
+            None,
+        )],
+    )
+    .await
+    .unwrap();
+    let txid = txids.first().to_string();
+    let raw_transaction = local_net
+        .validator()
+        .call_rpc("getrawtransaction", vec![txid.clone().into()])
+        .await
+        .unwrap()
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    assert!(matches!(
+        local_net
+            .validator()
+            .generate_block_with_transactions(&[], None)
+            .await,
+        Err(GenerateBlockError::UnexpectedMempoolTransactions(unexpected)) if unexpected == vec![txid.clone()]
+    ));
+    assert!(matches!(
+        local_net
+            .validator()
+            .generate_block_with_transactions(&[], Some("00"))
+            .await,
+        Err(GenerateBlockError::Unsupported(_))
+    ));
+
+    let height = local_net
+        .validator()
+        .generate_block_with_transactions(&[raw_transaction], None)
+        .await
+        .unwrap();
+    assert_eq!(height, 2.into());
+    let block = local_net
+        .validator()
+        .call_rpc("getblock", vec!["2".into(), serde_json::json!(1)])
+        .await
+        .unwrap();
+    assert!(block["tx"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|block_txid| block_txid.as_str() == Some(txid.as_str())));
+}
+
+#[tokio::test]
+async fn zcashd_faucet() {
+    tracing_subscriber::fmt().init();
+
+    let zcashd = Zcashd::launch(ZcashdConfig {
+        zcashd_bin: ZCASHD_BIN,
+        zcash_cli_bin: ZCASH_CLI_BIN,
+        rpc_port: None,
+        activation_heights: network::ActivationHeights::default(),
+        funding_streams: network::FundingStreams::default(),
+        miner_address: None,
+        chain_cache: None,
+        extra_options: Vec::new(),
+        extra_args: Vec::new(),
+    })
+    .await
+    .unwrap();
+    zcashd
+        .generate_blocks(network::COINBASE_MATURITY + 1)
+        .await
+        .unwrap();
+
+    let faucet = ZcashdFaucet::new(&zcashd).await.unwrap();
+    faucet.shield_coinbase().await.unwrap();
+    let txid = faucet
+        .send(REG_O_ADDR_FROM_ABANDONART, 100_000)
+        .await
+        .unwrap();
+    zcashd.generate_blocks(1).await.unwrap();
+
+    let transaction = zcashd
+        .call_rpc("getrawtransaction", vec![txid.into(), serde_json::json!(1)])
+        .await
+        .unwrap();
+    assert!(transaction["height"].as_u64().is_some());
+    assert!(matches!(
+        faucet.send(REG_O_ADDR_FROM_ABANDONART, u64::MAX / 2).await,
+        Err(FaucetError::OperationFailed { .. } | FaucetError::Rpc(_))
+    ));
+}
+
+#[tokio::test]
+async fn zcashd_wait_for_transaction() {
+    tracing_subscriber::fmt().init();
+
+    let zcashd = Zcashd::launch(ZcashdConfig {
+        zcashd_bin: ZCASHD_BIN,
+        zcash_cli_bin: ZCASH_CLI_BIN,
+        rpc_port: None,
+        activation_heights: network::ActivationHeights::default(),
+        funding_streams: network::FundingStreams::default(),
+        miner_address: None,
+        chain_cache: None,
+        extra_options: Vec::new(),
+        extra_args: Vec::new(),
+    })
+    .await
+    .unwrap();
+    zcashd
+        .generate_blocks(network::COINBASE_MATURITY + 1)
+        .await
+        .unwrap();
+
+    let faucet = ZcashdFaucet::new(&zcashd).await.unwrap();
+    faucet.shield_coinbase().await.unwrap();
+    let txid = faucet
+        .send(REG_O_ADDR_FROM_ABANDONART, 100_000)
+        .await
+        .unwrap();
+    zcashd.wait_for_mempool(&txid, WAIT_TIMEOUT).await.unwrap();
+
+    zcashd.generate_blocks(1).await.unwrap();
+    let mined_height = zcashd
+        .wait_for_confirmation(&txid, 1, WAIT_TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(mined_height, zcashd.get_chain_height().await);
+
+    zcashd.generate_blocks(2).await.unwrap();
+    assert_eq!(
+        zcashd
+            .wait_for_confirmation(&txid, 3, WAIT_TIMEOUT)
+            .await
+            .unwrap(),
+        mined_height
+    );
+}
+
+#[tokio::test]
+async fn zcashd_shield_coinbase() {
+    tracing_subscriber::fmt().init();
+
+    let zcashd = Zcashd::launch(ZcashdConfig {
+        zcashd_bin: ZCASHD_BIN,
+        zcash_cli_bin: ZCASH_CLI_BIN,
+        rpc_port: None,
+        activation_heights: network::ActivationHeights::default(),
+        funding_streams: network::FundingStreams::default(),
+        miner_address: None,
+        chain_cache: None,
+        extra_options: Vec::new(),
+        extra_args: Vec::new(),
+    })
+    .await
+    .unwrap();
+    let coinbase_height = zcashd.mine_until_spendable().await.unwrap();
+    assert_eq!(
+        u32::from(zcashd.get_chain_height().await),
+        u32::from(coinbase_height) + network::COINBASE_MATURITY
+    );
+
+    let faucet = ZcashdFaucet::new(&zcashd).await.unwrap();
+    let txid = zcashd.shield_coinbase(faucet.address()).await.unwrap();
+    zcashd.generate_blocks(1).await.unwrap();
+
+    let transaction = zcashd
+        .call_rpc("getrawtransaction", vec![txid.into(), serde_json::json!(1)])
+        .await
+        .unwrap();
+    assert!(transaction["height"].as_u64().is_some());
+    let spent_txids: Vec<_> = transaction["vin"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|input| input["txid"].as_str().unwrap().to_string())
+        .collect();
+    let coinbase_txid = |height: u32| {
+        let zcashd = &zcashd;
+        async move {
+            let block = zcashd
+                .call_rpc(
+                    "getblock",
+                    vec![height.to_string().into(), serde_json::json!(1)],
+                )
+                .await
+                .unwrap();
+            block["tx"][0].as_str().unwrap().to_string()
+        }
+    };
+    // the coinbase at the returned height is mature and the coinbase of the next block is not
+    assert!(spent_txids.contains(&coinbase_txid(u32::from(coinbase_height)).await));
+    assert!(!spent_txids.contains(&coinbase_txid(u32::from(coinbase_height) + 1).await));
+}
+
+#[tokio::test]
+async fn zebrad_shield_coinbase_unsupported() {
+    tracing_subscriber::fmt().init();
+
+    let zebrad = Zebrad::launch(ZebradConfig::default()).await.unwrap();
+    let coinbase_height = zebrad.mine_until_spendable().await.unwrap();
+    assert_eq!(
+        u32::from(zebrad.get_chain_height().await),
+        u32::from(coinbase_height) + network::COINBASE_MATURITY - 1
+    );
+    assert!(matches!(
+        zebrad.shield_coinbase(REG_O_ADDR_FROM_ABANDONART).await,
+        Err(CoinbaseError::Unsupported(_))
+    ));
+}
+
+#[cfg(feature = "funding")]
+#[tokio::test]
+async fn zebrad_shield_coinbase() {
+    use zcash_local_net::funding;
+
+    tracing_subscriber::fmt().init();
+
+    let zebrad = Zebrad::launch(ZebradConfig {
+        miner_address: funding::FUNDING_ADDRESS,
+        ..ZebradConfig::default()
+    })
+    .await
+    .unwrap();
+    zebrad.mine_until_spendable().await.unwrap();
+    let txid = zebrad
+        .shield_coinbase(REG_O_ADDR_FROM_ABANDONART)
+        .await
+        .unwrap();
+    zebrad.generate_blocks(1).await.unwrap();
+
+    let transaction = zebrad
+        .call_rpc("getrawtransaction", vec![txid.into(), serde_json::json!(1)])
+        .await
+        .unwrap();
+    assert!(transaction["height"].as_u64().is_some());
+}
+
+/// Asserts the endpoints of any indexer attached to a validator listening on `validator_port`.
+async fn assert_indexer_endpoints<I: Indexer + Sync>(indexer: &I, validator_port: u16) {
+    assert_eq!(indexer.validator_port(), validator_port);
+    assert_eq!(indexer.uri(), network::localhost_uri(indexer.port()));
+    assert_eq!(
+        indexer.validator_uri(),
+        network::localhost_uri(validator_port)
+    );
+    assert_eq!(indexer.network().await.unwrap(), network::Network::Regtest);
+    let mut client = indexer.client().await.unwrap();
+    client
+        .get_latest_block(zcash_client_backend::proto::service::ChainSpec {})
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn indexer_endpoints() {
+    tracing_subscriber::fmt().init();
+
+    let zcashd = Zcashd::launch(ZcashdConfig {
+        zcashd_bin: ZCASHD_BIN,
+        zcash_cli_bin: ZCASH_CLI_BIN,
+        rpc_port: None,
+        activation_heights: network::ActivationHeights::default(),
+        funding_streams: network::FundingStreams::default(),
+        miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
+        chain_cache: None,
+        extra_options: Vec::new(),
+        extra_args: Vec::new(),
+    })
+    .await
+    .unwrap();
+    let zainod = Zainod::launch(ZainodConfig {
+        zainod_bin: ZAINOD_BIN,
+        listen_port: None,
+        validator_port: zcashd.port(),
+        extra_options: toml::Table::new(),
+        extra_args: Vec::new(),
+    })
+    .unwrap();
+    let lightwalletd = Lightwalletd::launch(LightwalletdConfig {
+        lightwalletd_bin: LIGHTWALLETD_BIN,
+        listen_port: None,
+        zcashd_conf: zcashd.config_path(),
+        extra_options: serde_yaml::Mapping::new(),
+        extra_args: Vec::new(),
+    })
+    .unwrap();
+
+    assert_indexer_endpoints(&zainod, zcashd.port()).await;
+    assert_indexer_endpoints(&lightwalletd, zcashd.port()).await;
+}
+
 #[tokio::test]
 async fn zainod_basic_send() {
     tracing_subscriber::fmt().init();
@@ -202,14 +1130,19 @@ async fn zainod_basic_send() {
             zainod_bin: ZAINOD_BIN,
             listen_port: None,
             validator_port: 0,
+            extra_options: toml::Table::new(),
+            extra_args: Vec::new(),
         },
         ZcashdConfig {
             zcashd_bin: ZCASHD_BIN,
             zcash_cli_bin: ZCASH_CLI_BIN,
             rpc_port: None,
             activation_heights: network::ActivationHeights::default(),
+            funding_streams: network::FundingStreams::default(),
             miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
             chain_cache: None,
+            extra_options: Vec::new(),
+            extra_args: Vec::new(),
         },
     )
     .await;
@@ -233,6 +1166,7 @@ async fn zainod_basic_send() {
     .await
     .unwrap();
     local_net.validator().generate_blocks(1).await.unwrap();
+    local_net.sync_all(WAIT_TIMEOUT).await.unwrap();
     faucet.do_sync(false).await.unwrap();
     recipient.do_sync(false).await.unwrap();
 
@@ -258,14 +1192,19 @@ async fn lightwalletd_basic_send() {
             lightwalletd_bin: LIGHTWALLETD_BIN,
             listen_port: None,
             zcashd_conf: PathBuf::new(),
+            extra_options: serde_yaml::Mapping::new(),
+            extra_args: Vec::new(),
         },
         ZcashdConfig {
             zcashd_bin: ZCASHD_BIN,
             zcash_cli_bin: ZCASH_CLI_BIN,
             rpc_port: None,
             activation_heights: network::ActivationHeights::default(),
+            funding_streams: network::FundingStreams::default(),
             miner_address: Some(REG_O_ADDR_FROM_ABANDONART),
             chain_cache: None,
+            extra_options: Vec::new(),
+            extra_args: Vec::new(),
         },
     )
     .await;
@@ -289,6 +1228,7 @@ async fn lightwalletd_basic_send() {
     .await
     .unwrap();
     local_net.validator().generate_blocks(1).await.unwrap();
+    local_net.sync_all(WAIT_TIMEOUT).await.unwrap();
     faucet.do_sync(false).await.unwrap();
     recipient.do_sync(false).await.unwrap();
 
@@ -793,3 +1733,87 @@ mod client_rpcs {
         .await;
     }
 }
+
+#[cfg(feature = "funding")]
+#[tokio::test]
+async fn zebrad_generate_block_with_transactions() {
+    use std::str::FromStr as _;
+
+    use zcash_local_net::funding::{self, Funder};
+    use zebra_chain::{
+        serialization::{ZcashDeserializeInto as _, ZcashSerialize as _},
+        transaction::Transaction,
+        transparent,
+    };
+
+    tracing_subscriber::fmt().init();
+
+    let zebrad = Zebrad::launch(ZebradConfig {
+        miner_address: funding::FUNDING_ADDRESS,
+        ..ZebradConfig::default()
+    })
+    .await
+    .unwrap();
+    zebrad
+        .generate_blocks(network::COINBASE_MATURITY + 1)
+        .await
+        .unwrap();
+
+    let funder = Funder::new(&network::ActivationHeights::default());
+    let raw_transaction = funder
+        .build(&zebrad, &[(ZEBRAD_DEFAULT_MINER, 100_000)])
+        .await
+        .unwrap();
+    let transaction: Transaction = hex::decode(&raw_transaction)
+        .unwrap()
+        .zcash_deserialize_into()
+        .unwrap();
+    let mempool = zebrad.call_rpc("getrawmempool", vec![]).await.unwrap();
+    assert_eq!(mempool.as_array().map(Vec::len), Some(0));
+
+    // the template coinbase pays one zatoshi less to the miner
+    let block_template = zebrad.call_rpc("getblocktemplate", vec![]).await.unwrap();
+    let mut coinbase: Transaction =
+        hex::decode(block_template["coinbasetxn"]["data"].as_str().unwrap())
+            .unwrap()
+            .zcash_deserialize_into()
+            .unwrap();
+    let miner_script = transparent::Address::from_str(funding::FUNDING_ADDRESS)
+        .unwrap()
+        .create_script_from_address();
+    let outputs = match &mut coinbase {
+        Transaction::V4 { outputs, .. } | Transaction::V5 { outputs, .. } => outputs,
+        _ => panic!("block template coinbase should be a v4 or v5 transaction"),
+    };
+    let miner_output = outputs
+        .iter_mut()
+        .find(|output| output.lock_script == miner_script)
+        .unwrap();
+    miner_output.value = (u64::from(miner_output.value) - 1).try_into().unwrap();
+    let raw_coinbase = hex::encode(coinbase.zcash_serialize_to_vec().unwrap());
+
+    let chain_height = zebrad.get_chain_height().await;
+    let height = zebrad
+        .generate_block_with_transactions(
+            std::slice::from_ref(&raw_transaction),
+            Some(&raw_coinbase),
+        )
+        .await
+        .unwrap();
+    assert_eq!(height, chain_height + 1);
+    let block = zebrad
+        .call_rpc(
+            "getblock",
+            vec![u32::from(height).to_string().into(), serde_json::json!(1)],
+        )
+        .await
+        .unwrap();
+    let block_txids: Vec<&str> = block["tx"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|txid| txid.as_str().unwrap())
+        .collect();
+    assert_eq!(block_txids[0], coinbase.hash().to_string());
+    assert!(block_txids.contains(&transaction.hash().to_string().as_str()));
+}